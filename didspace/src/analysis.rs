@@ -1,47 +1,135 @@
-use wasmparser::{Parser, Payload};
-use std::collections::HashMap;
+use wasmparser::{ExternalKind, KnownCustom, Name, Parser, Payload, TypeRef};
+use std::collections::{BTreeMap, HashMap};
 use anyhow::Result;
+use serde::Serialize;
 
+use crate::report::{Measure, Section};
+
+/// Functions above this many instructions are reported as hotspots.
+const HOTSPOT_INSTRUCTIONS: usize = 500;
+
+#[derive(Debug, Serialize)]
 pub struct WasmAnalysis {
     pub section_sizes: HashMap<String, usize>,
     pub function_count: usize,
     pub imports: usize,
     pub exports: usize,
+    /// Operator name as wasmparser's `Debug` spells it (`LocalGet`, `I32Const`, ...),
+    /// not the text-format mnemonic (`local.get`) → count
     pub instruction_freq: HashMap<String, usize>,
+    /// Defined functions in index order
+    pub functions: Vec<FunctionInfo>,
+    pub import_list: Vec<ImportInfo>,
+    pub export_list: Vec<ExportInfo>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FunctionInfo {
+    pub index: u32,
+    /// From the name section
+    pub name: Option<String>,
+    /// Body size in bytes
+    pub size: usize,
+    pub instructions: usize,
+    pub offset: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportInfo {
+    pub module: String,
+    pub name: String,
+    pub kind: String, // "func" | "table" | "memory" | "global" | "tag"
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportInfo {
+    pub name: String,
+    pub kind: String, // "func" | "table" | "memory" | "global" | "tag"
+    pub index: u32,
+}
+
+impl FunctionInfo {
+    pub fn label(&self) -> String {
+        self.name.clone().unwrap_or_else(|| format!("func[{}]", self.index))
+    }
 }
 
 impl WasmAnalysis {
     pub fn analyze(bytes: &[u8]) -> Result<Self> {
         let mut section_sizes = HashMap::new();
         let mut function_count = 0;
-        let mut imports = 0;
-        let mut exports = 0;
         let mut instruction_freq = HashMap::new();
+        let mut functions = Vec::new();
+        let mut import_list = Vec::new();
+        let mut export_list = Vec::new();
+        let mut imported_funcs = 0u32;
+        let mut names = BTreeMap::new();
 
         let parser = Parser::new(0);
 
         for payload in parser.parse_all(bytes) {
-            match payload.unwrap() {
-                Payload::Version { .. } => {}
-                Payload::TypeSection(types) => {
-                    section_sizes.insert("Type".into(), types.range().end - types.range().start);
-                }
-                Payload::ImportSection(imports_section) => {
-                    imports += imports_section.count();
-                    section_sizes.insert("Import".into(), imports_section.range().end - imports_section.range().start);
+            let payload = payload?;
+            if let Some((id, range)) = payload.as_section() {
+                *section_sizes.entry(section_name(id).to_string()).or_insert(0) += range.end - range.start;
+            }
+            match payload {
+                Payload::ImportSection(reader) => {
+                    for im in reader {
+                        let im = im?;
+                        let kind = match im.ty {
+                            TypeRef::Func(_) | TypeRef::FuncExact(_) => {
+                                imported_funcs += 1;
+                                "func"
+                            }
+                            TypeRef::Table(_) => "table",
+                            TypeRef::Memory(_) => "memory",
+                            TypeRef::Global(_) => "global",
+                            TypeRef::Tag(_) => "tag",
+                        };
+                        import_list.push(ImportInfo {
+                            module: im.module.to_string(),
+                            name: im.name.to_string(),
+                            kind: kind.to_string(),
+                        });
+                    }
                 }
                 Payload::FunctionSection(funcs) => {
                     function_count += funcs.count();
-                    section_sizes.insert("Function".into(), funcs.range().end - funcs.range().start);
                 }
-                Payload::ExportSection(exports_section) => {
-                    exports += exports_section.count();
-                    section_sizes.insert("Export".into(), exports_section.range().end - exports_section.range().start);
+                Payload::ExportSection(reader) => {
+                    for ex in reader {
+                        let ex = ex?;
+                        export_list.push(ExportInfo {
+                            name: ex.name.to_string(),
+                            kind: kind_name(ex.kind).to_string(),
+                            index: ex.index,
+                        });
+                    }
                 }
                 Payload::CodeSectionEntry(code) => {
-                    for op in code.get_operators_reader().unwrap() {
-                        let op_name = format!("{:?}", op.unwrap());
-                        *instruction_freq.entry(op_name).or_insert(0) += 1;
+                    let mut count = 0;
+                    for op in code.get_operators_reader()? {
+                        *instruction_freq.entry(operator_name(&op?)).or_insert(0) += 1;
+                        count += 1;
+                    }
+                    let range = code.range();
+                    functions.push(FunctionInfo {
+                        index: imported_funcs + functions.len() as u32,
+                        name: None,
+                        size: range.end - range.start,
+                        instructions: count,
+                        offset: range.start,
+                    });
+                }
+                Payload::CustomSection(c) => {
+                    if let KnownCustom::Name(reader) = c.as_known() {
+                        for sub in reader.into_iter().flatten() {
+                            if let Name::Function(map) = sub {
+                                for naming in map.into_iter().flatten() {
+                                    names.insert(naming.index, naming.name.to_string());
+                                }
+                            }
+                        }
                     }
                 }
                 Payload::End(_) => break,
@@ -49,57 +137,29 @@ impl WasmAnalysis {
             }
         }
 
+        // the name section comes after the code
+        for f in &mut functions {
+            f.name = names.remove(&f.index);
+        }
+
         Ok(Self {
             section_sizes,
             function_count: function_count as usize,
-            imports: imports as usize,
-            exports: exports as usize,
+            imports: import_list.len(),
+            exports: export_list.len(),
             instruction_freq,
+            functions,
+            import_list,
+            export_list,
         })
     }
 
-    pub fn report(&self) -> String {
-        let mut out = String::new();
-        out.push_str("=== WASM Analysis Report ===\n\n");
-
-        out.push_str("Sections:\n");
-        for (section, size) in &self.section_sizes {
-            out.push_str(&format!("  - {}: {} bytes\n", section, size));
-        }
-
-        out.push_str(&format!(
-            "\nFunctions: {}\nImports: {}\nExports: {}\n",
-            self.function_count, self.imports, self.exports
-        ));
-
-        out.push_str("\nInstruction Frequency:\n");
-        for (instr, count) in &self.instruction_freq {
-            out.push_str(&format!("  {}: {}\n", instr, count));
-        }
-
-        // ✅ Optimization hints
-        out.push_str("\nOptimization Hints:\n");
-        if let Some(custom_size) = self.section_sizes.get("Custom") {
-            if *custom_size > 500 {
-                out.push_str("  - Large custom section detected. Consider stripping debug info.\n");
-            }
-        }
-        if self.function_count > 100 {
-            out.push_str("  - High function count. Consider inlining or reducing complexity.\n");
-        }
-        if self.instruction_freq.get("Call").unwrap_or(&0) > &50 {
-            out.push_str("  - Many calls detected. Consider reducing call overhead.\n");
-        }
-
-        out
-    }
-
-    pub fn analyze_sizes(bytes: &[u8]) -> HashMap<String, usize> {
+    pub fn analyze_sizes(bytes: &[u8]) -> Result<HashMap<String, usize>> {
         let mut sizes = HashMap::new();
         let parser = Parser::new(0);
 
         for payload in parser.parse_all(bytes) {
-            match payload.unwrap() {
+            match payload? {
                 Payload::TypeSection(s) => {
                     sizes.insert("Type".into(), s.range().end - s.range().start);
                 }
@@ -113,78 +173,137 @@ impl WasmAnalysis {
                     *sizes.entry("Code".into()).or_insert(0) += 1;
                 }
                 _ => {}
-                       }
+            }
         }
-        sizes
+        Ok(sizes)
     }
 
-    pub fn to_report_string(&self) -> String {
-        let mut out = String::new();
+    pub fn to_sections(&self) -> Vec<Section> {
+        let mut sizes: Vec<Measure> = self.section_sizes.iter().map(|(k, v)| Measure::new(k.clone(), *v)).collect();
+        sizes.sort_by(|a, b| b.value.cmp(&a.value).then_with(|| a.label.cmp(&b.label)));
 
-        out.push_str("WASM Analysis\n");
-        out.push_str("================\n\n");
+        let counts = Section::new("Counts")
+            .kv("functions", self.function_count)
+            .kv("imports", self.imports)
+            .kv("exports", self.exports);
 
-        out.push_str("Sections (bytes):\n");
-        // ordinati per nome (o fai per size se preferisci)
-        let mut sections: Vec<_> = self.section_sizes.iter().collect();
-        sections.sort_by_key(|(k, _)| *k);
-        for (name, size) in sections {
-            out.push_str(&format!("  - {:<10} {}\n", name, size));
-        }
+        let funcs = self.functions.iter().map(|f| Measure::new(f.label(), f.size)).collect();
 
-        out.push_str("\nCounts:\n");
-        out.push_str(&format!("  • functions: {}\n", self.function_count));
-        out.push_str(&format!("  • imports:   {}\n", self.imports));
-        out.push_str(&format!("  • exports:   {}\n", self.exports));
+        let imports = self
+            .import_list
+            .iter()
+            .map(|i| vec![i.module.clone(), i.name.clone(), i.kind.clone()])
+            .collect();
+        let exports = self
+            .export_list
+            .iter()
+            .map(|e| vec![e.name.clone(), e.kind.clone(), e.index.to_string()])
+            .collect();
 
-        out.push_str("\nTop instructions:\n");
         let mut instr: Vec<_> = self.instruction_freq.iter().collect();
-        instr.sort_by_key(|(_, v)| std::cmp::Reverse(**v));
-        for (name, count) in instr.into_iter().take(30) {
-            out.push_str(&format!("  - {:<40} {}\n", name, count));
+        instr.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        let top = instr.into_iter().take(30).map(|(name, count)| Measure::new(name.clone(), *count)).collect();
+
+        let mut hints = Section::new("Optimization hints");
+        if self.section_sizes.get("Custom").is_some_and(|s| *s > 500) {
+            hints = hints.finding("warn", "Large custom section detected. Consider stripping debug info.");
+        }
+        if self.function_count > 100 {
+            hints = hints.finding("warn", "High function count. Consider inlining or reducing complexity.");
+        }
+        if self.instruction_freq.get("Call").unwrap_or(&0) > &50 {
+            hints = hints.finding("warn", "Many calls detected. Consider reducing call overhead.");
+        }
+        if hints.blocks.is_empty() {
+            hints = hints.finding("ok", "nothing to suggest");
         }
 
-        out
+        vec![
+            Section::new("Sections").stacked("bytes", sizes),
+            counts,
+            Section::new("Functions by size").treemap("bytes", funcs),
+            Section::new("Imports").table(&["module", "name", "kind"], imports),
+            Section::new("Exports").table(&["name", "kind", "index"], exports),
+            Section::new("Top instructions").histogram("count", top),
+            hints,
+        ]
     }
 
-    pub fn analyze_report(bytes: &[u8]) -> Result<String> {
-        let a = Self::analyze(bytes)?;
-        Ok(a.to_report_string())
+    /// Functions ranked by instruction count, with hotspots flagged; `profile`
+    /// shows these after the `to_sections` overview.
+    pub fn profile_sections(&self) -> Vec<Section> {
+        let mut by_count: Vec<&FunctionInfo> = self.functions.iter().collect();
+        by_count.sort_by(|x, y| y.instructions.cmp(&x.instructions).then(x.index.cmp(&y.index)));
+
+        let map = by_count.iter().map(|f| Measure::new(f.label(), f.instructions)).collect();
+        let rows = by_count
+            .iter()
+            .map(|f| {
+                vec![
+                    f.index.to_string(),
+                    f.label(),
+                    f.offset.to_string(),
+                    f.size.to_string(),
+                    f.instructions.to_string(),
+                ]
+            })
+            .collect();
+
+        let mut hot = Section::new("Hotspots");
+        for f in by_count.iter().filter(|f| f.instructions > HOTSPOT_INSTRUCTIONS) {
+            hot = hot.finding(
+                "warn",
+                format!("{} (offset {}): {} instructions; consider optimizing it", f.label(), f.offset, f.instructions),
+            );
+        }
+        if hot.blocks.is_empty() {
+            hot = hot.finding("ok", format!("no function above {} instructions", HOTSPOT_INSTRUCTIONS));
+        }
+        vec![
+            Section::new("Instructions per function").treemap("instructions", map),
+            Section::new("Functions").table(&["index", "name", "offset", "bytes", "instructions"], rows),
+            hot,
+        ]
     }
+}
 
-    pub fn profile_functions(bytes: &[u8]) -> Result<String> {
-        let parser = Parser::new(0);
-        let mut out = String::new();
-        out.push_str("Profile Functions\n");
-        out.push_str("=================\n");
+// ---------- helpers ----------
 
-        for payload in parser.parse_all(bytes) {
-            match payload? {
-                Payload::CodeSectionEntry(code) => {
-                    let mut count = 0;
-                    //for op in code.get_operators_reader().unwrap() {
-                    let mut r = code.get_operators_reader()?;
-                    while !r.eof() {
-                        let _op = r.read()?; // non serve usarlo, basta contare
-                        count += 1;
-                    }
+fn section_name(id: u8) -> &'static str {
+    match id {
+        0 => "Custom",
+        1 => "Type",
+        2 => "Import",
+        3 => "Function",
+        4 => "Table",
+        5 => "Memory",
+        6 => "Global",
+        7 => "Export",
+        8 => "Start",
+        9 => "Element",
+        10 => "Code",
+        11 => "Data",
+        12 => "DataCount",
+        13 => "Tag",
+        _ => "Other",
+    }
+}
 
-                    out.push_str(&format!(
-                        "Function at offset {}: {} instructions\n",
-                        code.range().start,
-                        count
-                    ));
+fn kind_name(kind: ExternalKind) -> &'static str {
+    match kind {
+        ExternalKind::Func | ExternalKind::FuncExact => "func",
+        ExternalKind::Table => "table",
+        ExternalKind::Memory => "memory",
+        ExternalKind::Global => "global",
+        ExternalKind::Tag => "tag",
+    }
+}
 
-                    println!("Function at offset {}: {} instructions", code.range().start, count);
-                    if count > 500 {
-                        out.push_str("  ⚠ Hotspot detected: Consider optimizing this function.");
-                        println!("  ⚠ Hotspot detected: Consider optimizing this function.");
-                    }
-                }
-                Payload::End(_) => break,
-                _ => {}
-            }
-        }
-        Ok(out)
+/// `I32Const { value: 1 }` → `I32Const`: count opcodes, not immediates.
+fn operator_name(op: &wasmparser::Operator<'_>) -> String {
+    let s = format!("{:?}", op);
+    match s.find([' ', '{', '(']) {
+        Some(i) => s[..i].to_string(),
+        None => s,
     }
 }
@@ -0,0 +1,463 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+use rayon::prelude::*;
+use serde::Serialize;
+use wasmparser::{Parser, Payload};
+
+use crate::doctor::{doctor_report, DoctorOptions};
+use crate::lint::lint_module;
+use crate::report::{Report, Section};
+
+pub struct BatchOptions<'a> {
+    pub command: &'a str, // "analyze" | "doctor"
+    /// Files, directories and glob patterns (`*`, `?`, `**`)
+    pub inputs: &'a [String],
+    pub recursive: bool,
+    /// Worker threads (default: one per core)
+    pub jobs: Option<usize>,
+    pub wasi_sysroot: Option<&'a str>,
+    pub compat_table: Option<&'a str>,
+    pub expect_eh: Option<&'a str>,
+    pub lint_levels: &'a BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchRow {
+    pub file: String,
+    pub status: String, // "ok" | "error"
+    pub size: usize,
+    pub kind: String, // "core module" | "component" | "" on error
+    pub wasi: String,
+    pub functions: usize,
+    pub code_bytes: usize,
+    pub data_bytes: usize,
+    pub imports: usize,
+    pub exports: usize,
+    pub eh: String, // "none" | "maybe" | "likely"
+    /// Runtimes from the compat table that run the module as-is, `;`-separated
+    pub runs_on: String,
+    pub lint_warnings: usize,
+    pub lint_errors: usize,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct BatchTotals {
+    pub files: usize,
+    pub ok: usize,
+    pub failed: usize,
+    pub bytes: usize,
+    pub code_bytes: usize,
+    pub data_bytes: usize,
+    pub by_kind: BTreeMap<String, usize>,
+    pub by_wasi: BTreeMap<String, usize>,
+    pub lint_warnings: usize,
+    pub lint_errors: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchReport {
+    pub command: String,
+    pub threads: usize,
+    pub duration_ms: u128,
+    pub rows: Vec<BatchRow>,
+    pub totals: BatchTotals,
+}
+
+/// More than one input, a directory or a pattern means batch mode.
+pub fn is_batch(inputs: &[String], recursive: bool, format: Option<&str>) -> bool {
+    inputs.len() > 1
+        || recursive
+        || format.is_some()
+        || inputs.iter().any(|i| is_pattern(i) || Path::new(i).is_dir())
+}
+
+pub fn run_batch(opts: BatchOptions<'_>) -> Result<BatchReport> {
+    let mut failures = Vec::new();
+    let files = expand_inputs(opts.inputs, opts.recursive, &mut failures);
+    if files.is_empty() && failures.is_empty() {
+        return Err(anyhow!("No .wasm files matched {}", opts.inputs.join(" ")));
+    }
+
+    let mut builder = rayon::ThreadPoolBuilder::new();
+    if let Some(n) = opts.jobs {
+        builder = builder.num_threads(n);
+    }
+    let pool = builder.build()?;
+    let started = Instant::now();
+    let mut rows: Vec<BatchRow> = pool.install(|| files.par_iter().map(|f| row_for(f, &opts)).collect());
+    // inputs that could not be listed fail on their own row; the rest still run
+    rows.extend(failures.into_iter().map(|(file, err)| error_row(file, 0, err)));
+
+    let mut totals = BatchTotals { files: rows.len(), ..Default::default() };
+    for r in &rows {
+        if r.status != "ok" {
+            totals.failed += 1;
+            continue;
+        }
+        totals.ok += 1;
+        totals.bytes += r.size;
+        totals.code_bytes += r.code_bytes;
+        totals.data_bytes += r.data_bytes;
+        *totals.by_kind.entry(r.kind.clone()).or_insert(0) += 1;
+        *totals.by_wasi.entry(r.wasi.clone()).or_insert(0) += 1;
+        totals.lint_warnings += r.lint_warnings;
+        totals.lint_errors += r.lint_errors;
+    }
+
+    Ok(BatchReport {
+        command: opts.command.to_string(),
+        threads: pool.current_num_threads(),
+        duration_ms: started.elapsed().as_millis(),
+        rows,
+        totals,
+    })
+}
+
+impl BatchReport {
+    pub fn to_report(&self) -> Report {
+        let head = Section::untitled()
+            .kv("files", self.rows.len())
+            .kv("threads", self.threads)
+            .kv("elapsed", format!("{} ms", self.duration_ms));
+
+        let mut rows = Vec::new();
+        let mut errors = Section::new("Errors");
+        for r in &self.rows {
+            if let Some(err) = &r.error {
+                errors = errors.finding("error", format!("{}: {}", r.file, err));
+                continue;
+            }
+            let lint = format!("{}w/{}e", r.lint_warnings, r.lint_errors);
+            if self.command == "doctor" {
+                rows.push(vec![
+                    r.file.clone(),
+                    r.kind.clone(),
+                    r.wasi.clone(),
+                    r.eh.clone(),
+                    r.imports.to_string(),
+                    r.exports.to_string(),
+                    lint,
+                    if r.runs_on.is_empty() { "-".to_string() } else { r.runs_on.clone() },
+                ]);
+            } else {
+                rows.push(vec![
+                    r.file.clone(),
+                    r.size.to_string(),
+                    r.functions.to_string(),
+                    r.code_bytes.to_string(),
+                    r.data_bytes.to_string(),
+                    r.imports.to_string(),
+                    r.exports.to_string(),
+                    r.wasi.clone(),
+                    lint,
+                ]);
+            }
+        }
+        let columns: &[&str] = if self.command == "doctor" {
+            &["file", "kind", "wasi", "eh", "imp", "exp", "lint", "runs on"]
+        } else {
+            &["file", "size", "funcs", "code", "data", "imp", "exp", "wasi", "lint"]
+        };
+
+        let t = &self.totals;
+        let counts = |m: &BTreeMap<String, usize>| {
+            m.iter().map(|(k, n)| format!("{} ×{}", k, n)).collect::<Vec<_>>().join(", ")
+        };
+        let totals = Section::new("Totals")
+            .kv("files", format!("{} ({} ok, {} failed)", t.files, t.ok, t.failed))
+            .kv("size", format!("{} bytes (code {}, data {})", t.bytes, t.code_bytes, t.data_bytes))
+            .kv("kind", counts(&t.by_kind))
+            .kv("wasi", counts(&t.by_wasi))
+            .kv("lint", format!("{} warning(s), {} error(s)", t.lint_warnings, t.lint_errors));
+
+        let mut r = Report::new(format!("Batch {}", self.command))
+            .section(head)
+            .section(Section::new("Modules").table(columns, rows));
+        if t.failed > 0 {
+            r.push(errors);
+        }
+        // a file that failed or has lint errors fails the batch, so CI sees it in the exit status
+        r.ok = t.failed == 0 && t.lint_errors == 0;
+        r.section(totals)
+    }
+
+    /// One row per file plus a final `TOTAL` row.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        out.push_str(
+            "file,status,size,kind,wasi,functions,code_bytes,data_bytes,imports,exports,eh,runs_on,lint_warnings,lint_errors,error\n",
+        );
+        for r in &self.rows {
+            let fields = [
+                r.file.clone(),
+                r.status.clone(),
+                r.size.to_string(),
+                r.kind.clone(),
+                r.wasi.clone(),
+                r.functions.to_string(),
+                r.code_bytes.to_string(),
+                r.data_bytes.to_string(),
+                r.imports.to_string(),
+                r.exports.to_string(),
+                r.eh.clone(),
+                r.runs_on.clone(),
+                r.lint_warnings.to_string(),
+                r.lint_errors.to_string(),
+                r.error.clone().unwrap_or_default(),
+            ];
+            out.push_str(&fields.iter().map(|f| csv_field(f)).collect::<Vec<_>>().join(","));
+            out.push('\n');
+        }
+        let t = &self.totals;
+        out.push_str(&format!(
+            "TOTAL,{}/{} ok,{},,,,{},{},,,,,{},{},\n",
+            t.ok, t.files, t.bytes, t.code_bytes, t.data_bytes, t.lint_warnings, t.lint_errors
+        ));
+        out
+    }
+}
+
+// ---------- helpers ----------
+
+fn row_for(path: &Path, opts: &BatchOptions<'_>) -> BatchRow {
+    let file = path.display().to_string();
+    match inspect(path, &file, opts) {
+        Ok(row) => row,
+        Err(e) => {
+            let size = fs::metadata(path).map(|m| m.len() as usize).unwrap_or(0);
+            error_row(file, size, e.to_string())
+        }
+    }
+}
+
+fn error_row(file: String, size: usize, error: String) -> BatchRow {
+    BatchRow {
+        file,
+        status: "error".to_string(),
+        size,
+        kind: String::new(),
+        wasi: String::new(),
+        functions: 0,
+        code_bytes: 0,
+        data_bytes: 0,
+        imports: 0,
+        exports: 0,
+        eh: String::new(),
+        runs_on: String::new(),
+        lint_warnings: 0,
+        lint_errors: 0,
+        error: Some(error.split_whitespace().collect::<Vec<_>>().join(" ")),
+    }
+}
+
+fn inspect(path: &Path, file: &str, opts: &BatchOptions<'_>) -> Result<BatchRow> {
+    let bytes = fs::read(path)?;
+    let rep = doctor_report(
+        &bytes,
+        DoctorOptions {
+            wasi_sysroot: opts.wasi_sysroot,
+            max_list: 0,
+            compat_table: opts.compat_table,
+            expect_eh: opts.expect_eh,
+            module_path: Some(file),
+        },
+    )?;
+    let (functions, code_bytes, data_bytes) = module_stats(&bytes)?;
+    let (imports, exports) = match (&rep.core, &rep.component) {
+        (Some(c), _) => (c.imports_count, c.exports_count),
+        (None, Some(c)) => (c.imports_count, c.exports_count),
+        _ => (0, 0),
+    };
+    let lint = lint_module(&bytes, &rep.kind, opts.lint_levels)?;
+    let runs_on: Vec<String> = rep
+        .compat
+        .runtimes
+        .iter()
+        .filter(|r| r.verdict == "yes")
+        .map(|r| r.runtime.clone())
+        .collect();
+
+    Ok(BatchRow {
+        file: file.to_string(),
+        status: "ok".to_string(),
+        size: bytes.len(),
+        wasi: if rep.wasi.detected { rep.wasi.flavor.clone() } else { "none".to_string() },
+        kind: rep.kind,
+        functions,
+        code_bytes,
+        data_bytes,
+        imports,
+        exports,
+        eh: rep.heuristics.cxx_eh.level,
+        runs_on: runs_on.join(";"),
+        lint_warnings: lint.findings.iter().filter(|f| f.level == "warn").count(),
+        lint_errors: lint.findings.iter().filter(|f| f.level == "error").count(),
+        error: None,
+    })
+}
+
+/// Defined functions and code/data section sizes, counted across nested modules.
+fn module_stats(bytes: &[u8]) -> Result<(usize, usize, usize)> {
+    let mut functions = 0;
+    let mut code = 0;
+    let mut data = 0;
+    for payload in Parser::new(0).parse_all(bytes) {
+        match payload? {
+            Payload::FunctionSection(r) => functions += r.count() as usize,
+            Payload::CodeSectionStart { size, .. } => code += size as usize,
+            Payload::DataSection(r) => data += r.range().len(),
+            _ => {}
+        }
+    }
+    Ok((functions, code, data))
+}
+
+fn csv_field(f: &str) -> String {
+    if f.contains([',', '"', '\n']) {
+        format!("\"{}\"", f.replace('"', "\"\""))
+    } else {
+        f.to_string()
+    }
+}
+
+fn is_pattern(s: &str) -> bool {
+    s.contains(['*', '?'])
+}
+
+/// Files to inspect. Inputs and directory entries that cannot be read go to
+/// `failures` as (path, error) instead of aborting the batch.
+fn expand_inputs(inputs: &[String], recursive: bool, failures: &mut Vec<(String, String)>) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for input in inputs {
+        if is_pattern(input) {
+            glob(input, &mut files, failures);
+        } else if Path::new(input).is_dir() {
+            walk(Path::new(input), recursive, &mut files, failures);
+        } else if Path::new(input).is_file() {
+            files.push(PathBuf::from(input));
+        } else {
+            failures.push((input.clone(), "does not exist".to_string()));
+        }
+    }
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// `.wasm` files in `dir`. Symlinked files count, symlinked directories are not
+/// followed, so link cycles cannot recurse forever.
+fn walk(dir: &Path, recursive: bool, out: &mut Vec<PathBuf>, failures: &mut Vec<(String, String)>) {
+    for (path, kind) in list_dir(dir, failures) {
+        match kind {
+            EntryKind::Dir if recursive => walk(&path, true, out, failures),
+            EntryKind::File if path.extension().is_some_and(|e| e == "wasm") => out.push(path),
+            _ => {}
+        }
+    }
+}
+
+/// Minimal glob: `*` and `?` within a path component, `**` across components.
+/// Only directories the remaining pattern can still match are read.
+fn glob(pattern: &str, out: &mut Vec<PathBuf>, failures: &mut Vec<(String, String)>) {
+    let parts: Vec<&str> = pattern.split('/').collect();
+    let fixed = parts.iter().take_while(|p| !is_pattern(p)).count();
+    let base = if fixed == 0 {
+        PathBuf::from(".")
+    } else {
+        PathBuf::from(parts[..fixed].join("/"))
+    };
+    let base = if pattern.starts_with('/') && fixed <= 1 { PathBuf::from("/") } else { base };
+    if !base.is_dir() {
+        return;
+    }
+    let mut found = Vec::new();
+    glob_walk(&base, &parts[fixed..], &mut found, failures);
+    out.extend(found.into_iter().map(|p| p.strip_prefix("./").map(Path::to_path_buf).unwrap_or(p)));
+}
+
+fn glob_walk(dir: &Path, parts: &[&str], out: &mut Vec<PathBuf>, failures: &mut Vec<(String, String)>) {
+    let Some((&first, rest)) = parts.split_first() else { return };
+    if first == "**" && !rest.is_empty() {
+        // `**` matching zero components
+        glob_walk(dir, rest, out, failures);
+    }
+    for (path, kind) in list_dir(dir, failures) {
+        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        if first == "**" {
+            match kind {
+                // real directories only: `**` never follows links
+                EntryKind::Dir => glob_walk(&path, parts, out, failures),
+                EntryKind::File if rest.is_empty() => out.push(path),
+                _ => {}
+            }
+        } else if match_segment(first.as_bytes(), name.as_bytes()) {
+            match kind {
+                EntryKind::File if rest.is_empty() => out.push(path),
+                // a named component may go through a link; the pattern bounds the depth
+                EntryKind::Dir | EntryKind::LinkDir if !rest.is_empty() => glob_walk(&path, rest, out, failures),
+                _ => {}
+            }
+        }
+    }
+}
+
+enum EntryKind {
+    /// A regular file or a symlink to one
+    File,
+    /// A real directory
+    Dir,
+    /// A symlink to a directory
+    LinkDir,
+    Other,
+}
+
+/// Entries of `dir` classified without following directory links. Errors are
+/// recorded in `failures` and the entry (or the whole directory) is skipped.
+fn list_dir(dir: &Path, failures: &mut Vec<(String, String)>) -> Vec<(PathBuf, EntryKind)> {
+    let entries = match fs::read_dir(dir) {
+        Ok(e) => e,
+        Err(e) => {
+            failures.push((dir.display().to_string(), e.to_string()));
+            return Vec::new();
+        }
+    };
+    let mut out = Vec::new();
+    for entry in entries {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                failures.push((dir.display().to_string(), e.to_string()));
+                continue;
+            }
+        };
+        let path = entry.path();
+        let kind = match entry.file_type() {
+            Ok(t) if t.is_dir() => EntryKind::Dir,
+            Ok(t) if t.is_file() => EntryKind::File,
+            Ok(t) if t.is_symlink() && path.is_file() => EntryKind::File,
+            Ok(t) if t.is_symlink() && path.is_dir() => EntryKind::LinkDir,
+            Ok(_) => EntryKind::Other,
+            Err(e) => {
+                failures.push((path.display().to_string(), e.to_string()));
+                continue;
+            }
+        };
+        out.push((path, kind));
+    }
+    out
+}
+
+fn match_segment(p: &[u8], s: &[u8]) -> bool {
+    match (p.split_first(), s.split_first()) {
+        (None, None) => true,
+        (Some((b'*', rest)), _) => (0..=s.len()).any(|k| match_segment(rest, &s[k..])),
+        (Some((b'?', rest)), Some((_, srest))) => match_segment(rest, srest),
+        (Some((c, rest)), Some((d, srest))) => c == d && match_segment(rest, srest),
+        _ => false,
+    }
+}
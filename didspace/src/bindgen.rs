@@ -1,4 +1,64 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Write as _;
+
 use anyhow::{anyhow, Result};
+use wit_component::DecodedWasm;
+use wit_parser::{
+    Function, FunctionKind, Handle, InterfaceId, Resolve, Type, TypeDefKind, TypeId, WorldId, WorldItem, WorldKey,
+};
+
+/// A component's WIT world, read from a `--wit` directory or decoded from the component itself.
+pub struct WitWorld {
+    pub resolve: Resolve,
+    pub world: WorldId,
+}
+
+impl WitWorld {
+    /// Prefer the `--wit` directory when given; otherwise decode the types the component embeds.
+    pub fn load(bytes: &[u8], wit_dir: Option<&str>, world: Option<&str>) -> Result<Self> {
+        match wit_dir {
+            Some(dir) => Self::from_dir(dir, world),
+            None => Self::from_component(bytes),
+        }
+    }
+
+    /// Without a world name, the package's only world is used.
+    pub fn from_dir(dir: &str, world: Option<&str>) -> Result<Self> {
+        let mut resolve = Resolve::default();
+        let (pkg, _) = resolve.push_path(dir)?;
+        let worlds = &resolve.packages[pkg].worlds;
+        // The main package first, then anything pulled in from deps/
+        let found = match world {
+            Some(world) => worlds
+                .get(world)
+                .copied()
+                .or_else(|| resolve.worlds.iter().find(|(_, w)| w.name == world).map(|(id, _)| id)),
+            None if worlds.len() == 1 => worlds.values().next().copied(),
+            None => None,
+        };
+        match found {
+            Some(world) => Ok(Self { resolve, world }),
+            None => {
+                let names: Vec<&str> = resolve.worlds.iter().map(|(_, w)| w.name.as_str()).collect();
+                match world {
+                    Some(w) => Err(anyhow!("world '{}' not found in {} (available: {})", w, dir, names.join(", "))),
+                    None => Err(anyhow!("pass --world; {} defines: {}", dir, names.join(", "))),
+                }
+            }
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.resolve.worlds[self.world].name
+    }
+
+    pub fn from_component(bytes: &[u8]) -> Result<Self> {
+        match wit_component::decode(bytes)? {
+            DecodedWasm::Component(resolve, world) => Ok(Self { resolve, world }),
+            DecodedWasm::WitPackage(..) => Err(anyhow!("this is an encoded WIT package, not a component")),
+        }
+    }
+}
 
 pub fn gen_rust_host_bindings(wit_path: &str, world: &str) -> Result<String> {
 
@@ -16,6 +76,7 @@ bindgen!({{
 "#))
 }
 
+/// Untyped facade for when no WIT is available; see [`gen_ts_typed`] for the real thing.
 pub fn gen_ts_node_wrapper(world: &str) -> Result<String> {
     // world qui lo usi per dare un nome “carino” al tipo.
     // Non serve conoscere WIT per MVP.
@@ -50,6 +111,556 @@ export async function load(): Promise<{type_name}> {{
 "#))
 }
 
+/// TypeScript declarations for a WIT world, shaped like the ESM that
+/// `jco transpile` emits: camelCase functions and fields, records as
+/// interfaces, variants as `{ tag, val }` unions, enums as string unions,
+/// resources as classes. Exported functions returning `result<T, E>` return
+/// `T` and throw the `err` payload, as jco does.
+pub fn gen_ts_typed(wit: &WitWorld, world: &str) -> Result<String> {
+    let resolve = &wit.resolve;
+    let w = &resolve.worlds[wit.world];
+    let type_name = pascal_case(world);
+    let mut ts = TsGen { resolve, out: String::new(), emitted: HashSet::new(), names: HashMap::new() };
+
+    // Types first: imported interfaces, then world-level types, then exported interfaces.
+    // Names are settled before anything is emitted so references can point forward.
+    let types: Vec<TypeId> = w
+        .imports
+        .iter()
+        .chain(w.exports.iter())
+        .flat_map(|(_, item)| match item {
+            WorldItem::Interface { id, .. } => resolve.interfaces[*id].types.values().copied().collect(),
+            WorldItem::Type(id) => vec![*id],
+            WorldItem::Function(_) => Vec::new(),
+        })
+        .collect();
+    for id in &types {
+        ts.assign_name(*id)?;
+    }
+    for id in types {
+        ts.typedef(id);
+    }
+
+    let mut members = Vec::new();
+    for (key, item) in &w.exports {
+        match item {
+            WorldItem::Function(f) => members.push(format!("  {};", ts.func_sig(f, true))),
+            WorldItem::Interface { id, .. } => {
+                let iface_name = ts.interface_decl(*id, "", true);
+                members.push(format!("  {}: {};", camel_case(&world_key_name(resolve, key)), iface_name));
+            }
+            WorldItem::Type(_) => {}
+        }
+    }
+    let mut imports = Vec::new();
+    for (key, item) in &w.imports {
+        if let WorldItem::Interface { id, .. } = item
+            && !resolve.interfaces[*id].functions.is_empty()
+        {
+            ts.interface_decl(*id, "Import", false);
+            imports.push(resolve.name_world_key(key));
+        }
+    }
+
+    let mut out = format!(
+        r#"// Auto-generated by didspace bindgen (Node.js + Jco) from WIT world "{wit_world}"
+//
+// 1) Generate JS glue into ./generated:
+//    npx @bytecodealliance/jco transpile ./component.wasm -o ./generated
+//
+// 2) Use this wrapper from Node/TS:
+//
+//    import {{ load }} from "./bindings";
+//    const api = await load();
+//
+
+import * as component from "./generated/component.js";
+
+export type Result<T, E> = {{ tag: 'ok', val: T }} | {{ tag: 'err', val: E }};
+"#,
+        wit_world = w.name
+    );
+    if !imports.is_empty() {
+        out.push_str("\n// Imports the host must provide (jco --map or an import map):\n");
+        for i in &imports {
+            let _ = writeln!(out, "//   {}", i);
+        }
+    }
+    out.push_str(&ts.out);
+    let _ = write!(
+        out,
+        r#"
+export interface {type_name} {{
+{members}
+}}
+
+export async function load(): Promise<{type_name}> {{
+  // Jco default ESM mode exports the component exports directly from component.js
+  return component as unknown as {type_name};
+}}
+"#,
+        members = members.join("\n")
+    );
+    Ok(out)
+}
+
+/// Python loader for a component over `python -m wasmtime.bindgen` output,
+/// with a typed `Protocol` of the world's exports when its WIT is known.
+pub fn gen_python_component(wit: Option<&WitWorld>, world: &str) -> String {
+    let class = pascal_case(world);
+    let mut protocols = String::new();
+    let mut members = Vec::new();
+    // Annotations are never evaluated (`from __future__ import annotations`), so these
+    // are for type checkers only and cannot break at runtime if the layout differs.
+    let mut type_imports: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    if let Some(wit) = wit {
+        let resolve = &wit.resolve;
+        let exports = &resolve.worlds[wit.world].exports;
+        let freestanding = |id: &InterfaceId| resolve.interfaces[*id].functions.values().filter(|f| f.kind == FunctionKind::Freestanding);
+        let mut used = Vec::new();
+        for item in exports.values() {
+            match item {
+                WorldItem::Function(f) => py_named_types(resolve, f, &mut used),
+                WorldItem::Interface { id, .. } => freestanding(id).for_each(|f| py_named_types(resolve, f, &mut used)),
+                WorldItem::Type(_) => {}
+            }
+        }
+        let names = py_type_names(wit, &used, &mut type_imports);
+        for (key, item) in exports {
+            match item {
+                WorldItem::Function(f) => members.push(py_method(resolve, &names, f)),
+                WorldItem::Interface { id, .. } => {
+                    let name = world_key_name(resolve, key);
+                    let iface = pascal_case(&name);
+                    let methods: Vec<String> = freestanding(id).map(|f| py_method(resolve, &names, f)).collect();
+                    let body = if methods.is_empty() { "    ...".to_string() } else { methods.join("\n") };
+                    let _ = write!(protocols, "\nclass {}(Protocol):\n{}\n\n", iface, body);
+                    members.push(format!("    def {}(self) -> {}: ...", snake_case(&name), iface));
+                }
+                WorldItem::Type(_) => {}
+            }
+        }
+    }
+    let protocol = if members.is_empty() {
+        format!("{class} = Root  # no WIT available: exports are untyped\n")
+    } else {
+        format!("{protocols}\nclass {class}(Protocol):\n{}\n", members.join("\n"))
+    };
+    if protocol.contains("Result[") {
+        type_imports.entry("generated.types".into()).or_default().insert("Result".into());
+    }
+    let type_checking = if type_imports.is_empty() {
+        String::new()
+    } else {
+        let mut block = "\nif TYPE_CHECKING:\n".to_string();
+        for (module, names) in &type_imports {
+            let _ = writeln!(block, "    from {} import {}", module, names.iter().cloned().collect::<Vec<_>>().join(", "));
+        }
+        block
+    };
+    let typing = if type_checking.is_empty() { "Optional, Protocol" } else { "TYPE_CHECKING, Optional, Protocol" };
+
+    format!(
+        r#""""Auto-generated by didspace bindgen (wasmtime-py, component world "{world}").
+
+1) Generate the component glue:
+     python -m wasmtime.bindgen component.wasm --out-dir generated
+2) Use it:
+     from bindings import load
+     app, store = load()
+
+Exported functions take the store first, as wasmtime.bindgen generates them.
+Named WIT types (records, variants, enums, resources) live in the generated package.
+"""
+from __future__ import annotations
+
+from typing import {typing}
+
+import wasmtime
+
+from generated import Root
+{type_checking}
+{protocol}
+
+def load(imports=None, store: Optional[wasmtime.Store] = None) -> tuple[{class}, wasmtime.Store]:
+    """`imports` is the generated RootImports, required when the world has imports."""
+    store = store or wasmtime.Store()
+    root = Root(store, imports) if imports is not None else Root(store)
+    return root, store  # type: ignore[return-value]
+"#
+    )
+}
+
+// ---------- helpers ----------
+
+struct TsGen<'a> {
+    resolve: &'a Resolve,
+    out: String,
+    emitted: HashSet<TypeId>,
+    /// Declared TypeScript name per named type; see `assign_name`
+    names: HashMap<TypeId, String>,
+}
+
+impl TsGen<'_> {
+    /// Give a named type its TypeScript name. Everything lands in one module,
+    /// so a name already taken by another interface gets that interface's
+    /// name as a prefix (`types.error` next to `fs.error` becomes `FsError`).
+    fn assign_name(&mut self, id: TypeId) -> Result<()> {
+        let def = &self.resolve.types[id];
+        let Some(raw) = def.name.as_deref() else { return Ok(()) };
+        if self.names.contains_key(&id) || self.alias_target(id).is_some() {
+            return Ok(());
+        }
+        let taken = |n: &str| self.names.values().any(|v| v == n);
+        let mut name = pascal_case(raw);
+        if taken(&name) {
+            let owner = match def.owner {
+                wit_parser::TypeOwner::Interface(i) => self.resolve.interfaces[i].name.clone(),
+                wit_parser::TypeOwner::World(w) => Some(self.resolve.worlds[w].name.clone()),
+                wit_parser::TypeOwner::None => None,
+            };
+            let qualified = owner.map(|o| format!("{}{}", pascal_case(&o), name));
+            match qualified {
+                Some(q) if !taken(&q) => name = q,
+                _ => return Err(anyhow!("WIT type '{}' clashes with another type of the same name; rename one of them", raw)),
+            }
+        }
+        self.names.insert(id, name);
+        Ok(())
+    }
+
+    /// `use`d types alias the original under the same name.
+    fn alias_target(&self, id: TypeId) -> Option<TypeId> {
+        let def = &self.resolve.types[id];
+        match def.kind {
+            TypeDefKind::Type(Type::Id(target)) if def.name.is_some() && self.resolve.types[target].name == def.name => {
+                Some(target)
+            }
+            _ => None,
+        }
+    }
+
+    fn name_of(&self, id: TypeId) -> String {
+        if let Some(target) = self.alias_target(id) {
+            return self.name_of(target);
+        }
+        match (self.names.get(&id), &self.resolve.types[id].name) {
+            (Some(n), _) => n.clone(),
+            (None, Some(raw)) => pascal_case(raw),
+            (None, None) => self.inline(id),
+        }
+    }
+
+    /// Emit an interface listing the functions of `id` and, for exports, its
+    /// resource classes; returns the declared name.
+    fn interface_decl(&mut self, id: InterfaceId, suffix: &str, export: bool) -> String {
+        let iface = &self.resolve.interfaces[id];
+        let name = format!("{}{}", pascal_case(iface.name.as_deref().unwrap_or("interface")), suffix);
+        let mut body = String::new();
+        for f in iface.functions.values() {
+            if f.kind == FunctionKind::Freestanding {
+                let _ = writeln!(body, "{}  {};", self.docs(&f.docs.contents, "  "), self.func_sig(f, export));
+            }
+        }
+        if export {
+            for (res_name, ty) in &iface.types {
+                if matches!(self.resolve.types[*ty].kind, TypeDefKind::Resource) {
+                    let _ = writeln!(body, "  {}: typeof {};", pascal_case(res_name), self.name_of(*ty));
+                }
+            }
+        }
+        let _ = write!(self.out, "\nexport interface {} {{\n{}}}\n", name, body);
+        name
+    }
+
+    fn typedef(&mut self, id: TypeId) {
+        if !self.emitted.insert(id) {
+            return;
+        }
+        let def = &self.resolve.types[id];
+        if def.name.is_none() {
+            return;
+        }
+        // `use`d types alias the original; emit that one instead
+        if let Some(target) = self.alias_target(id) {
+            self.typedef(target);
+            return;
+        }
+        let name = self.name_of(id);
+        let mut out = format!("\n{}", self.docs(&def.docs.contents, ""));
+        match &def.kind {
+            TypeDefKind::Record(r) => {
+                let _ = writeln!(out, "export interface {} {{", name);
+                for field in &r.fields {
+                    let _ = writeln!(out, "  {}: {},", camel_case(&field.name), self.ty(&field.ty));
+                }
+                out.push_str("}\n");
+            }
+            TypeDefKind::Variant(v) => {
+                let cases: Vec<String> = v.cases.iter().map(|c| format!("{}{}", name, pascal_case(&c.name))).collect();
+                let _ = writeln!(out, "export type {} = {};", name, cases.join(" | "));
+                for (case, case_name) in v.cases.iter().zip(&cases) {
+                    match &case.ty {
+                        Some(t) => {
+                            let _ = writeln!(out, "export interface {} {{\n  tag: '{}',\n  val: {},\n}}", case_name, case.name, self.ty(t));
+                        }
+                        None => {
+                            let _ = writeln!(out, "export interface {} {{\n  tag: '{}',\n}}", case_name, case.name);
+                        }
+                    }
+                }
+            }
+            TypeDefKind::Enum(e) => {
+                let cases: Vec<String> = e.cases.iter().map(|c| format!("'{}'", c.name)).collect();
+                let _ = writeln!(out, "export type {} = {};", name, cases.join(" | "));
+            }
+            TypeDefKind::Flags(f) => {
+                let _ = writeln!(out, "export interface {} {{", name);
+                for flag in &f.flags {
+                    let _ = writeln!(out, "  {}?: boolean,", camel_case(&flag.name));
+                }
+                out.push_str("}\n");
+            }
+            TypeDefKind::Resource => out.push_str(&self.resource_class(id, &name)),
+            _ => {
+                let _ = writeln!(out, "export type {} = {};", name, self.inline(id));
+            }
+        }
+        self.out.push_str(&out);
+    }
+
+    fn resource_class(&self, id: TypeId, name: &str) -> String {
+        let mut out = format!("export declare class {} {{\n", name);
+        let owner = match self.resolve.types[id].owner {
+            wit_parser::TypeOwner::Interface(i) => self.resolve.interfaces[i].functions.values().collect::<Vec<_>>(),
+            wit_parser::TypeOwner::World(w) => self.resolve.worlds[w]
+                .exports
+                .values()
+                .chain(self.resolve.worlds[w].imports.values())
+                .filter_map(|item| match item {
+                    WorldItem::Function(f) => Some(f),
+                    _ => None,
+                })
+                .collect(),
+            wit_parser::TypeOwner::None => Vec::new(),
+        };
+        for f in owner.into_iter().filter(|f| f.kind.resource() == Some(id)) {
+            let docs = self.docs(&f.docs.contents, "  ");
+            let params = self.params(f);
+            let _ = match &f.kind {
+                FunctionKind::Constructor(_) => writeln!(out, "{}  constructor({});", docs, params),
+                FunctionKind::Static(_) => writeln!(out, "{}  static {}({}): {};", docs, camel_case(f.item_name()), params, self.ret(f, true)),
+                _ => writeln!(out, "{}  {}({}): {};", docs, camel_case(f.item_name()), params, self.ret(f, true)),
+            };
+        }
+        out.push_str("  [Symbol.dispose]?(): void;\n}\n");
+        out
+    }
+
+    fn func_sig(&self, f: &Function, unwrap_result: bool) -> String {
+        format!("{}({}): {}", camel_case(&f.name), self.params(f), self.ret(f, unwrap_result))
+    }
+
+    fn params(&self, f: &Function) -> String {
+        let skip = usize::from(matches!(f.kind, FunctionKind::Method(_)));
+        f.params
+            .iter()
+            .skip(skip)
+            .map(|(n, t)| format!("{}: {}", camel_case(n), self.ty(t)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Exports throw the `err` case of a top-level `result` rather than returning it.
+    fn ret(&self, f: &Function, unwrap_result: bool) -> String {
+        let Some(t) = f.result else { return "void".into() };
+        if let (true, Type::Id(id)) = (unwrap_result, t)
+            && let TypeDefKind::Result(r) = &self.resolve.types[id].kind
+        {
+            return r.ok.as_ref().map_or("void".into(), |ok| self.ty(ok));
+        }
+        self.ty(&t)
+    }
+
+    fn ty(&self, t: &Type) -> String {
+        match t {
+            Type::Bool => "boolean".into(),
+            Type::U8 | Type::U16 | Type::U32 | Type::S8 | Type::S16 | Type::S32 | Type::F32 | Type::F64 => "number".into(),
+            Type::U64 | Type::S64 => "bigint".into(),
+            Type::Char | Type::String => "string".into(),
+            Type::Id(id) => self.name_of(*id),
+            #[allow(unreachable_patterns)]
+            _ => "unknown".into(),
+        }
+    }
+
+    /// Structural rendering of a type definition, for anonymous types and aliases.
+    fn inline(&self, id: TypeId) -> String {
+        match &self.resolve.types[id].kind {
+            TypeDefKind::List(t) => match t {
+                Type::U8 => "Uint8Array".into(),
+                Type::S8 => "Int8Array".into(),
+                Type::U16 => "Uint16Array".into(),
+                Type::S16 => "Int16Array".into(),
+                Type::U32 => "Uint32Array".into(),
+                Type::S32 => "Int32Array".into(),
+                Type::U64 => "BigUint64Array".into(),
+                Type::S64 => "BigInt64Array".into(),
+                Type::F32 => "Float32Array".into(),
+                Type::F64 => "Float64Array".into(),
+                other => format!("Array<{}>", self.ty(other)),
+            },
+            TypeDefKind::Option(t) => format!("{} | undefined", self.ty(t)),
+            TypeDefKind::Result(r) => format!(
+                "Result<{}, {}>",
+                r.ok.as_ref().map_or("void".into(), |t| self.ty(t)),
+                r.err.as_ref().map_or("void".into(), |t| self.ty(t))
+            ),
+            TypeDefKind::Tuple(t) => format!("[{}]", t.types.iter().map(|t| self.ty(t)).collect::<Vec<_>>().join(", ")),
+            TypeDefKind::Handle(Handle::Own(r) | Handle::Borrow(r)) => self.ty(&Type::Id(*r)),
+            TypeDefKind::Type(t) => self.ty(t),
+            _ => "unknown".into(),
+        }
+    }
+
+    fn docs(&self, docs: &Option<String>, indent: &str) -> String {
+        match docs {
+            Some(d) => format!("{indent}/** {} */\n", d.trim().replace('\n', &format!("\n{indent} * "))),
+            None => String::new(),
+        }
+    }
+}
+
+fn world_key_name(resolve: &Resolve, key: &WorldKey) -> String {
+    match key {
+        WorldKey::Name(n) => n.clone(),
+        WorldKey::Interface(id) => resolve.interfaces[*id].name.clone().unwrap_or_default(),
+    }
+}
+
+fn py_method(resolve: &Resolve, names: &HashMap<TypeId, String>, f: &Function) -> String {
+    let params: String = f.params.iter().map(|(n, t)| format!(", {}: {}", snake_case(n), py_type(resolve, names, t))).collect();
+    let ret = f.result.map_or("None".into(), |t| py_type(resolve, names, &t));
+    format!("    def {}(self, store: wasmtime.Store{}) -> {}: ...", snake_case(&f.name), params, ret)
+}
+
+/// Named WIT types in `f`'s signature, which `py_type` refers to by name.
+fn py_named_types(resolve: &Resolve, f: &Function, out: &mut Vec<TypeId>) {
+    fn walk(resolve: &Resolve, t: &Type, out: &mut Vec<TypeId>) {
+        let Type::Id(id) = t else { return };
+        let def = &resolve.types[*id];
+        if def.name.is_some() {
+            if !out.contains(id) {
+                out.push(*id);
+            }
+            return;
+        }
+        match &def.kind {
+            TypeDefKind::List(t) | TypeDefKind::Option(t) | TypeDefKind::Type(t) => walk(resolve, t, out),
+            TypeDefKind::Result(r) => r.ok.iter().chain(&r.err).for_each(|t| walk(resolve, t, out)),
+            TypeDefKind::Tuple(t) => t.types.iter().for_each(|t| walk(resolve, t, out)),
+            TypeDefKind::Handle(Handle::Own(r) | Handle::Borrow(r)) => walk(resolve, &Type::Id(*r), out),
+            _ => {}
+        }
+    }
+    for (_, t) in &f.params {
+        walk(resolve, t, out);
+    }
+    if let Some(t) = &f.result {
+        walk(resolve, t, out);
+    }
+}
+
+/// Local names for `used` types, recording the imports that bring them in.
+/// A name already taken is imported under its module's name as a prefix
+/// (`from generated.exports.net import Error as NetError`).
+fn py_type_names(wit: &WitWorld, used: &[TypeId], imports: &mut BTreeMap<String, BTreeSet<String>>) -> HashMap<TypeId, String> {
+    let mut names: HashMap<TypeId, String> = HashMap::new();
+    for id in used {
+        let module = py_type_module(wit, *id);
+        let base = pascal_case(wit.resolve.types[*id].name.as_deref().unwrap_or_default());
+        let local = if names.values().any(|n| *n == base) {
+            let alias = format!("{}{}", pascal_case(module.rsplit('.').next().unwrap_or_default()), base);
+            imports.entry(module).or_default().insert(format!("{} as {}", base, alias));
+            alias
+        } else {
+            imports.entry(module).or_default().insert(base.clone());
+            base
+        };
+        names.insert(*id, local);
+    }
+    names
+}
+
+/// Module of the `wasmtime.bindgen` package that defines a named type:
+/// world-level types sit in the package root, interface types under
+/// `exports/` or `imports/`.
+fn py_type_module(wit: &WitWorld, id: TypeId) -> String {
+    let resolve = &wit.resolve;
+    let w = &resolve.worlds[wit.world];
+    match resolve.types[id].owner {
+        wit_parser::TypeOwner::Interface(iface) => {
+            let in_world = |exports: bool| {
+                let items = if exports { &w.exports } else { &w.imports };
+                items.iter().find_map(|(key, item)| match item {
+                    WorldItem::Interface { id, .. } if *id == iface => Some(world_key_name(resolve, key)),
+                    _ => None,
+                })
+            };
+            match in_world(true) {
+                Some(name) => format!("generated.exports.{}", snake_case(&name)),
+                None => {
+                    let name = in_world(false).or_else(|| resolve.interfaces[iface].name.clone()).unwrap_or_default();
+                    format!("generated.imports.{}", snake_case(&name))
+                }
+            }
+        }
+        _ => "generated".into(),
+    }
+}
+
+fn py_type(resolve: &Resolve, names: &HashMap<TypeId, String>, t: &Type) -> String {
+    match t {
+        Type::Bool => "bool".into(),
+        Type::F32 | Type::F64 => "float".into(),
+        Type::Char | Type::String => "str".into(),
+        Type::Id(id) => {
+            let def = &resolve.types[*id];
+            if let Some(n) = &def.name {
+                return names.get(id).cloned().unwrap_or_else(|| pascal_case(n));
+            }
+            let opt = |t: &Option<Type>| t.as_ref().map_or("None".into(), |t| py_type(resolve, names, t));
+            match &def.kind {
+                TypeDefKind::List(Type::U8) => "bytes".into(),
+                TypeDefKind::List(t) => format!("list[{}]", py_type(resolve, names, t)),
+                TypeDefKind::Option(t) => format!("Optional[{}]", py_type(resolve, names, t)),
+                TypeDefKind::Result(r) => format!("Result[{}, {}]", opt(&r.ok), opt(&r.err)),
+                TypeDefKind::Tuple(t) => {
+                    format!("tuple[{}]", t.types.iter().map(|t| py_type(resolve, names, t)).collect::<Vec<_>>().join(", "))
+                }
+                TypeDefKind::Handle(Handle::Own(r) | Handle::Borrow(r)) => py_type(resolve, names, &Type::Id(*r)),
+                TypeDefKind::Type(t) => py_type(resolve, names, t),
+                _ => "object".into(),
+            }
+        }
+        // integers
+        _ => "int".into(),
+    }
+}
+
+fn snake_case(s: &str) -> String {
+    s.replace(['-', ' '], "_").to_lowercase()
+}
+
+fn camel_case(s: &str) -> String {
+    let p = pascal_case(s);
+    let mut ch = p.chars();
+    match ch.next() {
+        None => String::new(),
+        Some(f) => f.to_lowercase().collect::<String>() + ch.as_str(),
+    }
+}
+
 fn pascal_case(s: &str) -> String {
     s.split(|c: char| c == '-' || c == '_' || c == ' ')
         .filter(|p| !p.is_empty())
@@ -0,0 +1,613 @@
+use std::path::{Path, PathBuf};
+use std::process::Command as SysCommand;
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+use crate::config::{Config, Mode};
+use crate::doctor::{check_eh_model, EhCheck};
+use crate::report::{Report, Section};
+
+const EMULATIONS: &[&str] = &["signal", "mman", "getpid", "process-clocks"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    C,
+    Cxx,
+}
+
+impl Lang {
+    /// C++ if any source has a C++ extension.
+    pub fn from_sources(sources: &[String]) -> Self {
+        let is_cxx = |s: &String| {
+            matches!(
+                Path::new(s).extension().and_then(|e| e.to_str()),
+                Some("cpp" | "cc" | "cxx" | "c++" | "C")
+            )
+        };
+        if sources.iter().any(is_cxx) { Lang::Cxx } else { Lang::C }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// wasi-sdk style clang (`--target=wasm32[-wasi]`)
+    Clang,
+    /// emcc / em++
+    Emscripten,
+}
+
+impl Backend {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "" | "clang" => Ok(Backend::Clang),
+            "emscripten" | "emcc" => Ok(Backend::Emscripten),
+            other => Err(anyhow!("unknown backend '{}' (use clang or emscripten)", other)),
+        }
+    }
+}
+
+/// Build options shared by the C/C++ compile commands (flattened into their CLI arguments).
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct BuildFlags {
+    /// Additional source file compiled and linked with the input (repeatable)
+    #[arg(long = "src", value_name = "FILE")]
+    pub extra_sources: Vec<String>,
+
+    /// Include directory (repeatable)
+    #[arg(short = 'I', long = "include", value_name = "DIR")]
+    pub include_dirs: Vec<String>,
+
+    /// Preprocessor define (repeatable)
+    #[arg(short = 'D', long = "define", value_name = "NAME[=VALUE]")]
+    pub defines: Vec<String>,
+
+    /// Optimization level: 0, 1, 2, 3, s or z
+    #[arg(short = 'O', long = "opt", value_name = "LEVEL")]
+    pub opt_level: Option<String>,
+
+    /// Emit DWARF debug info (-g)
+    #[arg(short = 'g', long = "debug")]
+    pub debug: bool,
+
+    /// WASI execution model: command (_start) or reactor (_initialize + exports)
+    #[arg(long, value_name = "MODEL")]
+    pub exec_model: Option<String>,
+
+    /// Symbol to export from the final module (repeatable)
+    #[arg(long = "export", value_name = "SYMBOL")]
+    pub exports: Vec<String>,
+
+    /// Extra library to link, as for -l (repeatable)
+    #[arg(short = 'l', long = "lib", value_name = "NAME")]
+    pub libs: Vec<String>,
+
+    /// WASI emulation libs: signal, mman, getpid, process-clocks, or none (default: from mode)
+    #[arg(long, value_delimiter = ',', value_name = "LIST")]
+    pub emulate: Option<Vec<String>>,
+
+    /// Compiler backend: clang (wasi-sdk) or emscripten (emcc/em++; a .js/.html output adds JS glue)
+    #[arg(long, default_value = "clang", value_parser = ["clang", "emscripten"])]
+    pub backend: String,
+
+    /// C++ exception handling: none (-fno-exceptions), emulated (-fexceptions, throw aborts
+    /// outside Emscripten) or wasm (-fwasm-exceptions with the EH-enabled libc++abi)
+    #[arg(long, value_name = "MODEL", default_value = "none", value_parser = ["none", "emulated", "wasm"])]
+    pub eh: String,
+
+    /// Where to write compile_commands.json (default: into `output.dir` when didspace.toml
+    /// sets one, otherwise not written)
+    #[arg(long, value_name = "PATH")]
+    pub compile_commands: Option<String>,
+
+    /// Directory for object files (default: obj/ under `output.dir`, otherwise a
+    /// per-output directory under the user cache dir, e.g. ~/.cache/didspace/obj)
+    #[arg(long, value_name = "DIR")]
+    pub obj_dir: Option<String>,
+
+    /// Reuse up-to-date objects (set by --watch)
+    #[arg(skip)]
+    pub incremental: bool,
+}
+
+pub struct BuildRequest<'a> {
+    pub sources: Vec<String>,
+    pub output: &'a str,
+    pub lang: Lang,
+    /// `--minimal` (freestanding) vs `--wasi`
+    pub minimal: bool,
+    pub sysroot: Option<&'a str>,
+    pub flags: &'a BuildFlags,
+    /// Print each command before running it
+    pub echo: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommandRecord {
+    pub argv: Vec<String>,
+    pub status: Option<i32>,
+    pub duration_ms: u128,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BuildReport {
+    pub output: String,
+    /// The wasm module: `output` itself, or the file next to the JS glue
+    pub wasm: String,
+    /// Emscripten JS glue, when the output is .js/.mjs/.html
+    pub glue: Option<String>,
+    pub backend: String, // "clang" | "emscripten"
+    pub lang: String,
+    pub mode: String, // "minimal" | "wasi" | "js"
+    pub eh: String, // "none" | "emulated" | "wasm"
+    pub sources: Vec<String>,
+    pub objects: Vec<String>,
+    /// Incremental builds: objects that were up to date and not recompiled
+    pub reused: Vec<String>,
+    pub compile_commands: Option<String>,
+    pub commands: Vec<CommandRecord>,
+    /// C++ only: the requested EH model checked against the linked output
+    pub eh_check: Option<EhCheck>,
+}
+
+#[derive(Serialize)]
+struct CompileCommand {
+    directory: String,
+    arguments: Vec<String>,
+    file: String,
+    output: String,
+}
+
+impl BuildReport {
+    pub fn to_sections(&self) -> Vec<Section> {
+        let mut s = Section::new(format!("Build ({} / {} / {})", self.lang, self.mode, self.backend))
+            .kv("output", &self.output);
+        if let Some(glue) = &self.glue {
+            s = s.kv("glue", glue).kv("wasm", &self.wasm);
+        }
+        if self.lang == "c++" {
+            s = s.kv("eh", &self.eh);
+        }
+        s = s.kv("sources", self.sources.join(", "));
+        if !self.reused.is_empty() {
+            s = s.kv("reused", format!("{} up-to-date object(s)", self.reused.len()));
+        }
+        if let Some(cc) = &self.compile_commands {
+            s = s.kv("compile_commands", cc);
+        }
+        if let Some(c) = &self.eh_check {
+            let level = match c.status.as_str() {
+                "ok" => "ok",
+                "mismatch" => "error",
+                _ => "warn",
+            };
+            s = s.finding(level, format!("eh check: expected {}, observed {} ({})", c.expected, c.observed, c.detail));
+        }
+
+        let rows = self
+            .commands
+            .iter()
+            .map(|c| {
+                let status = match c.status {
+                    Some(0) => "ok".to_string(),
+                    Some(code) => format!("exit {}", code),
+                    None => "killed".to_string(),
+                };
+                vec![status, c.duration_ms.to_string(), shell_join(&c.argv)]
+            })
+            .collect();
+        let commands = Section::new("Commands").table(&["status", "ms", "command"], rows);
+        vec![s, commands]
+    }
+}
+
+/// Compile every source to an object, then link them into `output`.
+/// Emits compile_commands.json for the compile steps when asked for (or when
+/// `output.dir` is configured); nothing else lands next to the caller's sources.
+pub fn build(req: BuildRequest<'_>, config: &Config) -> Result<BuildReport> {
+    if req.sources.is_empty() {
+        return Err(anyhow!("no sources given"));
+    }
+    for s in &req.sources {
+        if !Path::new(s).exists() {
+            return Err(anyhow!("Input file '{}' does not exist", s));
+        }
+    }
+    let backend = Backend::parse(&req.flags.backend)?;
+    let js_output = is_js_output(req.output);
+    let eh = eh_model(&req)?;
+
+    let mode = match backend {
+        Backend::Clang => {
+            if js_output {
+                return Err(anyhow!("JS glue output ({}) needs --backend emscripten", req.output));
+            }
+            if !req.minimal && req.sysroot.is_none() {
+                return Err(anyhow!("--wasi requires --wasi-sysroot"));
+            }
+            match (req.lang, req.minimal) {
+                (Lang::C, true) => Mode::Minimal,
+                (Lang::Cxx, true) => Mode::CxxMinimal,
+                (Lang::C, false) => Mode::Wasi,
+                (Lang::Cxx, false) => Mode::Cxx,
+            }
+        }
+        Backend::Emscripten if js_output => Mode::EmJs,
+        Backend::Emscripten if req.minimal => Mode::EmMinimal,
+        Backend::Emscripten => Mode::EmWasi,
+    };
+    if eh == "emulated" && backend == Backend::Emscripten && mode != Mode::EmJs {
+        return Err(anyhow!(
+            "--eh=emulated with emscripten catches exceptions in JS; use a .js output (or --eh=wasm for standalone)"
+        ));
+    }
+    let compiler = match (backend, req.lang) {
+        (Backend::Clang, Lang::C) => config.c_compiler(),
+        (Backend::Clang, Lang::Cxx) => config.cxx_compiler(),
+        (Backend::Emscripten, Lang::C) => config.emcc(),
+        (Backend::Emscripten, Lang::Cxx) => config.emxx(),
+    };
+    let args = Args::assemble(&req, config, mode, backend, eh)?;
+
+    let obj_dir = match (&req.flags.obj_dir, config.output_dir()) {
+        (Some(d), _) => PathBuf::from(d),
+        (None, Some(dir)) => Path::new(&dir).join("obj"),
+        (None, None) => cache_obj_dir(req.output)?,
+    };
+    let compile_commands = req
+        .flags
+        .compile_commands
+        .clone()
+        .or_else(|| config.output_dir().map(|d| Path::new(&d).join("compile_commands.json").display().to_string()));
+    std::fs::create_dir_all(&obj_dir)?;
+    if let Some(parent) = Path::new(req.output).parent()
+        && !parent.as_os_str().is_empty()
+    {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let cwd = std::env::current_dir()?.display().to_string();
+    let (wasm, glue) = if js_output {
+        let wasm = Path::new(req.output).with_extension("wasm").display().to_string();
+        (wasm, Some(req.output.to_string()))
+    } else {
+        (req.output.to_string(), None)
+    };
+    let mut report = BuildReport {
+        output: req.output.to_string(),
+        wasm,
+        glue,
+        backend: match backend { Backend::Clang => "clang", Backend::Emscripten => "emscripten" }.to_string(),
+        lang: match req.lang { Lang::C => "c", Lang::Cxx => "c++" }.to_string(),
+        mode: match mode {
+            Mode::EmJs => "js",
+            Mode::Minimal | Mode::CxxMinimal | Mode::EmMinimal => "minimal",
+            _ => "wasi",
+        }
+        .to_string(),
+        eh: eh.to_string(),
+        sources: req.sources.clone(),
+        objects: Vec::new(),
+        reused: Vec::new(),
+        compile_commands: None,
+        commands: Vec::new(),
+        eh_check: None,
+    };
+    let result = run_all(&req, &args, &compiler, &obj_dir, compile_commands, &cwd, &mut report);
+    match result {
+        Ok(()) => {
+            if req.lang == Lang::Cxx {
+                // best-effort: an unparsable output is left to validate/doctor to report
+                report.eh_check = check_eh_model(&std::fs::read(&report.wasm)?, eh).ok();
+            }
+            Ok(report)
+        }
+        // keep the commands that ran in the error so failures are debuggable
+        Err(e) => Err(anyhow!("{:#}\n\n{}", e, Report::new("Build log").sections(report.to_sections()).to_text())),
+    }
+}
+
+fn run_all(
+    req: &BuildRequest<'_>,
+    args: &Args,
+    compiler: &str,
+    obj_dir: &Path,
+    compile_commands: Option<String>,
+    cwd: &str,
+    report: &mut BuildReport,
+) -> Result<()> {
+    let mut db = Vec::new();
+
+    for (i, src) in req.sources.iter().enumerate() {
+        let stem = Path::new(src).file_stem().and_then(|s| s.to_str()).unwrap_or("src");
+        let obj = obj_dir.join(format!("{}-{}.o", i, stem)).display().to_string();
+
+        let mut argv = vec![compiler.to_string()];
+        argv.extend(args.compile.iter().cloned());
+        if req.flags.incremental {
+            argv.extend(["-MMD".to_string(), "-MF".to_string(), format!("{}.d", obj)]);
+        }
+        argv.extend(["-c".to_string(), src.clone(), "-o".to_string(), obj.clone()]);
+
+        db.push(CompileCommand {
+            directory: cwd.to_string(),
+            arguments: argv.clone(),
+            file: src.clone(),
+            output: obj.clone(),
+        });
+        let cmd_file = format!("{}.cmd", obj);
+        if req.flags.incremental && up_to_date(&obj, &cmd_file, src, &argv) {
+            report.reused.push(obj.clone());
+        } else {
+            run_step(&mut report.commands, argv.clone(), req.echo)?;
+            if req.flags.incremental {
+                std::fs::write(&cmd_file, shell_join(&argv))?;
+            }
+        }
+        report.objects.push(obj);
+    }
+
+    if let Some(cc_path) = compile_commands {
+        if let Some(parent) = Path::new(&cc_path).parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&cc_path, serde_json::to_string_pretty(&db)?)?;
+        report.compile_commands = Some(cc_path);
+    }
+
+    let mut argv = vec![compiler.to_string()];
+    argv.extend(args.link.iter().cloned());
+    argv.extend(report.objects.iter().cloned());
+    argv.extend(["-o".to_string(), req.output.to_string()]);
+    argv.extend(args.libs.iter().cloned());
+    run_step(&mut report.commands, argv, req.echo)
+}
+
+/// Compiler arguments split by phase.
+struct Args {
+    compile: Vec<String>,
+    link: Vec<String>,
+    libs: Vec<String>,
+}
+
+impl Args {
+    fn assemble(req: &BuildRequest<'_>, config: &Config, mode: Mode, backend: Backend, eh: &str) -> Result<Self> {
+        let f = req.flags;
+        let base = config.mode_flags(mode);
+        let mut common: Vec<String> = Vec::new();
+
+        for flag in &base.flags {
+            if eh != "none" && flag == "-fno-exceptions" {
+                continue;
+            }
+            common.push(flag.clone());
+        }
+        match eh {
+            "emulated" => common.push("-fexceptions".to_string()),
+            "wasm" => common.push("-fwasm-exceptions".to_string()),
+            _ => {}
+        }
+        if let Some(sysroot) = req.sysroot
+            && !req.minimal
+            && backend == Backend::Clang
+        {
+            common.extend(["--sysroot".to_string(), sysroot.to_string()]);
+        }
+        if let Some(level) = &f.opt_level {
+            if !matches!(level.as_str(), "0" | "1" | "2" | "3" | "s" | "z") {
+                return Err(anyhow!("invalid optimization level '{}' (use 0, 1, 2, 3, s or z)", level));
+            }
+            common.push(format!("-O{}", level));
+        }
+        if f.debug {
+            common.push("-g".to_string());
+        }
+
+        if backend == Backend::Emscripten
+            && f.emulate.as_ref().is_some_and(|l| l.iter().any(|e| e != "none"))
+        {
+            return Err(anyhow!("--emulate applies to the clang backend (emscripten provides signal/mman itself)"));
+        }
+        let emulate = if req.minimal || backend == Backend::Emscripten {
+            Vec::new()
+        } else {
+            match &f.emulate {
+                Some(list) => list.iter().filter(|e| e.as_str() != "none").cloned().collect(),
+                None => base.emulate.clone(),
+            }
+        };
+        for e in &emulate {
+            if !EMULATIONS.contains(&e.as_str()) {
+                return Err(anyhow!("unknown emulation '{}' (use {})", e, EMULATIONS.join(", ")));
+            }
+        }
+
+        // link-only flags stay out of the compile step to keep the compiler quiet
+        let mut compile: Vec<String> = common.iter().filter(|a| !is_link_only(a)).cloned().collect();
+        compile.extend(f.include_dirs.iter().map(|d| format!("-I{}", d)));
+        compile.extend(f.defines.iter().map(|d| format!("-D{}", d)));
+        compile.extend(emulate.iter().map(|e| format!("-D_WASI_EMULATED_{}", e.replace('-', "_").to_uppercase())));
+
+        let mut link = common;
+        if let Some(model) = &f.exec_model {
+            if !matches!(model.as_str(), "command" | "reactor") {
+                return Err(anyhow!("invalid exec model '{}' (use command or reactor)", model));
+            }
+            if req.minimal {
+                return Err(anyhow!("--exec-model applies to --wasi builds only"));
+            }
+            match backend {
+                Backend::Clang => link.push(format!("-mexec-model={}", model)),
+                Backend::Emscripten if model == "reactor" => link.push("--no-entry".to_string()),
+                Backend::Emscripten => {}
+            }
+        }
+        match backend {
+            Backend::Clang => link.extend(f.exports.iter().map(|e| format!("-Wl,--export={}", e))),
+            Backend::Emscripten if !f.exports.is_empty() => {
+                // emscripten wants the C-level (underscore-prefixed) names
+                let names: Vec<String> = f.exports.iter().map(|e| format!("_{}", e)).collect();
+                link.push(format!("-sEXPORTED_FUNCTIONS={}", names.join(",")));
+            }
+            Backend::Emscripten => {}
+        }
+
+        let mut libs = Vec::new();
+        if eh == "wasm" && backend == Backend::Clang {
+            // wasi-sdk ships an EH-enabled libc++/libc++abi next to the default ones
+            if let Some(dir) = req.sysroot.and_then(|s| eh_lib_dir(s, &base.flags)) {
+                libs.push(format!("-L{}", dir));
+            }
+        }
+        libs.extend(base.libs.iter().cloned());
+        if eh == "wasm" && backend == Backend::Clang {
+            libs.push("-lunwind".to_string());
+        }
+        libs.extend(f.libs.iter().map(|l| format!("-l{}", l)));
+        libs.extend(emulate.iter().map(|e| format!("-lwasi-emulated-{}", e)));
+
+        Ok(Self { compile, link, libs })
+    }
+}
+
+fn is_js_output(output: &str) -> bool {
+    matches!(
+        Path::new(output).extension().and_then(|e| e.to_str()),
+        Some("js" | "mjs" | "html")
+    )
+}
+
+/// Flags that only mean something to the linker (emcc `-s` settings included).
+fn is_link_only(arg: &str) -> bool {
+    arg.starts_with("-Wl,")
+        || arg.starts_with("-l")
+        || arg.starts_with("-mexec-model")
+        || arg.starts_with("-nostdlib")
+        || arg == "--no-entry"
+        || (arg.starts_with("-s") && arg[2..].starts_with(|c: char| c.is_ascii_uppercase()))
+}
+
+/// Requested EH model; anything but "none" needs a hosted C++ build.
+fn eh_model(req: &BuildRequest<'_>) -> Result<&'static str> {
+    let eh = match req.flags.eh.as_str() {
+        "" | "none" => "none",
+        "emulated" => "emulated",
+        "wasm" => "wasm",
+        other => return Err(anyhow!("invalid --eh '{}' (use none, emulated or wasm)", other)),
+    };
+    if eh != "none" && req.lang == Lang::C {
+        return Err(anyhow!("--eh={} applies to C++ sources only", eh));
+    }
+    if eh != "none" && req.minimal {
+        return Err(anyhow!("--eh={} needs libc++abi; use --wasi (freestanding C++ has no runtime)", eh));
+    }
+    Ok(eh)
+}
+
+/// `<sysroot>/lib/<triple>/eh` for the triple in `--target=`, if the SDK has it.
+fn eh_lib_dir(sysroot: &str, flags: &[String]) -> Option<String> {
+    let triple = flags
+        .iter()
+        .find_map(|f| f.strip_prefix("--target="))
+        .unwrap_or("wasm32-wasi");
+    // older SDKs lay out wasm32-wasi, newer ones wasm32-wasip1
+    let mut triples = vec![triple];
+    if triple == "wasm32-wasi" {
+        triples.push("wasm32-wasip1");
+    }
+    triples
+        .iter()
+        .map(|t| Path::new(sysroot).join("lib").join(t).join("eh"))
+        .find(|p| p.is_dir())
+        .map(|p| p.display().to_string())
+}
+
+/// Run and time one command, recording it in `commands`; with `echo` print it first.
+pub(crate) fn run_step(commands: &mut Vec<CommandRecord>, argv: Vec<String>, echo: bool) -> Result<()> {
+    if echo {
+        eprintln!("🔹 {}", shell_join(&argv));
+    }
+    let started = Instant::now();
+    let status = SysCommand::new(&argv[0])
+        .args(&argv[1..])
+        .status()
+        .map_err(|e| anyhow!("Failed to run {}: {}", argv[0], e))?;
+    commands.push(CommandRecord {
+        argv: argv.clone(),
+        status: status.code(),
+        duration_ms: started.elapsed().as_millis(),
+    });
+    if !status.success() {
+        return Err(anyhow!("{} failed: {}", argv[0], shell_join(&argv)));
+    }
+    Ok(())
+}
+
+/// Files each object was built from, per the compiler's `-MMD` depfiles (incremental builds).
+pub fn object_deps(objects: &[String]) -> Vec<PathBuf> {
+    let mut deps = Vec::new();
+    for obj in objects {
+        let Ok(text) = std::fs::read_to_string(format!("{}.d", obj)) else { continue };
+        for token in text.split_whitespace() {
+            if token == "\\" || token.ends_with(':') {
+                continue;
+            }
+            let path = PathBuf::from(token);
+            if !deps.contains(&path) {
+                deps.push(path);
+            }
+        }
+    }
+    deps
+}
+
+/// `<cache>/didspace/obj/<stem>-<hash of the output path>` in the per-user cache
+/// (`$XDG_CACHE_HOME`, `%LOCALAPPDATA%`, else `~/.cache`): stable per output, so
+/// `--watch` rebuilds still find their objects, but outside the source tree and out
+/// of a shared temp dir other users could plant files in. With no home at all, a
+/// fresh private temp dir.
+fn cache_obj_dir(output: &str) -> Result<PathBuf> {
+    use std::hash::{Hash, Hasher};
+    let cache = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|d| !d.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".cache")));
+    let Some(cache) = cache else {
+        return Ok(tempfile::Builder::new().prefix("didspace-obj-").tempdir()?.keep());
+    };
+    let abs = std::path::absolute(output)?;
+    let mut h = std::collections::hash_map::DefaultHasher::new();
+    abs.hash(&mut h);
+    let stem = abs.file_stem().and_then(|s| s.to_str()).unwrap_or("out");
+    Ok(cache.join("didspace").join("obj").join(format!("{}-{:016x}", stem, h.finish())))
+}
+
+/// An object can be reused when it was compiled with the same command line and is newer
+/// than its source and every header in its depfile.
+fn up_to_date(obj: &str, cmd_file: &str, src: &str, argv: &[String]) -> bool {
+    let mtime = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+    let Some(built) = mtime(Path::new(obj)) else { return false };
+    if std::fs::read_to_string(cmd_file).ok().as_deref() != Some(shell_join(argv).as_str()) {
+        return false;
+    }
+    let mut inputs = object_deps(&[obj.to_string()]);
+    inputs.push(PathBuf::from(src));
+    inputs.iter().all(|p| mtime(p).is_some_and(|t| t <= built))
+}
+
+pub fn shell_join(argv: &[String]) -> String {
+    argv.iter()
+        .map(|a| {
+            if a.is_empty() || a.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'') {
+                format!("'{}'", a.replace('\'', r"'\''"))
+            } else {
+                a.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,149 @@
+use std::io::{BufRead, BufReader};
+use std::process::{Command as SysCommand, Stdio};
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+use crate::report::Section;
+
+pub const RUST_TARGETS: &[&str] = &["wasm32-wasip1", "wasm32-wasip2", "wasm32-unknown-unknown"];
+
+pub struct CargoOptions<'a> {
+    pub manifest_path: Option<&'a str>,
+    /// Full triple or the short form (`wasip1`, `wasip2`, `unknown-unknown`)
+    pub target: &'a str,
+    pub profile: Option<&'a str>,
+    pub package: Option<&'a str>,
+    pub bin: Option<&'a str>,
+    /// Print the cargo command before running it
+    pub echo: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CargoBuildReport {
+    pub target: String,
+    pub profile: String,
+    pub argv: Vec<String>,
+    pub artifact: String,
+    /// Every `.wasm` cargo reported, including the chosen one
+    pub candidates: Vec<String>,
+    pub duration_ms: u128,
+}
+
+impl CargoBuildReport {
+    pub fn to_section(&self) -> Section {
+        let mut s = Section::new(format!("Cargo build ({} / {})", self.target, self.profile))
+            .kv("command", self.argv.join(" "))
+            .kv("artifact", &self.artifact);
+        if self.candidates.len() > 1 {
+            s = s.kv("other artifacts", self.candidates.len() - 1);
+        }
+        s.kv("elapsed", format!("{} ms", self.duration_ms))
+    }
+}
+
+/// Run `cargo build` for a wasm target and locate the produced `.wasm`
+/// from cargo's JSON messages (no guessing at target/ layout).
+pub fn cargo_build(opts: CargoOptions<'_>) -> Result<CargoBuildReport> {
+    let target = normalize_target(opts.target)?;
+
+    let mut argv: Vec<String> = vec![
+        "cargo".into(),
+        "build".into(),
+        "--message-format=json-render-diagnostics".into(),
+        "--target".into(),
+        target.clone(),
+    ];
+    if let Some(p) = opts.profile {
+        argv.extend(["--profile".into(), p.to_string()]);
+    }
+    if let Some(m) = opts.manifest_path {
+        argv.extend(["--manifest-path".into(), m.to_string()]);
+    }
+    if let Some(p) = opts.package {
+        argv.extend(["-p".into(), p.to_string()]);
+    }
+    if let Some(b) = opts.bin {
+        argv.extend(["--bin".into(), b.to_string()]);
+    }
+
+    if opts.echo {
+        eprintln!("🔹 {}", argv.join(" "));
+    }
+    let started = Instant::now();
+    // diagnostics are rendered to stderr by cargo; stdout carries only JSON
+    let mut child = SysCommand::new(&argv[0])
+        .args(&argv[1..])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| anyhow!("Failed to run cargo: {}", e))?;
+
+    let stdout = child.stdout.take().ok_or_else(|| anyhow!("cargo stdout not captured"))?;
+    let mut candidates = Vec::new();
+    for line in BufReader::new(stdout).lines() {
+        let line = line?;
+        let Ok(msg) = serde_json::from_str::<serde_json::Value>(&line) else {
+            continue;
+        };
+        if msg["reason"] != "compiler-artifact" {
+            continue;
+        }
+        for f in msg["filenames"].as_array().into_iter().flatten() {
+            if let Some(f) = f.as_str()
+                && f.ends_with(".wasm")
+            {
+                candidates.push(f.to_string());
+            }
+        }
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow!("cargo build failed ({})", status));
+    }
+
+    let artifact = match candidates.as_slice() {
+        [] => {
+            return Err(anyhow!(
+                "cargo produced no .wasm artifact for {} (is the crate a bin or a cdylib?)",
+                target
+            ));
+        }
+        [one] => one.clone(),
+        _ => match opts.bin {
+            Some(bin) => candidates
+                .iter()
+                .find(|c| c.ends_with(&format!("/{}.wasm", bin)))
+                .cloned()
+                .ok_or_else(|| anyhow!("no artifact named {}.wasm among: {}", bin, candidates.join(", ")))?,
+            None => {
+                return Err(anyhow!(
+                    "cargo produced several .wasm artifacts, pick one with --bin or -p: {}",
+                    candidates.join(", ")
+                ));
+            }
+        },
+    };
+
+    Ok(CargoBuildReport {
+        target,
+        profile: opts.profile.unwrap_or("dev").to_string(),
+        argv,
+        artifact,
+        candidates,
+        duration_ms: started.elapsed().as_millis(),
+    })
+}
+
+// ---------- helpers ----------
+
+fn normalize_target(t: &str) -> Result<String> {
+    let full = if t.starts_with("wasm32-") { t.to_string() } else { format!("wasm32-{}", t) };
+    if RUST_TARGETS.contains(&full.as_str()) {
+        Ok(full)
+    } else {
+        Err(anyhow!("unsupported target '{}' (use {})", t, RUST_TARGETS.join(", ")))
+    }
+}
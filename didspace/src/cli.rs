@@ -1,5 +1,6 @@
 // src/cli.rs
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use didspace::builder::BuildFlags;
 
 #[derive(Parser)]
 #[command(name = "didspace", version = "1.0", about = "WASM/WAT Translator")]
@@ -7,6 +8,10 @@ pub struct Cli {
     #[arg(long, global = true)]
     pub report: Option<String>,
 
+    /// Project config file (default: didspace.toml searched upward from the working dir)
+    #[arg(long, global = true)]
+    pub config: Option<String>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -24,10 +29,80 @@ pub enum Commands {
     WasmWat { input: String, output: String },
 
     /// Convert WAT to WASM
-    WatWasm { input: String, output: String },
+    WatWasm {
+        input: String,
+        output: String,
+
+        /// Also validate the module; type errors point at the WAT line
+        #[arg(long)]
+        validate: bool,
+
+        /// Recompile on every change and print what changed in the output
+        #[arg(long)]
+        watch: bool,
+    },
+
+    /// Reformat WAT files in place (indentation, numeric literals, optional folding)
+    Fmt {
+        #[arg(required = true, value_name = "FILE")]
+        files: Vec<String>,
+
+        /// Rewrite function bodies as folded S-expressions
+        #[arg(long, conflicts_with = "unfold")]
+        fold: bool,
+
+        /// Rewrite function bodies as a flat instruction stream
+        #[arg(long)]
+        unfold: bool,
+
+        /// Don't write anything; fail if a file is not already formatted
+        #[arg(long)]
+        check: bool,
+
+        /// Print the formatted text instead of writing it back
+        #[arg(long, conflicts_with = "check")]
+        stdout: bool,
+    },
+
+    /// Check that wasm → WAT → wasm loses nothing (.wasm) or that printing is idempotent (.wat)
+    Roundtrip {
+        #[arg(value_name = "FILE")]
+        file: String,
+    },
 
     /// Convert WASM to C
-    WasmC { input: String, output: String },
+    WasmC {
+        input: String,
+        output: String,
+
+        /// Also compile the C with wasm-rt-impl.c and a generated main.c into a native executable
+        #[arg(long)]
+        build: bool,
+
+        /// Native executable path (default: output without extension)
+        #[arg(long, requires = "build")]
+        exe: Option<String>,
+
+        /// Export the harness calls instead of _start (arguments are zero)
+        #[arg(long, requires = "build")]
+        invoke: Option<String>,
+
+        /// Host C compiler (default: $DIDSPACE_NATIVE_CC, $CC, config compilers.native, cc)
+        #[arg(long, requires = "build")]
+        cc: Option<String>,
+
+        /// wabt's wasm2c/ directory with wasm-rt-impl.c (default: discovered)
+        #[arg(long, requires = "build")]
+        wasm2c_runtime: Option<String>,
+
+        /// Sanitizers for the native build, e.g. address,undefined
+        #[arg(long, requires = "build")]
+        sanitize: Option<String>,
+
+        /// Optimization level for the native build (default 0)
+        #[arg(short = 'O', long = "opt", requires = "build")]
+        opt_level: Option<String>,
+    },
 
     /// Convert C to WASM
     C2Wasm {
@@ -36,6 +111,9 @@ pub enum Commands {
         #[arg(long)] minimal: bool,
         #[arg(long)] wasi: bool,
         #[arg(long)] wasi_sysroot: Option<String>,
+        /// Rebuild incrementally on every source/header change and print the delta
+        #[arg(long)] watch: bool,
+        #[command(flatten)] build: BuildFlags,
     },
 
     /// Convert C to WAT (via WASM)
@@ -45,28 +123,90 @@ pub enum Commands {
         #[arg(long)] minimal: bool,
         #[arg(long)] wasi: bool,
         #[arg(long)] wasi_sysroot: Option<String>,
+        #[command(flatten)] build: BuildFlags,
     },
 
     /// Convert C++ to WASM
     Cpp2Wasm {
         input: String,
         output: String,
+        #[arg(long)] minimal: bool,
         #[arg(long)] wasi: bool,
         #[arg(long)] wasi_sysroot: Option<String>,
+        /// Rebuild incrementally on every source/header change and print the delta
+        #[arg(long)] watch: bool,
+        #[command(flatten)] build: BuildFlags,
     },
 
     /// Convert C++ to WAT
     Cpp2Wat {
         input: String,
         output: String,
+        #[arg(long)] minimal: bool,
         #[arg(long)] wasi: bool,
         #[arg(long)] wasi_sysroot: Option<String>,
+        #[command(flatten)] build: BuildFlags,
+    },
+
+    /// Build several C/C++ sources into one WASM module
+    Build {
+        /// Source files (C++ if any has a .cpp/.cc/.cxx extension)
+        #[arg(required = true)]
+        sources: Vec<String>,
+        #[arg(short, long)]
+        output: String,
+        #[arg(long)] minimal: bool,
+        #[arg(long)] wasi: bool,
+        #[arg(long)] wasi_sysroot: Option<String>,
+        #[command(flatten)] build: BuildFlags,
+    },
+
+    /// Build a Rust crate for a wasm target, then analyze and doctor the result
+    CargoBuild {
+        /// Path to the crate's Cargo.toml (default: cargo's own lookup)
+        #[arg(long)]
+        manifest_path: Option<String>,
+
+        /// wasm32-wasip1 | wasm32-wasip2 | wasm32-unknown-unknown (the wasm32- prefix is optional)
+        #[arg(long, default_value = "wasm32-wasip1")]
+        target: String,
+
+        /// Cargo profile (dev, release or a custom one)
+        #[arg(long)]
+        profile: Option<String>,
+
+        /// Package to build in a workspace
+        #[arg(short = 'p', long)]
+        package: Option<String>,
+
+        /// Binary target to build (also picks the artifact when there are several)
+        #[arg(long)]
+        bin: Option<String>,
+
+        #[arg(long)]
+        wasi_sysroot: Option<String>,
+
+        /// Max imports/exports listed by doctor (default: config doctor.max_list or 20)
+        #[arg(long)]
+        max_list: Option<usize>,
+
+        /// Also validate the module with every proposal enabled
+        #[arg(long)]
+        validate: bool,
+
+        /// Also run the lint rules (levels from [lint.rules] in didspace.toml)
+        #[arg(long)]
+        lint: bool,
     },
 
     /// Analyze a WASM binary and show detailed report
     Analyze {
-        #[arg(value_name = "FILE")]
-        file: String,
+        /// WASM files, directories or globs ('dist/**/*.wasm'); several run in batch mode
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<String>,
+
+        #[command(flatten)]
+        batch: BatchFlags,
     },
 
     /// Profile functions in a WASM bin for performance hot
@@ -89,14 +229,19 @@ pub enum Commands {
 
     /// Doctor WASI-Wasmtime analysis
     Doctor {
-        #[arg(value_name="FILE")]
-        file: String,
+        /// WASM files, directories or globs ('build/**/*.wasm'); several run in batch mode
+        #[arg(value_name = "FILE", required = true)]
+        files: Vec<String>,
+
+        #[command(flatten)]
+        batch: BatchFlags,
 
         #[arg(long)]
         wasi_sysroot: Option<String>,
 
-        #[arg(long, default_value_t = 20)]
-        max_list: usize,
+        /// Max imports/exports listed (default: config doctor.max_list or 20)
+        #[arg(long)]
+        max_list: Option<usize>,
 
         #[arg(long)]
         json: bool,
@@ -106,15 +251,76 @@ pub enum Commands {
 
         #[arg(long = "check-toolchain")]
         check_toolchain: bool,
+
+        /// Extra runtime profiles (TOML) merged over the built-in compat table
+        #[arg(long)]
+        compat_table: Option<String>,
+
+        /// C++ EH model the module was built with (none, emulated, wasm); checked against the binary
+        #[arg(long, value_parser = ["none", "emulated", "wasm"])]
+        expect_eh: Option<String>,
+
+        /// Instantiate and run the module in an embedded sandboxed runtime
+        #[arg(long)]
+        smoke: bool,
+
+        /// Export to call during --smoke (default: _start or wasi:cli/run)
+        #[arg(long, requires = "smoke")]
+        invoke: Option<String>,
+
+        /// Fuel budget for --smoke
+        #[arg(long, requires = "smoke", default_value_t = 100_000_000)]
+        fuel: u64,
+
+        /// Wall-clock limit for --smoke, in milliseconds
+        #[arg(long, requires = "smoke", default_value_t = 5000)]
+        timeout_ms: u64,
+    },
+
+    /// Run a module in the embedded runtime: `_start` / `wasi:cli/run`, or one export with typed arguments
+    Run {
+        /// .wasm or .wat
+        #[arg(value_name = "FILE")]
+        file: String,
+
+        /// Export to call; ARGS are parsed against its signature. `--invoke 'add(1, 2)'` also works
+        #[arg(long)]
+        invoke: Option<String>,
+
+        /// Preopen a host directory, HOST[::GUEST] (repeatable)
+        #[arg(long = "dir", value_name = "HOST[::GUEST]")]
+        dirs: Vec<String>,
+
+        /// Guest environment variable, NAME=VALUE or NAME to pass the host value (repeatable)
+        #[arg(long = "env", value_name = "NAME[=VALUE]")]
+        envs: Vec<String>,
+
+        /// Fuel budget
+        #[arg(long, default_value_t = 10_000_000_000)]
+        fuel: u64,
+
+        /// Wall-clock limit, in milliseconds
+        #[arg(long, default_value_t = 30_000)]
+        timeout_ms: u64,
+
+        /// Cap on linear memory, in MiB
+        #[arg(long, value_name = "MIB")]
+        max_memory: Option<usize>,
+
+        /// Program arguments, or the export's arguments with --invoke
+        #[arg(value_name = "ARGS", trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
     },
 
     Bindgen {
         #[arg(value_name="FILE")]
         file: String,
 
+        /// rust | python | c (core modules and components; c components are untyped), ts (components)
         #[arg(long, default_value="rust")]
         lang: String,
 
+        /// node for ts; wasmtime (default) or wasm2c (core modules) for c; wasmtime (default) or wasmer for rust core modules
         #[arg(long)]
         runtime: Option<String>,
 
@@ -122,14 +328,40 @@ pub enum Commands {
         #[arg(long)]
         wit: Option<String>,
 
-        /// World name to generate bindings for
+        /// World name to generate bindings for; names the generated API for core modules
+        /// (default: the file stem)
         #[arg(long)]
-        world: String,
+        world: Option<String>,
 
-        // Output file for generated bindings
-        //out: String,
+        /// Write the generated files into DIR (created if missing) instead of printing them
+        #[arg(long, value_name = "DIR")]
+        out: Option<String>,
     },
     
-    Repl,
+    Repl {
+        /// Run a command file without a prompt; exits 1 at the first failing line
+        #[arg(long, value_name = "SCRIPT")]
+        source: Option<String>,
+    },
+
+}
+
+/// Input handling shared by the commands that inspect modules: batch runs and watch mode.
+#[derive(Args, Debug, Clone, Default)]
+pub struct BatchFlags {
+    /// Also descend into subdirectories of directory inputs
+    #[arg(short = 'r', long)]
+    pub recursive: bool,
+
+    /// Combined report format (implies batch mode)
+    #[arg(long, value_parser = ["text", "json", "csv"])]
+    pub format: Option<String>,
+
+    /// Worker threads for batch mode (default: one per core)
+    #[arg(short = 'j', long)]
+    pub jobs: Option<usize>,
 
+    /// Re-run on every change to the (single) input and print what changed
+    #[arg(long)]
+    pub watch: bool,
 }
@@ -0,0 +1,394 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use wasmparser::{
+    CompositeInnerType, ConstExpr, DataKind, ElementItems, ElementKind, Operator, Parser, Payload, TypeRef, ValType,
+};
+
+use crate::report::Section;
+
+/// Built-in runtime profiles, shipped as an editable data file.
+const BUILTIN_TABLE: &str = include_str!("../data/runtimes.toml");
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RuntimeProfile {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub features: Vec<String>,
+    #[serde(default)]
+    pub flag_features: BTreeMap<String, String>,
+    #[serde(default)]
+    pub namespaces: Vec<String>,
+    #[serde(default)]
+    pub flag_namespaces: BTreeMap<String, String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProfileTable {
+    #[serde(default)]
+    runtime: Vec<RuntimeProfile>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompatReport {
+    pub features: Vec<String>,
+    pub namespaces: Vec<String>,
+    pub runtimes: Vec<CompatEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompatEntry {
+    pub runtime: String,
+    pub version: String,
+    pub verdict: String, // "yes" | "no" | "with-flags"
+    pub flags: Vec<String>,
+    pub reasons: Vec<String>,
+}
+
+/// Load the built-in profiles, then merge an optional user table on top.
+/// A user entry with the same name and version replaces the built-in one.
+pub fn load_profiles(extra_table: Option<&str>) -> Result<Vec<RuntimeProfile>> {
+    let mut profiles = toml::from_str::<ProfileTable>(BUILTIN_TABLE)
+        .map_err(|e| anyhow!("built-in runtime table is invalid: {}", e))?
+        .runtime;
+
+    if let Some(path) = extra_table {
+        let src = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read compat table '{}': {}", path, e))?;
+        let extra = toml::from_str::<ProfileTable>(&src)
+            .map_err(|e| anyhow!("Invalid compat table '{}': {}", path, e))?;
+        for p in extra.runtime {
+            profiles.retain(|q| !(q.name == p.name && q.version == p.version));
+            profiles.push(p);
+        }
+    }
+    Ok(profiles)
+}
+
+pub fn compat_report(bytes: &[u8], kind: &str, profiles: &[RuntimeProfile]) -> Result<CompatReport> {
+    let mut features = detect_features(bytes)?;
+    if kind == "component" {
+        features.insert("component-model".to_string());
+    }
+    let namespaces = detect_namespaces(bytes)?;
+
+    let runtimes = profiles
+        .iter()
+        .map(|p| evaluate(p, &features, &namespaces))
+        .collect();
+
+    Ok(CompatReport {
+        features: features.into_iter().collect(),
+        namespaces: namespaces.into_iter().collect(),
+        runtimes,
+    })
+}
+
+pub fn compat_section(c: &CompatReport) -> Section {
+    let mut s = Section::new("Runtime compatibility")
+        .kv("features", if c.features.is_empty() { "(mvp only)".to_string() } else { c.features.join(", ") })
+        .kv("namespaces", if c.namespaces.is_empty() { "(no imports)".to_string() } else { c.namespaces.join(", ") });
+    for r in &c.runtimes {
+        let level = match r.verdict.as_str() {
+            "yes" => "ok",
+            "with-flags" => "warn",
+            _ => "error",
+        };
+        s = s.finding_with(level, format!("{} {}: {}", r.runtime, r.version, r.verdict), r.reasons.clone());
+    }
+    s
+}
+
+// ---------- helpers ----------
+
+fn evaluate(p: &RuntimeProfile, features: &BTreeSet<String>, namespaces: &BTreeSet<String>) -> CompatEntry {
+    let mut flags = Vec::new();
+    let mut reasons = Vec::new();
+    let mut blocked = false;
+
+    for f in features {
+        if p.features.iter().any(|x| x == f) {
+            continue;
+        }
+        if let Some(flag) = p.flag_features.get(f) {
+            flags.push(flag.clone());
+            reasons.push(format!("feature {} needs: {}", f, flag));
+        } else {
+            blocked = true;
+            reasons.push(format!("feature {} is not supported", f));
+        }
+    }
+
+    for ns in namespaces {
+        if p.namespaces.iter().any(|x| x == ns) {
+            continue;
+        }
+        if let Some(how) = p.flag_namespaces.get(ns) {
+            flags.push(how.clone());
+            reasons.push(format!("imports from {} need: {}", ns, how));
+        } else {
+            blocked = true;
+            reasons.push(format!("imports from {} are not provided", ns));
+        }
+    }
+
+    if let Some(notes) = &p.notes
+        && !reasons.is_empty()
+    {
+        reasons.push(format!("note: {}", notes));
+    }
+
+    let verdict = if blocked {
+        "no"
+    } else if !flags.is_empty() {
+        "with-flags"
+    } else {
+        "yes"
+    };
+
+    flags.sort();
+    flags.dedup();
+
+    CompatEntry {
+        runtime: p.name.clone(),
+        version: p.version.clone(),
+        verdict: verdict.to_string(),
+        flags,
+        reasons,
+    }
+}
+
+/// Post-MVP proposals the module relies on, from types, imports and opcodes.
+/// A component's nested core modules are scanned too (their code runs on the
+/// host engine), with memories and tables counted per module.
+pub fn detect_features(bytes: &[u8]) -> Result<BTreeSet<String>> {
+    let mut found = BTreeSet::new();
+    // (memories, tables) per open module/component, innermost last
+    let mut counts: Vec<(usize, usize)> = Vec::new();
+
+    let memory = |found: &mut BTreeSet<String>, counts: &mut Vec<(usize, usize)>, ty: &wasmparser::MemoryType| {
+        if let Some(c) = counts.last_mut() {
+            c.0 += 1;
+        }
+        if ty.memory64 {
+            found.insert("memory64".to_string());
+        }
+        if ty.shared {
+            found.insert("threads".to_string());
+        }
+    };
+
+    for payload in Parser::new(0).parse_all(bytes) {
+        match payload? {
+            Payload::Version { .. } => counts.push((0, 0)),
+            Payload::End(_) => {
+                let (memories, tables) = counts.pop().unwrap_or_default();
+                if memories > 1 {
+                    found.insert("multi-memory".to_string());
+                }
+                if tables > 1 {
+                    found.insert("reference-types".to_string());
+                }
+            }
+            Payload::TypeSection(reader) => {
+                for group in reader {
+                    for sub in group?.into_types() {
+                        match &sub.composite_type.inner {
+                            CompositeInnerType::Func(f) => {
+                                if f.results().len() > 1 {
+                                    found.insert("multi-value".to_string());
+                                }
+                                if f.params().iter().chain(f.results()).any(|t| *t == ValType::V128) {
+                                    found.insert("simd".to_string());
+                                }
+                            }
+                            _ => {
+                                found.insert("gc".to_string());
+                            }
+                        }
+                    }
+                }
+            }
+            Payload::ImportSection(reader) => {
+                for im in reader {
+                    match im?.ty {
+                        TypeRef::Memory(ty) => memory(&mut found, &mut counts, &ty),
+                        TypeRef::Table(_) => {
+                            if let Some(c) = counts.last_mut() {
+                                c.1 += 1;
+                            }
+                        }
+                        TypeRef::Global(g) if g.mutable => {
+                            found.insert("mutable-globals".to_string());
+                        }
+                        TypeRef::Tag(_) => {
+                            found.insert("exceptions".to_string());
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Payload::MemorySection(reader) => {
+                for ty in reader {
+                    memory(&mut found, &mut counts, &ty?);
+                }
+            }
+            Payload::TableSection(reader) => {
+                if let Some(c) = counts.last_mut() {
+                    c.1 += reader.count() as usize;
+                }
+            }
+            Payload::TagSection(_) => {
+                found.insert("exceptions".to_string());
+            }
+            Payload::GlobalSection(reader) => {
+                for g in reader {
+                    const_expr_features(&mut found, &g?.init_expr)?;
+                }
+            }
+            Payload::ElementSection(reader) => {
+                for el in reader {
+                    let el = el?;
+                    if let ElementKind::Active { offset_expr, .. } = &el.kind {
+                        const_expr_features(&mut found, offset_expr)?;
+                    }
+                    if let ElementItems::Expressions(_, items) = el.items {
+                        for item in items {
+                            const_expr_features(&mut found, &item?)?;
+                        }
+                    }
+                }
+            }
+            Payload::DataSection(reader) => {
+                for data in reader {
+                    if let DataKind::Active { offset_expr, .. } = &data?.kind {
+                        const_expr_features(&mut found, offset_expr)?;
+                    }
+                }
+            }
+            Payload::CodeSectionEntry(body) => {
+                let mut ops = body.get_operators_reader()?;
+                while !ops.eof() {
+                    let name = format!("{:?}", ops.read()?);
+                    if let Some(f) = feature_of_operator(&name) {
+                        found.insert(f.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(found)
+}
+
+/// Global initializers and segment offsets: arithmetic there is extended-const;
+/// anything else classifies like it would in code (`ref.func` and the like).
+fn const_expr_features(found: &mut BTreeSet<String>, expr: &ConstExpr) -> Result<()> {
+    let mut ops = expr.get_operators_reader();
+    while !ops.eof() {
+        let op = ops.read()?;
+        let feature = match op {
+            Operator::I32Add | Operator::I32Sub | Operator::I32Mul
+            | Operator::I64Add | Operator::I64Sub | Operator::I64Mul => Some("extended-const"),
+            _ => feature_of_operator(&format!("{:?}", op)),
+        };
+        if let Some(f) = feature {
+            found.insert(f.to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Classify an operator by its `Debug` name (same naming `analyze` reports).
+fn feature_of_operator(name: &str) -> Option<&'static str> {
+    let name = name.split([' ', '{', '(']).next().unwrap_or(name);
+    let simd_prefixes = ["V128", "I8x16", "I16x8", "I32x4", "I64x2", "F32x4", "F64x2"];
+
+    if simd_prefixes.iter().any(|p| name.starts_with(p)) {
+        return Some(if name.contains("Relaxed") { "relaxed-simd" } else { "simd" });
+    }
+    if name.contains("Atomic") {
+        return Some("threads");
+    }
+    match name {
+        "ReturnCall" | "ReturnCallIndirect" | "ReturnCallRef" => Some("tail-call"),
+        "Try" | "Catch" | "CatchAll" | "Delegate" | "Throw" | "Rethrow" | "TryTable" | "ThrowRef" => {
+            Some("exceptions")
+        }
+        "MemoryCopy" | "MemoryFill" | "MemoryInit" | "DataDrop" | "TableCopy" | "TableInit" | "ElemDrop" => {
+            Some("bulk-memory")
+        }
+        "RefNull" | "RefIsNull" | "RefFunc" | "TableGet" | "TableSet" | "TableGrow" | "TableSize"
+        | "TableFill" | "TypedSelect" => Some("reference-types"),
+        "I32Extend8S" | "I32Extend16S" | "I64Extend8S" | "I64Extend16S" | "I64Extend32S" => Some("sign-ext"),
+        n if n.contains("TruncSat") => Some("nontrapping-fptoint"),
+        n if n.starts_with("Struct") || n.starts_with("Array") || n.starts_with("RefCast")
+            || n.starts_with("RefTest") || n.starts_with("RefI31") || n.starts_with("I31Get")
+            || n.starts_with("BrOnCast") || n.starts_with("AnyConvert") || n.starts_with("ExternConvert") => Some("gc"),
+        "CallRef" | "RefAsNonNull" | "BrOnNull" | "BrOnNonNull" => Some("function-references"),
+        _ => None,
+    }
+}
+
+/// Import namespaces grouped into host families (WASI flavors, JS glue, ...).
+/// For components only the top-level imports count: the inner and adapter
+/// modules' imports are satisfied inside the component, not by the host.
+pub fn detect_namespaces(bytes: &[u8]) -> Result<BTreeSet<String>> {
+    let mut found = BTreeSet::new();
+    // 1 = the outermost module/component; nested ones sit deeper
+    let mut depth = 0usize;
+
+    for payload in Parser::new(0).parse_all(bytes) {
+        match payload? {
+            Payload::Version { .. } => depth += 1,
+            Payload::End(_) => depth -= 1,
+            _ if depth > 1 => {}
+            Payload::ImportSection(reader) => {
+                for im in reader {
+                    let im = im?;
+                    found.insert(core_namespace(im.module, im.name));
+                }
+            }
+            Payload::ComponentImportSection(reader) => {
+                for im in reader {
+                    found.insert(component_namespace(im?.name.0));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(found)
+}
+
+/// Modules outside the known families come back as `other:<module>`; no
+/// built-in runtime provides those, so they block until a profile lists them.
+fn core_namespace(module: &str, name: &str) -> String {
+    let family = match module {
+        "wasi_snapshot_preview1" => "wasi-preview1",
+        "wasi_unstable" => "wasi-preview0",
+        "wasi" if name == "thread-spawn" => "wasi-threads",
+        "__wbindgen_placeholder__" | "__wbindgen_externref_xform__" | "wbg" => "wasm-bindgen",
+        "env" if name.starts_with("emscripten_")
+            || name.starts_with("_emscripten_")
+            || name.starts_with("__syscall_")
+            || name.starts_with("invoke_") => "emscripten",
+        "env" => "env",
+        _ => return format!("other:{}", module),
+    };
+    family.to_string()
+}
+
+/// `wasi:*` interfaces are WASI 0.2; any other interface is `<pkg>/<iface>`
+/// without its version, and a plain function or instance name is `other:<name>`.
+fn component_namespace(name: &str) -> String {
+    if name.starts_with("wasi:") {
+        return "wasi-preview2".to_string();
+    }
+    let unversioned = name.split('@').next().unwrap_or(name);
+    if unversioned.contains(':') { unversioned.to_string() } else { format!("other:{}", unversioned) }
+}
@@ -0,0 +1,353 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+use crate::report::Format;
+
+pub const CONFIG_FILE: &str = "didspace.toml";
+
+/// Project configuration (`didspace.toml`), searched upward from the working directory.
+///
+/// Precedence when commands resolve a setting: CLI flag > environment > this file > built-in default.
+/// Relative paths in the file are resolved against the directory that contains it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub wasi_sysroot: Option<String>,
+    pub compilers: Compilers,
+    pub mode: Modes,
+    pub output: Output,
+    pub doctor: DoctorConfig,
+    pub lint: LintConfig,
+    pub report: ReportConfig,
+
+    /// Path of the file this config was read from (None = built-in defaults)
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Compilers {
+    pub c: Option<String>,
+    pub cxx: Option<String>,
+    pub emcc: Option<String>,
+    pub emxx: Option<String>,
+    /// Host C compiler for wasm2c output
+    pub native: Option<String>,
+}
+
+/// Per-mode compile flags. `flags` go before the input, `libs` after it,
+/// `emulate` selects libwasi-emulated-* (signal, mman, getpid, process-clocks).
+/// A mode that is present replaces the built-in flags for that mode entirely.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Modes {
+    pub minimal: Option<ModeFlags>,
+    pub wasi: Option<ModeFlags>,
+    pub cxx: Option<ModeFlags>,
+    /// Freestanding C++ (no libc/libc++), used by `--minimal` on C++ sources
+    #[serde(rename = "cxx-minimal")]
+    pub cxx_minimal: Option<ModeFlags>,
+    /// Emscripten standalone wasm without an entry point
+    #[serde(rename = "em-minimal")]
+    pub em_minimal: Option<ModeFlags>,
+    /// Emscripten standalone wasm importing only WASI
+    #[serde(rename = "em-wasi")]
+    pub em_wasi: Option<ModeFlags>,
+    /// Emscripten with JS glue (output ends in .js/.mjs/.html)
+    #[serde(rename = "em-js")]
+    pub em_js: Option<ModeFlags>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModeFlags {
+    pub flags: Vec<String>,
+    pub libs: Vec<String>,
+    pub emulate: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Output {
+    /// Directory for build outputs given as bare relative paths
+    pub dir: Option<String>,
+    /// Directory for `--report` files given as bare relative paths
+    pub report_dir: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DoctorConfig {
+    pub max_list: Option<usize>,
+    pub compat_table: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LintConfig {
+    /// rule name → "off" | "warn" | "error"
+    pub rules: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ReportConfig {
+    /// Default format of reports printed to stdout ("text" | "json" | "markdown" | "html")
+    pub format: Option<String>,
+    pub pretty: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Minimal,
+    Wasi,
+    Cxx,
+    CxxMinimal,
+    EmMinimal,
+    EmWasi,
+    EmJs,
+}
+
+impl Config {
+    /// Load `explicit` (from `--config`), else `$DIDSPACE_CONFIG`, else the first
+    /// `didspace.toml` found walking up from the working directory.
+    /// No file at all is not an error: built-in defaults apply.
+    pub fn load(explicit: Option<&str>) -> Result<Self> {
+        let path = match explicit {
+            Some(p) => Some(PathBuf::from(p)),
+            None => match std::env::var("DIDSPACE_CONFIG") {
+                Ok(p) if !p.trim().is_empty() => Some(PathBuf::from(p)),
+                _ => find_upward(&std::env::current_dir()?),
+            },
+        };
+
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+
+        let src = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow!("Failed to read config '{}': {}", path.display(), e))?;
+        let mut cfg: Config = toml::from_str(&src)
+            .map_err(|e| anyhow!("Invalid config '{}': {}", path.display(), e))?;
+        cfg.source = Some(path);
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    fn validate(&self) -> Result<()> {
+        for (rule, level) in &self.lint.rules {
+            if !crate::lint::RULES.iter().any(|(name, _)| name == rule) {
+                return Err(anyhow!("unknown lint rule '{}'", rule));
+            }
+            if !matches!(level.as_str(), "off" | "warn" | "error") {
+                return Err(anyhow!(
+                    "lint rule '{}': level must be off, warn or error (got '{}')",
+                    rule, level
+                ));
+            }
+        }
+        if let Some(fmt) = &self.report.format
+            && Format::parse(fmt).is_none()
+        {
+            return Err(anyhow!("report.format must be text, json, markdown or html (got '{}')", fmt));
+        }
+        Ok(())
+    }
+
+    /// Resolve a path from the config file against the file's directory.
+    fn resolve(&self, p: &str) -> String {
+        match self.source.as_ref().and_then(|s| s.parent()) {
+            Some(dir) if Path::new(p).is_relative() && !dir.as_os_str().is_empty() => {
+                dir.join(p).display().to_string()
+            }
+            _ => p.to_string(),
+        }
+    }
+
+    /// `--wasi-sysroot` > `$WASI_SYSROOT` > `$WASI_SDK_PATH/share/wasi-sysroot` > config.
+    pub fn sysroot(&self, cli: Option<&str>) -> Option<String> {
+        if let Some(p) = cli {
+            return Some(p.to_string());
+        }
+        if let Ok(p) = std::env::var("WASI_SYSROOT")
+            && !p.trim().is_empty()
+        {
+            return Some(p);
+        }
+        if let Ok(sdk) = std::env::var("WASI_SDK_PATH")
+            && !sdk.trim().is_empty()
+        {
+            return Some(Path::new(&sdk).join("share").join("wasi-sysroot").display().to_string());
+        }
+        self.wasi_sysroot.as_deref().map(|p| self.resolve(p))
+    }
+
+    /// `$DIDSPACE_CC` > config > `wasm32-wasi-clang`.
+    pub fn c_compiler(&self) -> String {
+        env_nonempty("DIDSPACE_CC")
+            .or_else(|| self.compilers.c.as_deref().map(|p| self.resolve_tool(p)))
+            .unwrap_or_else(|| "wasm32-wasi-clang".to_string())
+    }
+
+    /// `$DIDSPACE_CXX` > config > `wasm32-wasi-clang++`.
+    pub fn cxx_compiler(&self) -> String {
+        env_nonempty("DIDSPACE_CXX")
+            .or_else(|| self.compilers.cxx.as_deref().map(|p| self.resolve_tool(p)))
+            .unwrap_or_else(|| "wasm32-wasi-clang++".to_string())
+    }
+
+    /// `$DIDSPACE_EMCC` > config > `emcc`.
+    pub fn emcc(&self) -> String {
+        env_nonempty("DIDSPACE_EMCC")
+            .or_else(|| self.compilers.emcc.as_deref().map(|p| self.resolve_tool(p)))
+            .unwrap_or_else(|| "emcc".to_string())
+    }
+
+    /// `$DIDSPACE_EMXX` > config > `em++`.
+    pub fn emxx(&self) -> String {
+        env_nonempty("DIDSPACE_EMXX")
+            .or_else(|| self.compilers.emxx.as_deref().map(|p| self.resolve_tool(p)))
+            .unwrap_or_else(|| "em++".to_string())
+    }
+
+    /// `$DIDSPACE_NATIVE_CC` > `$CC` > config > `cc`.
+    pub fn native_cc(&self) -> String {
+        env_nonempty("DIDSPACE_NATIVE_CC")
+            .or_else(|| env_nonempty("CC"))
+            .or_else(|| self.compilers.native.as_deref().map(|p| self.resolve_tool(p)))
+            .unwrap_or_else(|| "cc".to_string())
+    }
+
+    /// Bare names ("clang") are looked up in PATH; anything with a separator is a path.
+    fn resolve_tool(&self, p: &str) -> String {
+        if p.contains('/') || p.contains('\\') {
+            self.resolve(p)
+        } else {
+            p.to_string()
+        }
+    }
+
+    /// Flags for a compile mode: the config section if present, else the built-in defaults.
+    pub fn mode_flags(&self, mode: Mode) -> ModeFlags {
+        let configured = match mode {
+            Mode::Minimal => &self.mode.minimal,
+            Mode::Wasi => &self.mode.wasi,
+            Mode::Cxx => &self.mode.cxx,
+            Mode::CxxMinimal => &self.mode.cxx_minimal,
+            Mode::EmMinimal => &self.mode.em_minimal,
+            Mode::EmWasi => &self.mode.em_wasi,
+            Mode::EmJs => &self.mode.em_js,
+        };
+        configured.clone().unwrap_or_else(|| default_mode_flags(mode))
+    }
+
+    /// `output.dir` resolved against the config file, if set.
+    pub fn output_dir(&self) -> Option<String> {
+        self.output.dir.as_deref().map(|d| self.resolve(d))
+    }
+
+    /// Place a bare relative output path under `output.dir` (created on demand).
+    pub fn output_path(&self, output: &str) -> Result<String> {
+        place_under(self.output.dir.as_deref().map(|d| self.resolve(d)), output)
+    }
+
+    /// Place a bare relative report path under `output.report_dir` (created on demand).
+    pub fn report_path(&self, report: &str) -> Result<String> {
+        place_under(self.output.report_dir.as_deref().map(|d| self.resolve(d)), report)
+    }
+
+    pub fn max_list(&self, cli: Option<usize>) -> usize {
+        cli.or(self.doctor.max_list).unwrap_or(20)
+    }
+
+    pub fn compat_table(&self, cli: Option<&str>) -> Option<String> {
+        cli.map(str::to_string)
+            .or_else(|| self.doctor.compat_table.as_deref().map(|p| self.resolve(p)))
+    }
+
+    /// Format for reports printed to stdout: JSON if `--json` was given, else the config default.
+    pub fn report_format(&self, cli_json: bool) -> Format {
+        if cli_json {
+            return Format::Json;
+        }
+        self.report.format.as_deref().and_then(Format::parse).unwrap_or(Format::Text)
+    }
+
+    pub fn wants_pretty(&self, cli_pretty: bool) -> bool {
+        cli_pretty || self.report.pretty.unwrap_or(false)
+    }
+}
+
+pub fn default_mode_flags(mode: Mode) -> ModeFlags {
+    let v = |xs: &[&str]| xs.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    match mode {
+        Mode::Minimal => ModeFlags {
+            flags: v(&["--target=wasm32", "-fno-exceptions", "-fno-rtti", "-Wl,--no-entry", "-Wl,--export-all"]),
+            libs: Vec::new(),
+            emulate: Vec::new(),
+        },
+        Mode::Wasi => ModeFlags {
+            flags: v(&["--target=wasm32-wasi"]),
+            libs: Vec::new(),
+            emulate: v(&["signal", "mman"]),
+        },
+        Mode::Cxx => ModeFlags {
+            flags: v(&["--target=wasm32-wasi", "-fno-exceptions", "-fno-rtti"]),
+            libs: v(&["-lc++", "-lc++abi"]),
+            emulate: v(&["signal", "mman"]),
+        },
+        Mode::CxxMinimal => ModeFlags {
+            flags: v(&[
+                "--target=wasm32", "-fno-exceptions", "-fno-rtti", "-nostdlib++",
+                "-Wl,--no-entry", "-Wl,--export-all",
+            ]),
+            libs: Vec::new(),
+            emulate: Vec::new(),
+        },
+        // emcc links libc/libc++ and its own emulation layer itself
+        Mode::EmMinimal => ModeFlags {
+            flags: v(&["-fno-exceptions", "-sSTANDALONE_WASM", "--no-entry", "-Wl,--export-all"]),
+            libs: Vec::new(),
+            emulate: Vec::new(),
+        },
+        Mode::EmWasi => ModeFlags {
+            flags: v(&["-fno-exceptions", "-sSTANDALONE_WASM"]),
+            libs: Vec::new(),
+            emulate: Vec::new(),
+        },
+        Mode::EmJs => ModeFlags {
+            flags: v(&["-fno-exceptions"]),
+            libs: Vec::new(),
+            emulate: Vec::new(),
+        },
+    }
+}
+
+// ---------- helpers ----------
+
+fn find_upward(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(CONFIG_FILE))
+        .find(|p| p.is_file())
+}
+
+fn env_nonempty(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|v| !v.trim().is_empty())
+}
+
+fn place_under(dir: Option<String>, path: &str) -> Result<String> {
+    let p = Path::new(path);
+    let bare = p.is_relative() && p.parent().is_none_or(|d| d.as_os_str().is_empty());
+    match dir {
+        Some(dir) if bare => {
+            std::fs::create_dir_all(&dir)?;
+            Ok(Path::new(&dir).join(p).display().to_string())
+        }
+        _ => Ok(path.to_string()),
+    }
+}
@@ -1,10 +1,197 @@
-use wabt::wat2wasm;
-use wasmprinter;
+use std::fmt;
+
+use serde::Serialize;
+use wasmparser::{Parser, Payload, Validator, WasmFeatures};
+use wast::core::{FuncKind, ModuleField, ModuleKind};
+use wast::parser::{self, ParseBuffer};
+use wast::token::Span;
+use wast::Wat;
+
+/// One WAT front end for the CLI and the REPL: parse (wast), encode, optionally validate,
+/// and report every failure against the WAT source.
+#[derive(Debug, Serialize)]
+pub struct WatDiagnostic {
+    pub file: String,
+    /// 1-based; 0 when the error could not be mapped to the source
+    pub line: usize,
+    pub col: usize,
+    pub phase: String, // "parse" | "validate"
+    pub message: String,
+    pub snippet: String,
+    pub hint: Option<String>,
+}
+
+impl fmt::Display for WatDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "error: {}", self.message)?;
+        if self.line > 0 {
+            writeln!(f, "  --> {}:{}:{}", self.file, self.line, self.col)?;
+        } else {
+            writeln!(f, "  --> {}", self.file)?;
+        }
+        if !self.snippet.is_empty() {
+            write!(f, "{}", self.snippet)?;
+        }
+        if let Some(hint) = &self.hint {
+            writeln!(f, "   = hint: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for WatDiagnostic {}
+
+/// Compile WAT text to a binary module. With `validate`, the result is also run through
+/// the validator and type errors point at the offending instruction in the source.
+pub fn compile_wat(src: &str, file: &str, validate: bool) -> Result<Vec<u8>, Box<WatDiagnostic>> {
+    let mut buf = ParseBuffer::new(src).map_err(|e| from_wast(src, file, &e))?;
+    buf.track_instr_spans(true);
+    let mut wat = parser::parse::<Wat>(&buf).map_err(|e| from_wast(src, file, &e))?;
+
+    let spans = func_spans(&wat);
+    let bytes = wat.encode().map_err(|e| from_wast(src, file, &e))?;
+
+    if validate
+        && let Err(e) = Validator::new_with_features(WasmFeatures::all()).validate_all(&bytes)
+    {
+        let span = span_for_offset(&bytes, e.offset(), &spans);
+        let message = e.message().to_string();
+        return Err(Box::new(match span {
+            Some(span) => diagnostic(src, file, span.offset(), "validate", message),
+            None => WatDiagnostic {
+                file: file.to_string(),
+                line: 0,
+                col: 0,
+                phase: "validate".to_string(),
+                hint: hint_for(&message),
+                message: format!("{} (at binary offset 0x{:x})", message, e.offset()),
+                snippet: String::new(),
+            },
+        }));
+    }
+    Ok(bytes)
+}
 
 pub fn wat_to_wasm(wat: &str) -> Result<Vec<u8>, String> {
-    wat2wasm(wat).map_err(|e| e.to_string())
+    compile_wat(wat, "<repl>", true).map_err(|e| e.to_string())
 }
 
 pub fn wasm_to_wat(bytes: &[u8]) -> Result<String, String> {
     wasmprinter::print_bytes(bytes).map_err(|e| e.to_string())
 }
+
+// ---------- helpers ----------
+
+/// Span of each defined function and of each of its instructions, in code-section order.
+struct FuncSpans {
+    func: Span,
+    instrs: Option<Box<[Span]>>,
+}
+
+fn func_spans(wat: &Wat<'_>) -> Vec<FuncSpans> {
+    let Wat::Module(module) = wat else {
+        return Vec::new();
+    };
+    let ModuleKind::Text(fields) = &module.kind else {
+        return Vec::new();
+    };
+    fields
+        .iter()
+        .filter_map(|field| match field {
+            ModuleField::Func(f) => match &f.kind {
+                FuncKind::Inline { expression, .. } => Some(FuncSpans {
+                    func: f.span,
+                    instrs: expression.instr_spans.clone(),
+                }),
+                FuncKind::Import(..) => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Map a binary offset inside the code section back to the WAT instruction
+/// (wast encodes instructions one-to-one, so the operator index is the instr index).
+fn span_for_offset(bytes: &[u8], offset: usize, spans: &[FuncSpans]) -> Option<Span> {
+    let mut index = 0usize;
+    for payload in Parser::new(0).parse_all(bytes) {
+        let Ok(Payload::CodeSectionEntry(body)) = payload else {
+            continue;
+        };
+        let range = body.range();
+        let this = index;
+        index += 1;
+        if !range.contains(&offset) {
+            continue;
+        }
+        let f = spans.get(this)?;
+        let Ok(mut ops) = body.get_operators_reader() else {
+            return Some(f.func);
+        };
+        let mut nth = None;
+        while !ops.eof() && ops.original_position() <= offset {
+            nth = Some(nth.map_or(0, |n: usize| n + 1));
+            if ops.read().is_err() {
+                break;
+            }
+        }
+        return Some(
+            nth.and_then(|n| f.instrs.as_ref().and_then(|s| s.get(n)).copied())
+                .unwrap_or(f.func),
+        );
+    }
+    None
+}
+
+fn from_wast(src: &str, file: &str, e: &wast::Error) -> Box<WatDiagnostic> {
+    // wast's message carries its own rendered snippet; keep just the first line
+    let message = e.message().lines().next().unwrap_or_default().to_string();
+    Box::new(diagnostic(src, file, e.span().offset(), "parse", message))
+}
+
+fn diagnostic(src: &str, file: &str, offset: usize, phase: &str, message: String) -> WatDiagnostic {
+    let span = Span::from_offset(offset.min(src.len()));
+    let (line, col) = span.linecol_in(src);
+    let text = src.lines().nth(line).unwrap_or("");
+    let gutter = format!("{}", line + 1).len();
+    let snippet = format!(
+        "{:w$} |\n{} | {}\n{:w$} | {}^\n",
+        "",
+        line + 1,
+        text,
+        "",
+        " ".repeat(col),
+        w = gutter
+    );
+    WatDiagnostic {
+        file: file.to_string(),
+        line: line + 1,
+        col: col + 1,
+        phase: phase.to_string(),
+        hint: hint_for(&message),
+        message,
+        snippet,
+    }
+}
+
+fn hint_for(message: &str) -> Option<String> {
+    let m = message.to_lowercase();
+    let hint = if m.contains("type mismatch") {
+        "operand types on the stack don't match; convert explicitly (i32.wrap_i64, i64.extend_i32_s, f64.convert_i32_s, ...)"
+    } else if m.contains("values remaining on stack") || m.contains("expected 0 elements") {
+        "the block/function leaves extra values on the stack; drop them or fix its result type"
+    } else if m.contains("unknown operator") || m.contains("unexpected token") && m.contains("instruction") {
+        "instruction name not recognized; check spelling (e.g. i32.add, local.get) or the proposal it needs"
+    } else if m.contains("unknown local") || m.contains("unknown func") || m.contains("failed to find") {
+        "identifiers must be declared ($name) before use; check the spelling"
+    } else if m.contains("unknown memory") {
+        "declare a memory with (memory 1) or import one before using loads/stores"
+    } else if m.contains("expected `)`") || m.contains("expected `(`") || m.contains("unexpected end") {
+        "parentheses are unbalanced around here"
+    } else if m.contains("unknown global") {
+        "declare the global with (global $g ...) before referencing it"
+    } else {
+        return None;
+    };
+    Some(hint.to_string())
+}
@@ -2,6 +2,9 @@ use anyhow::Result;
 use serde::Serialize;
 use wasmparser::{Parser, Payload};
 use crate::toolchain::ToolchainReport;
+use crate::report::Section;
+use crate::runtime::{smoke_section, SmokeReport};
+use crate::compat::{compat_report, compat_section, detect_features, load_profiles, CompatReport};
 
 #[derive(Debug, Serialize)]
 pub struct DoctorReport {
@@ -12,8 +15,13 @@ pub struct DoctorReport {
     pub heuristics: Heuristics,
     pub sysroot: Option<SysrootInfo>,
     pub suggestions: Suggestions,
+    pub compat: CompatReport,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emscripten: Option<EmscriptenInfo>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub toolchain: Option<ToolchainReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smoke: Option<SmokeReport>,
 }
 
 #[derive(Debug, Serialize)]
@@ -41,6 +49,8 @@ pub struct ComponentInfo {
 #[derive(Debug, Serialize)]
 pub struct Heuristics {
     pub cxx_eh: EhHeuristic,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eh_check: Option<EhCheck>,
     //pub detected_strings: Vec<String>,
 }
 
@@ -50,6 +60,27 @@ pub struct EhHeuristic {
     pub signals: Vec<String>,
 }
 
+/// Requested C++ EH model (from the build) vs what the binary actually contains.
+#[derive(Debug, Serialize)]
+pub struct EhCheck {
+    pub expected: String, // "none" | "emulated" | "wasm"
+    pub observed: String, // "none" | "emulated" | "wasm"
+    pub status: String,   // "ok" | "mismatch" | "unverified"
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmscriptenInfo {
+    /// No `env` imports: runs without the JS glue (-sSTANDALONE_WASM)
+    pub standalone: bool,
+    /// Has a `dylink.0` section: a side module for dynamic linking
+    pub side_module: bool,
+    /// JS glue found next to the module
+    pub glue: Option<String>,
+    pub env_imports: usize,
+    pub signals: Vec<String>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct SysrootInfo {
     pub path: String,
@@ -59,12 +90,26 @@ pub struct SysrootInfo {
 #[derive(Debug, Serialize)]
 pub struct Suggestions {
     pub wasmtime_run: Vec<String>,
+    /// The `wasmtime run` suggestions as `didspace run`, to try them without wasmtime
+    pub didspace_run: Vec<String>,
     pub compile_hints: Vec<String>,
 }
 
 pub struct DoctorOptions<'a> {
     pub wasi_sysroot: Option<&'a str>,
     pub max_list: usize,
+    /// Extra runtime profiles merged over the built-in compat table
+    pub compat_table: Option<&'a str>,
+    /// EH model the module was built with; verified against the binary
+    pub expect_eh: Option<&'a str>,
+    /// Where the module was read from; used to find Emscripten JS glue next to it
+    pub module_path: Option<&'a str>,
+}
+
+impl Default for DoctorOptions<'_> {
+    fn default() -> Self {
+        Self { wasi_sysroot: None, max_list: 20, compat_table: None, expect_eh: None, module_path: None }
+    }
 }
 
 fn detect_cxx_eh(bytes: &[u8], core_imports: Option<&[String]>) -> EhHeuristic {
@@ -144,6 +189,7 @@ pub fn doctor_report(bytes: &[u8], opts: DoctorOptions<'_>) -> Result<DoctorRepo
             }),
             Suggestions {
                 wasmtime_run: run,
+                didspace_run: Vec::new(),
                 compile_hints: Vec::new(),
             },
         )
@@ -171,14 +217,29 @@ pub fn doctor_report(bytes: &[u8], opts: DoctorOptions<'_>) -> Result<DoctorRepo
             None,
             Suggestions {
                 wasmtime_run: run,
+                didspace_run: Vec::new(),
                 compile_hints: Vec::new(),
             }, 
         )
     };
 
     let eh = detect_cxx_eh(bytes, core.as_ref().map(|c| c.imports.as_slice()));
+    let eh_check = match opts.expect_eh {
+        Some(expected) => Some(check_eh_model(bytes, expected)?),
+        None => None,
+    };
     let (eh_found, detected_strings) = detect_cxx_eh_strings(bytes);
     let mut compile_hints = suggestions.compile_hints;
+    let mut wasmtime_run = suggestions.wasmtime_run;
+
+    let emscripten = if kind == "component" {
+        None
+    } else {
+        detect_emscripten(bytes, opts.module_path)?
+    };
+    if let Some(em) = &emscripten {
+        wasmtime_run = emscripten_run_hints(em, opts.module_path.unwrap_or("module.wasm"));
+    }
     
     if eh_found {
         compile_hints.push(
@@ -208,6 +269,9 @@ pub fn doctor_report(bytes: &[u8], opts: DoctorOptions<'_>) -> Result<DoctorRepo
         }
     });
 
+    let profiles = load_profiles(opts.compat_table)?;
+    let compat = compat_report(bytes, &kind, &profiles)?;
+
     Ok(DoctorReport {
         kind,
         wasi: WasiInfo {
@@ -218,80 +282,254 @@ pub fn doctor_report(bytes: &[u8], opts: DoctorOptions<'_>) -> Result<DoctorRepo
         component,
         heuristics: Heuristics {
             cxx_eh: eh, //eh_found,
+            eh_check,
         },
         sysroot,
         suggestions: Suggestions {
-            wasmtime_run: suggestions.wasmtime_run,
+            didspace_run: didspace_run_hints(&wasmtime_run, opts.module_path),
+            wasmtime_run,
             compile_hints,
         },
+        compat,
+        emscripten,
         toolchain: None,
+        smoke: None,
     })
 }
 
-/// Versione testo, no JSON
-pub fn report_to_text(r: &DoctorReport) -> String {
-    let mut out = String::new();
-    out.push_str("didspace doctor\n");
-    out.push_str("==============\n\n");
-
-    out.push_str(&format!("Kind: {}\n", r.kind));
-    out.push_str(&format!("WASI: {}\n", r.wasi.flavor));
+/// Sezioni del report (testo, Markdown, HTML)
+pub fn doctor_sections(r: &DoctorReport) -> Vec<Section> {
+    let mut out = Vec::new();
+    out.push(Section::new("Module").kv("kind", &r.kind).kv("WASI", &r.wasi.flavor));
 
     if let Some(core) = &r.core {
-        out.push_str(&format!("Core imports: {}\n", core.imports.len()));
-        for s in &core.imports {
-            out.push_str(&format!(" -  {}\n", s));
-        }
-        out.push_str(&format!("\nCore exports: {}\n", core.exports.len()));
-        for s in &core.exports {
-            out.push_str(&format!(" -  {}\n", s));
-        }
-        out.push('\n');
+        out.push(Section::new(format!("Core imports ({})", core.imports_count)).list(core.imports.clone()));
+        out.push(Section::new(format!("Core exports ({})", core.exports_count)).list(core.exports.clone()));
     }
-
-    out.push_str("Heuristics:\n");
-    if r.heuristics.cxx_eh.level != "none" {
-        out.push_str("  ⚠ C++ EH strings detected If linking fails on __cxa_* with wasi-sdk, try: -fno-exceptions -fno-rtti -D_LIBCPP_NO_EXCEPTIONS\n");//.to_string());
-        for s in &r.heuristics.cxx_eh.signals {
-            out.push_str(&format!("    - {}\n", s));
-        }
-    } else {
-        out.push_str("  ✓ No obvious C++ EH strings found\n");
+    if let Some(comp) = &r.component {
+        out.push(Section::new(format!("Component imports ({})", comp.imports_count)).list(comp.imports.clone()));
+        out.push(Section::new(format!("Component exports ({})", comp.exports_count)).list(comp.exports.clone()));
     }
 
+    out.push(heuristics_section(r));
+
     if let Some(sys) = &r.sysroot {
-        out.push_str("\nEmulations available in sysroot:\n");
-        if sys.emulations.is_empty() {
-            out.push_str("  (none found)\n");
+        out.push(Section::new("Emulations available in sysroot").kv("sysroot", &sys.path).list(sys.emulations.clone()));
+    }
+
+    if let Some(em) = &r.emscripten {
+        let build = if em.side_module {
+            "side module (dylink.0)"
+        } else if em.standalone {
+            "standalone (WASI imports only)"
         } else {
-            for e in &sys.emulations {
-                out.push_str(&format!(" -  {}\n", e));
-            }
+            "needs JS glue"
+        };
+        let mut s = Section::new("Emscripten")
+            .kv("build", build)
+            .kv("env imports", em.env_imports)
+            .kv("glue", em.glue.as_deref().unwrap_or("(not found)"));
+        if !em.signals.is_empty() {
+            s = s.list(em.signals.clone());
         }
-    }
-    
-    out.push_str("\nWasmtime suggestions:\n");
-    for cmd in &r.suggestions.wasmtime_run {
-        out.push_str(&format!("  {}\n", cmd));
+        out.push(s);
     }
 
+    let heading = if r.emscripten.is_some() { "Run suggestions" } else { "Wasmtime suggestions" };
+    out.push(Section::new(heading).list(r.suggestions.wasmtime_run.clone()));
+    if !r.suggestions.didspace_run.is_empty() {
+        out.push(Section::new("Try without wasmtime").list(r.suggestions.didspace_run.clone()));
+    }
     if !r.suggestions.compile_hints.is_empty() {
-        out.push_str("\nCompile hints:\n");
-        for h in &r.suggestions.compile_hints {
-            out.push_str(&format!(" -  {}\n", h));
-        }
+        out.push(Section::new("Compile hints").list(r.suggestions.compile_hints.clone()));
     }
 
+    out.push(compat_section(&r.compat));
     if let Some(tc) = &r.toolchain {
-        out.push_str("\n");
-        out.push_str(&tc.to_text());
+        out.extend(tc.to_sections());
+    }
+    if let Some(smoke) = &r.smoke {
+        out.push(smoke_section(smoke));
     }
-
     out
 }
 
+/// The verdicts without the listings: EH heuristics and runtime compatibility.
+pub fn doctor_findings(r: &DoctorReport) -> Vec<Section> {
+    vec![heuristics_section(r), compat_section(&r.compat)]
+}
+
 // ---------- helpers ----------
 
+fn heuristics_section(r: &DoctorReport) -> Section {
+    let mut heur = Section::new("Heuristics");
+    if r.heuristics.cxx_eh.level != "none" {
+        heur = heur.finding_with(
+            "warn",
+            "C++ EH strings detected. If linking fails on __cxa_* with wasi-sdk, try: -fno-exceptions -fno-rtti -D_LIBCPP_NO_EXCEPTIONS",
+            r.heuristics.cxx_eh.signals.clone(),
+        );
+    } else {
+        heur = heur.finding("ok", "No obvious C++ EH strings found");
+    }
+    if let Some(c) = &r.heuristics.eh_check {
+        let level = match c.status.as_str() {
+            "ok" => "ok",
+            "mismatch" => "error",
+            _ => "warn",
+        };
+        heur = heur.finding(level, format!("EH model: expected {}, observed {} ({})", c.expected, c.observed, c.detail));
+    }
+    heur
+}
+
+/// Recognize Emscripten output from its sections, runtime imports and exports.
+fn detect_emscripten(bytes: &[u8], module_path: Option<&str>) -> Result<Option<EmscriptenInfo>> {
+    let mut signals = Vec::new();
+    let mut side_module = false;
+    let mut env_imports = 0usize;
+
+    for payload in Parser::new(0).parse_all(bytes) {
+        match payload? {
+            Payload::CustomSection(c) if c.name() == "dylink.0" || c.name() == "emscripten_metadata" => {
+                side_module |= c.name() == "dylink.0";
+                signals.push(format!("section:{}", c.name()));
+            }
+            Payload::ImportSection(reader) => {
+                for im in reader {
+                    let im = im?;
+                    if im.module != "env" {
+                        continue;
+                    }
+                    env_imports += 1;
+                    let em = im.name.starts_with("emscripten_")
+                        || im.name.starts_with("_emscripten_")
+                        || im.name.starts_with("__syscall_")
+                        || im.name.starts_with("invoke_");
+                    if em && signals.iter().filter(|s| s.starts_with("import:")).count() < 5 {
+                        signals.push(format!("import:env::{}", im.name));
+                    }
+                }
+            }
+            Payload::ExportSection(reader) => {
+                for ex in reader {
+                    let name = ex?.name;
+                    let em = name.starts_with("_emscripten_")
+                        || name.starts_with("emscripten_stack_")
+                        || name.starts_with("__em_js__")
+                        || name == "stackSave"
+                        || name == "stackRestore";
+                    if em && signals.iter().filter(|s| s.starts_with("export:")).count() < 5 {
+                        signals.push(format!("export:{}", name));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if signals.is_empty() {
+        return Ok(None);
+    }
+
+    let glue = module_path.and_then(|p| {
+        ["js", "mjs"]
+            .iter()
+            .map(|ext| std::path::Path::new(p).with_extension(ext))
+            .find(|g| g.is_file())
+            .map(|g| g.display().to_string())
+    });
+
+    Ok(Some(EmscriptenInfo {
+        standalone: env_imports == 0,
+        side_module,
+        glue,
+        env_imports,
+        signals,
+    }))
+}
+
+/// `wasmtime run [flags] module.wasm` → `didspace run [flags] <path>`.
+fn didspace_run_hints(wasmtime_run: &[String], module_path: Option<&str>) -> Vec<String> {
+    wasmtime_run
+        .iter()
+        .filter_map(|cmd| cmd.strip_prefix("wasmtime run "))
+        .map(|rest| {
+            let mut words: Vec<&str> = rest.split(' ').collect();
+            if let (Some(path), Some(last)) = (module_path, words.last_mut())
+                && last.ends_with(".wasm")
+            {
+                *last = path;
+            }
+            format!("didspace run {}", words.join(" "))
+        })
+        .collect()
+}
+
+fn emscripten_run_hints(em: &EmscriptenInfo, module: &str) -> Vec<String> {
+    let mut run = Vec::new();
+    if em.side_module {
+        run.push("side module: load it from an Emscripten main module (dlopen / loadDynamicLibrary)".to_string());
+        return run;
+    }
+    if let Some(glue) = &em.glue {
+        run.push(format!("node {}", glue));
+    }
+    if em.standalone {
+        run.push(format!("wasmtime run {}", module));
+    } else if em.glue.is_none() {
+        run.push(format!(
+            "{} imports the Emscripten JS runtime: link with -o app.js and run `node app.js`, or rebuild with -sSTANDALONE_WASM for wasmtime",
+            module
+        ));
+    }
+    run
+}
+
+/// Classify the EH model present in the binary and compare with `expected`.
+/// wasm: exception-handling opcodes or tags; emulated: __cxa_throw & co without
+/// them (or Emscripten invoke_* trampolines); none: neither.
+/// A module with no throw sites looks like "none" whatever it was built with,
+/// so that case is "unverified" rather than a mismatch.
+pub fn check_eh_model(bytes: &[u8], expected: &str) -> Result<EhCheck> {
+    let heuristic = detect_cxx_eh(bytes, None);
+    let native = detect_features(bytes)?.contains("exceptions");
+    let mut invoke = false;
+    for payload in Parser::new(0).parse_all(bytes) {
+        if let Payload::ImportSection(reader) = payload? {
+            for im in reader {
+                let im = im?;
+                invoke |= im.module == "env" && im.name.starts_with("invoke_");
+            }
+        }
+    }
+
+    let (observed, why) = if native {
+        ("wasm", "exception-handling instructions/tags present")
+    } else if invoke {
+        ("emulated", "invoke_* trampolines imported")
+    } else if heuristic.level != "none" {
+        ("emulated", "C++ EH runtime symbols without wasm EH instructions")
+    } else {
+        ("none", "no EH instructions or runtime symbols")
+    };
+
+    let status = if observed == expected {
+        "ok"
+    } else if observed == "none" {
+        "unverified"
+    } else {
+        "mismatch"
+    };
+
+    Ok(EhCheck {
+        expected: expected.to_string(),
+        observed: observed.to_string(),
+        status: status.to_string(),
+        detail: why.to_string(),
+    })
+}
+
 fn limit_vec(mut v: Vec<String>, max: usize) -> Vec<String> {
     if v.len() > max {
         v.truncate(max);
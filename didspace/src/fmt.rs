@@ -0,0 +1,1092 @@
+use std::collections::HashMap;
+use std::fs;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+
+use crate::converter::compile_wat;
+use crate::report::{Report, Section};
+
+/// Folded expressions and short fields stay on one line up to this column.
+const WIDTH: usize = 80;
+const INDENT: &str = "  ";
+
+/// Instruction names without a `.`; every other mnemonic has one.
+const PLAIN_OPS: &[&str] = &[
+    "unreachable", "nop", "block", "loop", "if", "else", "end", "br", "br_if", "br_table",
+    "return", "call", "call_indirect", "return_call", "return_call_indirect", "call_ref",
+    "return_call_ref", "drop", "select", "try", "try_table", "catch", "catch_all", "delegate",
+    "throw", "throw_ref", "rethrow", "br_on_null", "br_on_non_null", "br_on_cast",
+    "br_on_cast_fail",
+];
+
+const BINARY_OPS: &[&str] = &[
+    "add", "sub", "mul", "div", "div_s", "div_u", "rem_s", "rem_u", "and", "or", "xor", "shl",
+    "shr_s", "shr_u", "rotl", "rotr", "min", "max", "copysign", "eq", "ne", "lt", "lt_s", "lt_u",
+    "gt", "gt_s", "gt_u", "le", "le_s", "le_u", "ge", "ge_s", "ge_u",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// Keep each instruction folded or flat as written
+    Keep,
+    Folded,
+    Flat,
+}
+
+impl Layout {
+    pub fn name(self) -> &'static str {
+        match self {
+            Layout::Keep => "keep",
+            Layout::Folded => "folded",
+            Layout::Flat => "flat",
+        }
+    }
+}
+
+pub struct FmtOptions<'a> {
+    pub files: &'a [String],
+    pub layout: Layout,
+    /// Only compare; never write
+    pub check: bool,
+    /// Return the formatted text in the report instead of writing it back
+    pub stdout: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FmtFile {
+    pub file: String,
+    pub status: String, // "unchanged" | "reformatted" | "would-reformat" | "printed"
+    /// First line (1-based) where the formatted text differs from the file
+    pub first_diff: Option<usize>,
+    /// The formatted text, with `stdout` set
+    #[serde(skip)]
+    pub formatted: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FmtReport {
+    pub layout: String,
+    pub check: bool,
+    pub files: Vec<FmtFile>,
+}
+
+impl FmtReport {
+    pub fn unformatted(&self) -> usize {
+        self.files.iter().filter(|f| f.status == "would-reformat").count()
+    }
+
+    pub fn to_report(&self) -> Report {
+        let mut s = Section::untitled().kv("layout", &self.layout);
+        for f in &self.files {
+            let line = f.first_diff.unwrap_or(0);
+            s = match f.status.as_str() {
+                "unchanged" => s.finding("ok", format!("{} already formatted", f.file)),
+                "reformatted" => s.finding("info", format!("{} reformatted (from line {})", f.file, line)),
+                "would-reformat" => s.finding(
+                    "error",
+                    format!("{} is not formatted (first difference at line {})", f.file, line),
+                ),
+                _ => s.finding("info", format!("{} printed to stdout", f.file)),
+            };
+        }
+        let mut r = Report::new("Fmt");
+        if self.check {
+            s = s.kv("need formatting", format!("{} of {} file(s)", self.unformatted(), self.files.len()));
+            r.ok = self.unformatted() == 0;
+        }
+        r.section(s)
+    }
+}
+
+pub fn fmt_files(opts: FmtOptions<'_>) -> Result<FmtReport> {
+    let mut files = Vec::new();
+    for file in opts.files {
+        let src = fs::read_to_string(file).map_err(|e| anyhow!("Failed to read {}: {}", file, e))?;
+        let formatted = format_wat(&src, file, opts.layout)?;
+        let first_diff = first_diff(&src, &formatted);
+        let status = if opts.stdout {
+            "printed"
+        } else if first_diff.is_none() {
+            "unchanged"
+        } else if opts.check {
+            "would-reformat"
+        } else {
+            fs::write(file, &formatted)?;
+            "reformatted"
+        };
+        files.push(FmtFile {
+            file: file.clone(),
+            status: status.to_string(),
+            first_diff,
+            formatted: opts.stdout.then_some(formatted),
+        });
+    }
+    Ok(FmtReport {
+        layout: opts.layout.name().to_string(),
+        check: opts.check,
+        files,
+    })
+}
+
+/// Reformat one WAT module. Comments and single blank lines are kept; redundant labels
+/// after `else`/`end` are dropped. The result must encode to exactly the same binary as
+/// the input, otherwise nothing is returned.
+pub fn format_wat(src: &str, file: &str, layout: Layout) -> Result<String> {
+    let before = compile_wat(src, file, false).map_err(|e| anyhow!("Cannot format {}:\n{}", file, e))?;
+
+    let nodes = parse(lex(src)?)?;
+    let sigs = Sigs::collect(&nodes);
+    let mut p = Printer { layout, sigs: &sigs, lines: Vec::new() };
+    p.seq(&nodes, 0);
+    let text = p.finish();
+
+    let after = compile_wat(&text, file, false)
+        .map_err(|e| anyhow!("Formatting {} produced invalid WAT; file left unchanged:\n{}", file, e))?;
+    if after != before {
+        return Err(anyhow!("Formatting {} changed the encoded module; file left unchanged", file));
+    }
+    Ok(text)
+}
+
+// ---------- helpers ----------
+
+/// First line (1-based) where `a` and `b` differ, counting a missing final newline.
+/// Shared with `roundtrip` for its printed-text comparison.
+pub(crate) fn first_diff(a: &str, b: &str) -> Option<usize> {
+    let mut left = a.lines();
+    let mut right = b.lines();
+    let mut n = 1;
+    loop {
+        match (left.next(), right.next()) {
+            (None, None) => return (a != b).then_some(n),
+            (l, r) if l != r => return Some(n),
+            _ => n += 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    List(Vec<Node>),
+    Atom(String),
+    Str(String),
+    /// `;; ...` (`line`) or `(; ... ;)`; `trailing` when it shared a line with the previous token
+    Comment { text: String, line: bool, trailing: bool },
+    /// One or more empty lines in the source
+    Blank,
+}
+
+enum Tok {
+    Open,
+    Close,
+    Node(Node),
+}
+
+fn lex(src: &str) -> Result<Vec<Tok>> {
+    let b = src.as_bytes();
+    let mut toks = Vec::new();
+    let mut newlines = 0usize;
+    let mut i = 0;
+    while i < b.len() {
+        let c = b[i];
+        if c.is_ascii_whitespace() {
+            newlines += usize::from(c == b'\n');
+            i += 1;
+            continue;
+        }
+        if newlines >= 2 && !toks.is_empty() {
+            toks.push(Tok::Node(Node::Blank));
+        }
+        let trailing = newlines == 0 && !toks.is_empty();
+        newlines = 0;
+
+        if b[i..].starts_with(b";;") {
+            let end = src[i..].find('\n').map_or(b.len(), |n| i + n);
+            toks.push(Tok::Node(Node::Comment { text: src[i..end].trim_end().to_string(), line: true, trailing }));
+            i = end;
+        } else if b[i..].starts_with(b"(;") {
+            let mut depth = 0;
+            let mut j = i;
+            loop {
+                if j >= b.len() {
+                    return Err(anyhow!("unterminated block comment"));
+                }
+                if b[j..].starts_with(b"(;") {
+                    depth += 1;
+                    j += 2;
+                } else if b[j..].starts_with(b";)") {
+                    depth -= 1;
+                    j += 2;
+                    if depth == 0 {
+                        break;
+                    }
+                } else {
+                    j += 1;
+                }
+            }
+            toks.push(Tok::Node(Node::Comment { text: src[i..j].to_string(), line: false, trailing }));
+            i = j;
+        } else if c == b'(' {
+            toks.push(Tok::Open);
+            i += 1;
+        } else if c == b')' {
+            toks.push(Tok::Close);
+            i += 1;
+        } else if c == b'"' {
+            let mut j = i + 1;
+            while j < b.len() && b[j] != b'"' {
+                j += if b[j] == b'\\' { 2 } else { 1 };
+            }
+            if j >= b.len() {
+                return Err(anyhow!("unterminated string"));
+            }
+            toks.push(Tok::Node(Node::Str(src[i..=j].to_string())));
+            i = j + 1;
+        } else {
+            let mut j = i;
+            while j < b.len() && !b[j].is_ascii_whitespace() && !matches!(b[j], b'(' | b')' | b'"' | b';') {
+                j += 1;
+            }
+            toks.push(Tok::Node(Node::Atom(src[i..j].to_string())));
+            i = j;
+        }
+    }
+    Ok(toks)
+}
+
+fn parse(toks: Vec<Tok>) -> Result<Vec<Node>> {
+    let mut stack: Vec<Vec<Node>> = vec![Vec::new()];
+    for tok in toks {
+        match tok {
+            Tok::Open => stack.push(Vec::new()),
+            Tok::Close => {
+                let items = stack.pop().filter(|_| !stack.is_empty()).ok_or_else(|| anyhow!("unbalanced `)`"))?;
+                if let Some(parent) = stack.last_mut() {
+                    parent.push(Node::List(items));
+                }
+            }
+            Tok::Node(n) => {
+                if let Some(top) = stack.last_mut() {
+                    top.push(n);
+                }
+            }
+        }
+    }
+    match (stack.pop(), stack.is_empty()) {
+        (Some(nodes), true) => Ok(nodes),
+        _ => Err(anyhow!("unbalanced `(`")),
+    }
+}
+
+fn head(items: &[Node]) -> Option<&str> {
+    match items.first() {
+        Some(Node::Atom(a)) => Some(a),
+        _ => None,
+    }
+}
+
+fn list_head(n: &Node) -> Option<&str> {
+    match n {
+        Node::List(items) => head(items),
+        _ => None,
+    }
+}
+
+fn is_op(a: &str) -> bool {
+    a.starts_with(|c: char| c.is_ascii_lowercase())
+        && (PLAIN_OPS.contains(&a) || (a.contains('.') && !a.contains('=')))
+}
+
+/// Canonical spelling of a numeric literal: no `+`, lowercase hex digits and exponent markers,
+/// no redundant leading zeros, a digit after every decimal point. `None` if `a` isn't a number.
+fn norm_number(a: &str) -> Option<String> {
+    let (sign, body) = split_sign(a);
+    if body == "inf" || body == "nan" {
+        return Some(format!("{}{}", sign, body));
+    }
+    if let Some(payload) = body.strip_prefix("nan:0x") {
+        if payload.is_empty() || !payload.chars().all(|c| c.is_ascii_hexdigit() || c == '_') {
+            return None;
+        }
+        return Some(format!("{}nan:0x{}", sign, trim_zeros(&payload.to_lowercase())));
+    }
+
+    let (hex, digits) = match body.strip_prefix("0x") {
+        Some(d) => (true, d),
+        None => (false, body),
+    };
+    let exp_marks: &[char] = if hex { &['p', 'P'] } else { &['e', 'E'] };
+    let (mantissa, exp) = match digits.find(exp_marks) {
+        Some(k) => (&digits[..k], Some(&digits[k + 1..])),
+        None => (digits, None),
+    };
+    let (int, frac) = match mantissa.split_once('.') {
+        Some((i, f)) => (i, Some(f)),
+        None => (mantissa, None),
+    };
+    let is_digit = |c: char| c == '_' || if hex { c.is_ascii_hexdigit() } else { c.is_ascii_digit() };
+    if int.is_empty() || !int.chars().all(is_digit) || !frac.is_none_or(|f| f.chars().all(is_digit)) {
+        return None;
+    }
+
+    let mut out = String::from(sign);
+    if hex {
+        out.push_str("0x");
+    }
+    out.push_str(&trim_zeros(&int.to_lowercase()));
+    if let Some(f) = frac {
+        out.push('.');
+        out.push_str(if f.is_empty() { "0" } else { f });
+        if hex {
+            out.make_ascii_lowercase();
+        }
+    }
+    if let Some(e) = exp {
+        let (esign, ed) = split_sign(e);
+        if ed.is_empty() || !ed.chars().all(|c| c.is_ascii_digit() || c == '_') {
+            return None;
+        }
+        out.push(if hex { 'p' } else { 'e' });
+        out.push_str(esign);
+        out.push_str(&trim_zeros(ed));
+    }
+    Some(out)
+}
+
+fn split_sign(s: &str) -> (&str, &str) {
+    match s.as_bytes().first() {
+        Some(b'+') => ("", &s[1..]),
+        Some(b'-') => ("-", &s[1..]),
+        _ => ("", s),
+    }
+}
+
+fn trim_zeros(digits: &str) -> String {
+    // digit groups (1_000) are the author's choice; leave them alone
+    if digits.contains('_') {
+        return digits.to_string();
+    }
+    let t = digits.trim_start_matches('0');
+    if t.is_empty() { "0".to_string() } else { t.to_string() }
+}
+
+fn norm_atom(a: &str) -> String {
+    if !a.starts_with('$')
+        && let Some((key, value)) = a.split_once('=')
+    {
+        return format!("{}={}", key, norm_number(value).unwrap_or_else(|| value.to_string()));
+    }
+    norm_number(a).unwrap_or_else(|| a.to_string())
+}
+
+fn inline_node(n: &Node) -> Option<String> {
+    match n {
+        Node::Atom(a) => Some(norm_atom(a)),
+        Node::Str(s) => Some(s.clone()),
+        Node::List(items) => inline_list(items),
+        Node::Comment { .. } | Node::Blank => None,
+    }
+}
+
+fn inline_list(items: &[Node]) -> Option<String> {
+    let parts: Option<Vec<String>> = items
+        .iter()
+        .filter(|n| !matches!(n, Node::Blank))
+        .map(inline_node)
+        .collect();
+    Some(format!("({})", parts?.join(" ")))
+}
+
+/// Parameter and result counts of the functions and types a module declares, so folding
+/// knows how many operands a `call` consumes.
+#[derive(Default)]
+struct Sigs {
+    types: Vec<Option<(usize, usize)>>,
+    type_ids: HashMap<String, usize>,
+    funcs: Vec<Option<(usize, usize)>>,
+    func_ids: HashMap<String, usize>,
+}
+
+impl Sigs {
+    fn collect(nodes: &[Node]) -> Sigs {
+        let mut fields = Vec::new();
+        for n in nodes {
+            match n {
+                Node::List(items) if head(items) == Some("module") => fields.extend(items.iter()),
+                _ => fields.push(n),
+            }
+        }
+
+        let mut s = Sigs::default();
+        for f in &fields {
+            let Node::List(items) = f else { continue };
+            if head(items) != Some("type") {
+                continue;
+            }
+            if let Some(Node::Atom(id)) = items.get(1)
+                && id.starts_with('$')
+            {
+                s.type_ids.insert(id.clone(), s.types.len());
+            }
+            let sig = items.iter().find_map(|n| match n {
+                Node::List(f) if head(f) == Some("func") => s.sig_of(&f[1..]),
+                _ => None,
+            });
+            s.types.push(sig);
+        }
+        // imports come before definitions in the function index space, as in the text
+        for f in &fields {
+            let Node::List(items) = f else { continue };
+            match head(items) {
+                Some("func") => s.add_func(&items[1..]),
+                Some("import") => {
+                    if let Some(Node::List(desc)) = items.iter().find(|n| list_head(n) == Some("func")) {
+                        s.add_func(&desc[1..]);
+                    }
+                }
+                _ => {}
+            }
+        }
+        s
+    }
+
+    fn add_func(&mut self, items: &[Node]) {
+        if let Some(Node::Atom(id)) = items.first()
+            && id.starts_with('$')
+        {
+            self.func_ids.insert(id.clone(), self.funcs.len());
+        }
+        let sig = self.sig_of(items);
+        self.funcs.push(sig);
+    }
+
+    fn sig_of(&self, items: &[Node]) -> Option<(usize, usize)> {
+        let mut params = 0;
+        let mut results = 0;
+        let mut explicit = false;
+        let mut ty = None;
+        for n in items {
+            let Node::List(l) = n else { continue };
+            match head(l) {
+                Some("param") => {
+                    explicit = true;
+                    params += match l.get(1) {
+                        Some(Node::Atom(id)) if id.starts_with('$') => 1,
+                        _ => l.len() - 1,
+                    };
+                }
+                Some("result") => {
+                    explicit = true;
+                    results += l.len() - 1;
+                }
+                Some("type") => ty = Some(l.get(1).and_then(|t| index(t, &self.type_ids))),
+                _ => {}
+            }
+        }
+        match ty {
+            Some(t) if !explicit => t.and_then(|i| self.types.get(i).copied().flatten()),
+            _ => Some((params, results)),
+        }
+    }
+
+    /// Operands consumed and results produced, when they follow from the instruction alone.
+    fn arity(&self, name: &str, imms: &[Node]) -> Option<(usize, usize)> {
+        let fixed = match name {
+            "drop" => (1, 0),
+            "select" => (3, 1),
+            "nop" => (0, 0),
+            "br_if" | "br_table" => (1, 0),
+            "call" => return imms.first().and_then(|f| index(f, &self.func_ids)).and_then(|i| self.funcs.get(i).copied().flatten()),
+            "call_indirect" => return self.sig_of(imms).map(|(p, r)| (p + 1, r)),
+            "local.get" | "global.get" | "memory.size" | "table.size" | "ref.func" | "ref.null" => (0, 1),
+            "local.set" | "global.set" => (1, 0),
+            "local.tee" | "memory.grow" | "table.get" | "ref.is_null" => (1, 1),
+            "table.set" => (2, 0),
+            "table.grow" => (2, 1),
+            "memory.fill" | "memory.copy" | "memory.init" | "table.fill" | "table.copy" | "table.init" => (3, 0),
+            _ => {
+                let (ty, op) = name.split_once('.')?;
+                if !matches!(ty, "i32" | "i64" | "f32" | "f64") || op.starts_with("atomic") {
+                    return None;
+                }
+                if op == "const" {
+                    (0, 1)
+                } else if op.starts_with("load") {
+                    (1, 1)
+                } else if op.starts_with("store") {
+                    (2, 0)
+                } else if BINARY_OPS.contains(&op) {
+                    (2, 1)
+                } else {
+                    // unary ops, tests and conversions
+                    (1, 1)
+                }
+            }
+        };
+        Some(fixed)
+    }
+}
+
+fn index(n: &Node, ids: &HashMap<String, usize>) -> Option<usize> {
+    match n {
+        Node::Atom(a) if a.starts_with('$') => ids.get(a).copied(),
+        Node::Atom(a) => a.parse().ok(),
+        _ => None,
+    }
+}
+
+/// A function body instruction, folded `(op ...)` or flat `op ... end`.
+#[derive(Debug, Clone)]
+enum Instr {
+    Op { name: String, imms: Vec<Node>, args: Vec<Instr>, folded: bool },
+    Block { name: String, imms: Vec<Node>, body: Vec<Instr>, folded: bool },
+    If { imms: Vec<Node>, cond: Vec<Instr>, then: Vec<Instr>, els: Option<Vec<Instr>>, folded: bool },
+    /// Comments, blank lines and forms kept as written (locals, legacy folded `try`)
+    Other(Node),
+}
+
+/// Immediates after a mnemonic: plain atoms and non-instruction lists such as `(type $t)`.
+fn parse_imms(nodes: &[Node], i: &mut usize) -> Vec<Node> {
+    let mut imms = Vec::new();
+    while let Some(n) = nodes.get(*i) {
+        let take = match n {
+            Node::Atom(a) => !is_op(a),
+            Node::List(items) => {
+                !head(items).is_some_and(|h| is_op(h) || h == "then") && inline_list(items).is_some()
+            }
+            _ => false,
+        };
+        if !take {
+            break;
+        }
+        imms.push(n.clone());
+        *i += 1;
+    }
+    imms
+}
+
+fn parse_instrs(nodes: &[Node], i: &mut usize, stops: &[&str]) -> Vec<Instr> {
+    let mut out = Vec::new();
+    while let Some(n) = nodes.get(*i) {
+        match n {
+            Node::Atom(a) if is_op(a) => {
+                if stops.contains(&a.as_str()) {
+                    break;
+                }
+                *i += 1;
+                let imms = parse_imms(nodes, i);
+                let name = a.clone();
+                match a.as_str() {
+                    "block" | "loop" | "try" | "try_table" => {
+                        let body = parse_instrs(nodes, i, &["end"]);
+                        skip_marker(nodes, i, "end");
+                        out.push(Instr::Block { name, imms, body, folded: false });
+                    }
+                    "if" => {
+                        let then = parse_instrs(nodes, i, &["else", "end"]);
+                        let els = skip_marker(nodes, i, "else").then(|| parse_instrs(nodes, i, &["end"]));
+                        skip_marker(nodes, i, "end");
+                        out.push(Instr::If { imms, cond: Vec::new(), then, els, folded: false });
+                    }
+                    _ => out.push(Instr::Op { name, imms, args: Vec::new(), folded: false }),
+                }
+            }
+            Node::List(items) => {
+                out.push(parse_folded(items));
+                *i += 1;
+            }
+            other => {
+                out.push(Instr::Other(other.clone()));
+                *i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Consume `else`/`end` and the optional label repeated after it.
+fn skip_marker(nodes: &[Node], i: &mut usize, marker: &str) -> bool {
+    if !matches!(nodes.get(*i), Some(Node::Atom(a)) if a == marker) {
+        return false;
+    }
+    *i += 1;
+    if matches!(nodes.get(*i), Some(Node::Atom(a)) if a.starts_with('$')) {
+        *i += 1;
+    }
+    true
+}
+
+fn parse_folded(items: &[Node]) -> Instr {
+    let keep = || Instr::Other(Node::List(items.to_vec()));
+    let Some(name) = head(items).filter(|h| is_op(h)) else {
+        return keep();
+    };
+    let mut i = 1;
+    let imms = parse_imms(items, &mut i);
+    match name {
+        "block" | "loop" | "try_table" => Instr::Block {
+            name: name.to_string(),
+            imms,
+            body: parse_instrs(items, &mut i, &[]),
+            folded: true,
+        },
+        "if" => {
+            let Some(t) = items.iter().position(|n| list_head(n) == Some("then")) else {
+                return keep();
+            };
+            let rest = &items[t + 1..];
+            // anything but an (else ...) after (then ...) would not survive restructuring
+            if rest.len() > 1 || rest.first().is_some_and(|n| list_head(n) != Some("else")) {
+                return keep();
+            }
+            let body = |n: &Node| match n {
+                Node::List(l) => parse_instrs(l, &mut 1, &[]),
+                _ => Vec::new(),
+            };
+            Instr::If {
+                imms,
+                cond: parse_instrs(&items[..t], &mut i, &[]),
+                then: body(&items[t]),
+                els: rest.first().map(body),
+                folded: true,
+            }
+        }
+        "try" => keep(),
+        _ => Instr::Op {
+            name: name.to_string(),
+            imms,
+            args: parse_instrs(items, &mut i, &[]),
+            folded: true,
+        },
+    }
+}
+
+fn unfold(list: Vec<Instr>) -> Vec<Instr> {
+    let mut out = Vec::new();
+    for ins in list {
+        match ins {
+            Instr::Op { name, imms, args, .. } => {
+                out.extend(unfold(args));
+                out.push(Instr::Op { name, imms, args: Vec::new(), folded: false });
+            }
+            Instr::Block { name, imms, body, .. } => {
+                out.push(Instr::Block { name, imms, body: unfold(body), folded: false });
+            }
+            Instr::If { imms, cond, then, els, .. } => {
+                out.extend(unfold(cond));
+                out.push(Instr::If { imms, cond: Vec::new(), then: unfold(then), els: els.map(unfold), folded: false });
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Result count of a block type, unless it takes parameters or names a type.
+fn block_results(imms: &[Node]) -> Option<usize> {
+    let mut results = 0;
+    for n in imms {
+        match list_head(n) {
+            Some("param") | Some("type") => return None,
+            Some("result") => {
+                if let Node::List(l) = n {
+                    results += l.len() - 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(results)
+}
+
+/// Pop the last `n` pending expressions as operands, if each yields exactly one value.
+fn take_operands(pending: &mut Vec<(Instr, Option<usize>)>, n: usize) -> Vec<Instr> {
+    if n == 0 || pending.len() < n || pending[pending.len() - n..].iter().any(|(_, r)| *r != Some(1)) {
+        return Vec::new();
+    }
+    pending.drain(pending.len() - n..).map(|(ins, _)| ins).collect()
+}
+
+struct Line {
+    indent: usize,
+    text: String,
+    /// Ends in a `;;` comment, so nothing can be appended
+    comment: bool,
+}
+
+struct Printer<'a> {
+    layout: Layout,
+    sigs: &'a Sigs,
+    lines: Vec<Line>,
+}
+
+impl Printer<'_> {
+    fn finish(mut self) -> String {
+        while self.lines.last().is_some_and(|l| l.text.is_empty()) {
+            self.lines.pop();
+        }
+        let mut out = String::new();
+        for l in &self.lines {
+            if !l.text.is_empty() {
+                out.push_str(&INDENT.repeat(l.indent));
+                out.push_str(&l.text);
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    fn push(&mut self, indent: usize, text: String) {
+        self.lines.push(Line { indent, text, comment: false });
+    }
+
+    fn fits(indent: usize, text: &str) -> bool {
+        indent * INDENT.len() + text.chars().count() <= WIDTH
+    }
+
+    fn close(&mut self, indent: usize) {
+        while self.lines.last().is_some_and(|l| l.text.is_empty()) {
+            self.lines.pop();
+        }
+        match self.lines.last_mut() {
+            Some(l) if !l.comment => l.text.push(')'),
+            _ => self.push(indent, ")".to_string()),
+        }
+    }
+
+    fn blank(&mut self) {
+        if self.lines.last().is_some_and(|l| !l.text.is_empty()) {
+            self.push(0, String::new());
+        }
+    }
+
+    fn comment(&mut self, indent: usize, text: &str, line: bool, trailing: bool) {
+        if trailing
+            && let Some(last) = self.lines.last_mut()
+            && !last.text.is_empty()
+            && !last.comment
+        {
+            last.text.push(' ');
+            last.text.push_str(text);
+            last.comment = line;
+            return;
+        }
+        self.lines.push(Line { indent, text: text.to_string(), comment: line });
+    }
+
+    /// Items of a list body (or the file), dropping blank lines right after the opener.
+    fn seq(&mut self, nodes: &[Node], indent: usize) {
+        for (k, n) in nodes.iter().enumerate() {
+            if k == 0 && matches!(n, Node::Blank) {
+                continue;
+            }
+            self.node(n, indent);
+        }
+    }
+
+    fn node(&mut self, n: &Node, indent: usize) {
+        match n {
+            Node::Atom(a) => self.push(indent, norm_atom(a)),
+            Node::Str(s) => self.push(indent, s.clone()),
+            Node::Comment { text, line, trailing } => self.comment(indent, text, *line, *trailing),
+            Node::Blank => self.blank(),
+            Node::List(items) => match head(items) {
+                Some("module") => self.fields(items, indent, false),
+                Some("func") => self.func(items, indent),
+                _ => self.fields(items, indent, true),
+            },
+        }
+    }
+
+    /// Generic list: one line if it fits, else leading atoms on the first line and
+    /// every other item on its own line.
+    fn fields(&mut self, items: &[Node], indent: usize, may_inline: bool) {
+        let inline = inline_list(items);
+        if let Some(text) = &inline
+            && (may_inline || items.len() <= 2)
+            && Self::fits(indent, text)
+        {
+            self.push(indent, text.clone());
+            return;
+        }
+        let split = items.iter().take_while(|n| matches!(n, Node::Atom(_) | Node::Str(_))).count();
+        let header: Vec<String> = items[..split].iter().filter_map(inline_node).collect();
+        self.push(indent, format!("({}", header.join(" ")));
+        self.seq(&items[split..], indent + 1);
+        self.close(indent);
+    }
+
+    fn func(&mut self, items: &[Node], indent: usize) {
+        let mut header = Vec::new();
+        let mut k = 0;
+        while let Some(n) = items.get(k) {
+            let part = match n {
+                Node::Atom(a) if k == 0 || !is_op(a) => Some(norm_atom(a)),
+                Node::List(l) if matches!(head(l), Some("export" | "import" | "type" | "param" | "result")) => {
+                    inline_list(l)
+                }
+                _ => None,
+            };
+            let Some(part) = part else { break };
+            header.push(part);
+            k += 1;
+        }
+
+        let body = parse_instrs(&items[k..], &mut 0, &[]);
+        let body = match self.layout {
+            Layout::Keep => body,
+            Layout::Flat => unfold(body),
+            Layout::Folded => self.fold(unfold(body)),
+        };
+        if body.is_empty() {
+            self.push(indent, format!("({})", header.join(" ")));
+            return;
+        }
+        self.push(indent, format!("({}", header.join(" ")));
+        self.instrs(&body, indent + 1);
+        self.close(indent);
+    }
+
+    /// Rebuild a flat instruction stream as nested expressions. Folded and flat forms
+    /// encode identically, so a wrong arity only costs readability, never correctness.
+    fn fold(&self, flat: Vec<Instr>) -> Vec<Instr> {
+        let mut pending: Vec<(Instr, Option<usize>)> = Vec::new();
+        for ins in flat {
+            let entry = match ins {
+                Instr::Op { name, imms, .. } => {
+                    let arity = self.sigs.arity(&name, &imms);
+                    let args = take_operands(&mut pending, arity.map_or(0, |a| a.0));
+                    (Instr::Op { name, imms, args, folded: true }, arity.map(|a| a.1))
+                }
+                // legacy try has no folded form without (do ...); keep it flat
+                Instr::Block { name, imms, body, .. } if name == "try" => {
+                    (Instr::Block { name, imms, body, folded: false }, None)
+                }
+                Instr::Block { name, imms, body, .. } => {
+                    let results = block_results(&imms);
+                    (Instr::Block { name, imms, body: self.fold(body), folded: true }, results)
+                }
+                Instr::If { imms, then, els, .. } => {
+                    let cond = take_operands(&mut pending, 1);
+                    let results = block_results(&imms);
+                    let then = self.fold(then);
+                    let els = els.map(|e| self.fold(e));
+                    (Instr::If { imms, cond, then, els, folded: true }, results)
+                }
+                other => (other, None),
+            };
+            pending.push(entry);
+        }
+        pending.into_iter().map(|(ins, _)| ins).collect()
+    }
+
+    fn instrs(&mut self, list: &[Instr], indent: usize) {
+        for (k, ins) in list.iter().enumerate() {
+            if k == 0 && matches!(ins, Instr::Other(Node::Blank)) {
+                continue;
+            }
+            self.instr(ins, indent);
+        }
+    }
+
+    fn instr(&mut self, ins: &Instr, indent: usize) {
+        match ins {
+            Instr::Op { name, imms, folded: false, .. } => {
+                // legacy try handlers line up with their `try`, like `else`
+                let indent = if matches!(name.as_str(), "catch" | "catch_all" | "delegate") {
+                    indent.saturating_sub(1)
+                } else {
+                    indent
+                };
+                self.push(indent, op_head(name, imms));
+            }
+            Instr::Op { name, imms, args, folded: true } => {
+                if let Some(text) = inline_instr(ins)
+                    && Self::fits(indent, &text)
+                {
+                    self.push(indent, text);
+                    return;
+                }
+                self.push(indent, format!("({}", op_head(name, imms)));
+                self.instrs(args, indent + 1);
+                self.close(indent);
+            }
+            Instr::Block { name, imms, body, folded } => {
+                if *folded {
+                    self.push(indent, format!("({}", op_head(name, imms)));
+                    self.instrs(body, indent + 1);
+                    self.close(indent);
+                } else {
+                    self.push(indent, op_head(name, imms));
+                    self.instrs(body, indent + 1);
+                    self.push(indent, "end".to_string());
+                }
+            }
+            Instr::If { imms, cond, then, els, folded } => {
+                if *folded {
+                    self.push(indent, format!("({}", op_head("if", imms)));
+                    self.instrs(cond, indent + 1);
+                    self.arm("then", then, indent + 1);
+                    if let Some(els) = els {
+                        self.arm("else", els, indent + 1);
+                    }
+                    self.close(indent);
+                } else {
+                    self.push(indent, op_head("if", imms));
+                    self.instrs(then, indent + 1);
+                    if let Some(els) = els {
+                        self.push(indent, "else".to_string());
+                        self.instrs(els, indent + 1);
+                    }
+                    self.push(indent, "end".to_string());
+                }
+            }
+            Instr::Other(n) => self.node(n, indent),
+        }
+    }
+
+    fn arm(&mut self, name: &str, body: &[Instr], indent: usize) {
+        let parts: Option<Vec<String>> = body.iter().map(inline_instr).collect();
+        if let Some(parts) = parts {
+            let text = format!("({})", [vec![name.to_string()], parts].concat().join(" "));
+            if Self::fits(indent, &text) {
+                self.push(indent, text);
+                return;
+            }
+        }
+        self.push(indent, format!("({}", name));
+        self.instrs(body, indent + 1);
+        self.close(indent);
+    }
+}
+
+fn op_head(name: &str, imms: &[Node]) -> String {
+    let mut parts = vec![name.to_string()];
+    parts.extend(imms.iter().filter_map(inline_node));
+    parts.join(" ")
+}
+
+fn inline_instr(ins: &Instr) -> Option<String> {
+    match ins {
+        Instr::Op { name, imms, args, folded } => {
+            let head = op_head(name, imms);
+            if !folded {
+                return Some(head);
+            }
+            let mut parts = vec![head];
+            for a in args {
+                if !matches!(a, Instr::Other(Node::Blank)) {
+                    parts.push(inline_instr(a)?);
+                }
+            }
+            Some(format!("({})", parts.join(" ")))
+        }
+        Instr::Other(n) => inline_node(n),
+        Instr::Block { .. } | Instr::If { .. } => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLAT: &str = r#"(module
+  ;; adds two numbers
+  (func $add (param $a i32) (param $b i32) (result i32)
+    local.get $a
+    local.get $b
+    i32.add (; inline ;))
+  (func (export "k") (result f64)
+    f64.const 0x1.8p3)
+  (memory 1)
+  (data (i32.const 0x10) "a (b) ;; c \22q\22"))
+"#;
+
+    const FOLDED: &str = r#"(module
+  ;; adds two numbers
+  (func $add (param $a i32) (param $b i32) (result i32)
+    (i32.add (local.get $a) (local.get $b)) (; inline ;))
+  (func (export "k") (result f64)
+    (f64.const 0x1.8p3))
+  (memory 1)
+  (data (i32.const 0x10) "a (b) ;; c \22q\22"))
+"#;
+
+    fn fmt(src: &str, layout: Layout) -> String {
+        format_wat(src, "test.wat", layout).unwrap()
+    }
+
+    #[test]
+    fn numbers_are_normalized() {
+        assert_eq!(norm_number("+0x00FF").as_deref(), Some("0xff"));
+        assert_eq!(norm_number("007").as_deref(), Some("7"));
+        assert_eq!(norm_number("1.E+05").as_deref(), Some("1.0e5"));
+        assert_eq!(norm_number("+0x01.8P+3").as_deref(), Some("0x1.8p3"));
+        assert_eq!(norm_number("-nan:0x00FF").as_deref(), Some("-nan:0xff"));
+        assert_eq!(norm_number("+inf").as_deref(), Some("inf"));
+        // digit groups are kept as written
+        assert_eq!(norm_number("0_100").as_deref(), Some("0_100"));
+        assert_eq!(norm_number("$x"), None);
+        assert_eq!(norm_number("i32.add"), None);
+        assert_eq!(norm_atom("offset=0x0010"), "offset=0x10");
+    }
+
+    #[test]
+    fn literals_are_normalized_in_place() {
+        let src = "(module\n  (func (result f64)\n    f64.const +0x01.8P3)\n  (data (i32.const 0x0010) \"x\"))\n";
+        let out = fmt(src, Layout::Keep);
+        assert!(out.contains("f64.const 0x1.8p3"), "{}", out);
+        assert!(out.contains("(i32.const 0x10)"), "{}", out);
+    }
+
+    #[test]
+    fn fold_and_unfold_round_trip() {
+        assert_eq!(fmt(FLAT, Layout::Folded), FOLDED);
+        assert_eq!(fmt(FOLDED, Layout::Flat), FLAT);
+        assert_eq!(fmt(FLAT, Layout::Keep), FLAT);
+        assert_eq!(fmt(FOLDED, Layout::Keep), FOLDED);
+    }
+
+    #[test]
+    fn comments_and_strings_survive() {
+        let src = "(module\n;; line comment\n  (; block ;)   (memory 1)\n\n\n  (data (i32.const 0) \"(; not a comment ;) ;; nor this \\\"\"))\n";
+        let out = fmt(src, Layout::Keep);
+        assert!(out.contains("  ;; line comment\n"), "{}", out);
+        assert!(out.contains("(; block ;)"), "{}", out);
+        assert!(out.contains(r#""(; not a comment ;) ;; nor this \"""#), "{}", out);
+        // runs of blank lines collapse to one
+        assert!(!out.contains("\n\n\n"), "{}", out);
+        assert_eq!(fmt(&out, Layout::Keep), out);
+    }
+
+    #[test]
+    fn check_reports_without_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let messy = dir.path().join("messy.wat");
+        let clean = dir.path().join("clean.wat");
+        let messy_src = FLAT.replace("0x1.8p3", "+0x01.8P3");
+        std::fs::write(&messy, &messy_src).unwrap();
+        std::fs::write(&clean, FLAT).unwrap();
+        let files = [messy.display().to_string(), clean.display().to_string()];
+
+        let rep = fmt_files(FmtOptions { files: &files, layout: Layout::Keep, check: true, stdout: false }).unwrap();
+        assert_eq!(rep.files[0].status, "would-reformat");
+        assert_eq!(rep.files[0].first_diff, Some(8));
+        assert_eq!(rep.files[1].status, "unchanged");
+        assert_eq!(rep.unformatted(), 1);
+        assert!(!rep.to_report().ok);
+        assert_eq!(std::fs::read_to_string(&messy).unwrap(), messy_src);
+
+        let rep = fmt_files(FmtOptions { files: &files[1..], layout: Layout::Keep, check: true, stdout: false }).unwrap();
+        assert!(rep.to_report().ok);
+    }
+
+    #[test]
+    fn first_diff_counts_trailing_newline() {
+        assert_eq!(first_diff("a\nb\n", "a\nb\n"), None);
+        assert_eq!(first_diff("a\nb\n", "a\nc\n"), Some(2));
+        assert_eq!(first_diff("a\nb", "a\nb\n"), Some(3));
+        assert_eq!(first_diff("a\n", "a\nb\n"), Some(2));
+    }
+}
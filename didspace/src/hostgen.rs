@@ -0,0 +1,1872 @@
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use wasmparser::{ExternalKind, KnownCustom, Name, Parser, Payload, TypeRef, ValType};
+
+/// Import module whose functions the runtimes provide themselves.
+const WASI_MODULE: &str = "wasi_snapshot_preview1";
+
+/// A core module's imports and exports, as seen by a host binding generator.
+#[derive(Debug, Serialize)]
+pub struct CoreModule {
+    pub imports: Vec<CoreFunc>,
+    pub exports: Vec<CoreFunc>,
+    /// Name of the exported linear memory, if any
+    pub memory: Option<String>,
+    pub allocator: Option<Allocator>,
+    pub wasi: bool,
+    /// Memory, table, global and tag imports as (module, name, kind)
+    pub other_imports: Vec<(String, String, String)>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CoreFunc {
+    /// Import module; empty for exports
+    pub module: String,
+    pub name: String,
+    /// `name` (`module_name` for imports) as an identifier, unique among the
+    /// module's imports or exports
+    pub ident: String,
+    /// Parameter names from the name section, `p0`.. otherwise; sanitized to
+    /// identifiers and unique within the function
+    pub params: Vec<(String, CoreType)>,
+    pub results: Vec<CoreType>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum CoreType {
+    I32,
+    I64,
+    F32,
+    F64,
+    V128,
+    Ref,
+}
+
+/// Exports used to place host strings and buffers in linear memory.
+#[derive(Debug, Serialize)]
+pub struct Allocator {
+    pub alloc: String,
+    /// `cabi_realloc(old, old_size, align, new_size)` rather than `malloc(size)`
+    pub cabi: bool,
+    pub free: Option<String>,
+    /// Rust-style `dealloc(ptr, len)` rather than `free(ptr)`
+    pub free_len: bool,
+}
+
+/// One generated source file.
+#[derive(Debug, Serialize)]
+pub struct GeneratedFile {
+    pub name: String,
+    pub contents: String,
+}
+
+impl CoreModule {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut types = Vec::new();
+        let mut func_types = Vec::new();
+        let mut imports = Vec::new();
+        let mut other_imports = Vec::new();
+        let mut exported = Vec::new();
+        let mut memory = None;
+        let mut locals: HashMap<u32, HashMap<u32, String>> = HashMap::new();
+
+        for payload in Parser::new(0).parse_all(bytes) {
+            match payload? {
+                Payload::TypeSection(reader) => {
+                    for ty in reader.into_iter_err_on_gc_types() {
+                        types.push(ty?);
+                    }
+                }
+                Payload::ImportSection(reader) => {
+                    for im in reader {
+                        let im = im?;
+                        let kind = match im.ty {
+                            TypeRef::Func(t) | TypeRef::FuncExact(t) => {
+                                func_types.push(t);
+                                imports.push((im.module.to_string(), im.name.to_string(), t));
+                                continue;
+                            }
+                            TypeRef::Table(_) => "table",
+                            TypeRef::Memory(_) => "memory",
+                            TypeRef::Global(_) => "global",
+                            TypeRef::Tag(_) => "tag",
+                        };
+                        other_imports.push((im.module.to_string(), im.name.to_string(), kind.to_string()));
+                    }
+                }
+                Payload::FunctionSection(reader) => {
+                    for t in reader {
+                        func_types.push(t?);
+                    }
+                }
+                Payload::ExportSection(reader) => {
+                    for ex in reader {
+                        let ex = ex?;
+                        match ex.kind {
+                            ExternalKind::Func => exported.push((ex.name.to_string(), ex.index)),
+                            ExternalKind::Memory if memory.is_none() => memory = Some(ex.name.to_string()),
+                            _ => {}
+                        }
+                    }
+                }
+                Payload::CustomSection(c) => {
+                    if let KnownCustom::Name(reader) = c.as_known() {
+                        for sub in reader.into_iter().flatten() {
+                            if let Name::Local(map) = sub {
+                                for f in map.into_iter().flatten() {
+                                    let names = f.names.into_iter().flatten().map(|n| (n.index, n.name.to_string())).collect();
+                                    locals.insert(f.index, names);
+                                }
+                            }
+                        }
+                    }
+                }
+                Payload::ComponentSection { .. } | Payload::ComponentTypeSection(_) => {
+                    return Err(anyhow!("this is a component; core-module bindings need a core module"));
+                }
+                Payload::End(_) => break,
+                _ => {}
+            }
+        }
+
+        let func = |module: String, name: String, type_index: u32, func_index: Option<u32>| -> Result<CoreFunc> {
+            let ty = types
+                .get(type_index as usize)
+                .ok_or_else(|| anyhow!("function {} refers to missing type {}", name, type_index))?;
+            // the name section may name only some locals; params are locals 0..n
+            let names = func_index.and_then(|i| locals.get(&i));
+            let params: Vec<String> = (0..ty.params().len())
+                .map(|i| names.and_then(|n| n.get(&(i as u32))).cloned().unwrap_or_else(|| format!("p{}", i)))
+                .collect();
+            let params = unique_idents(&params).into_iter().zip(ty.params().iter().map(core_type)).collect();
+            let ident = if module.is_empty() { name.clone() } else { format!("{}_{}", module, name) };
+            Ok(CoreFunc { module, name, ident, params, results: ty.results().iter().map(core_type).collect() })
+        };
+
+        let wasi = imports.iter().any(|(m, _, _)| m == WASI_MODULE);
+        let mut imports = imports
+            .into_iter()
+            .map(|(m, n, t)| func(m, n, t, None))
+            .collect::<Result<Vec<_>>>()?;
+        let mut exports = exported
+            .into_iter()
+            .map(|(n, idx)| {
+                let t = *func_types.get(idx as usize).ok_or_else(|| anyhow!("export {} refers to missing function {}", n, idx))?;
+                func(String::new(), n, t, Some(idx))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for funcs in [&mut imports, &mut exports] {
+            let idents = unique_idents(&funcs.iter().map(|f| f.ident.clone()).collect::<Vec<_>>());
+            for (f, id) in funcs.iter_mut().zip(idents) {
+                f.ident = id;
+            }
+        }
+        let allocator = find_allocator(&exports);
+        Ok(CoreModule { imports, exports, memory, allocator, wasi, other_imports })
+    }
+
+    /// The generated hosts only link functions; a module that imports its
+    /// memory, a table, a global or a tag can't be instantiated by any of them.
+    pub fn check_imports(&self) -> Result<()> {
+        if self.other_imports.is_empty() {
+            return Ok(());
+        }
+        let list: Vec<String> = self.other_imports.iter().map(|(m, n, k)| format!("{} {}.{}", k, m, n)).collect();
+        Err(anyhow!(
+            "the module imports {}; generated bindings only provide function imports",
+            list.join(", ")
+        ))
+    }
+
+    /// Imports the host has to provide (everything but WASI).
+    pub fn host_imports(&self) -> impl Iterator<Item = &CoreFunc> {
+        self.imports.iter().filter(|f| f.module != WASI_MODULE)
+    }
+
+    /// Exports with a plain numeric signature; v128 and references are left to the raw API.
+    pub fn wrapped_exports(&self) -> impl Iterator<Item = &CoreFunc> {
+        self.exports.iter().filter(|f| f.is_numeric())
+    }
+
+    /// Reactor modules export `_initialize`, which has to run before any other export.
+    pub fn is_reactor(&self) -> bool {
+        self.exports.iter().any(|f| f.name == "_initialize" && f.params.is_empty() && f.results.is_empty())
+    }
+
+    pub fn skipped_exports(&self) -> Vec<&str> {
+        self.exports.iter().filter(|f| !f.is_numeric()).map(|f| f.name.as_str()).collect()
+    }
+}
+
+impl CoreFunc {
+    fn is_numeric(&self) -> bool {
+        self.params.iter().map(|(_, t)| t).chain(&self.results).all(|t| !matches!(t, CoreType::V128 | CoreType::Ref))
+    }
+}
+
+/// `app.h` + `app.c` over the wasmtime C API: load, import stubs, one typed
+/// function per export and linear-memory helpers.
+pub fn gen_c_wasmtime(m: &CoreModule, prefix: &str) -> Vec<GeneratedFile> {
+    let p = c_ident(prefix).to_lowercase();
+    let guard = format!("{}_H", p.to_uppercase());
+    let t = format!("{}_t", p);
+
+    let mut h = format!(
+        r#"/* Auto-generated by didspace bindgen: wasmtime C API host wrappers.
+ * Link with -lwasmtime. Every call returns false and fills ->error on failure. */
+#ifndef {guard}
+#define {guard}
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <wasmtime.h>
+
+typedef struct {p} {{
+  wasm_engine_t *engine;
+  wasmtime_store_t *store;
+  wasmtime_context_t *context;
+  wasmtime_module_t *module;
+  wasmtime_instance_t instance;
+"#
+    );
+    if m.memory.is_some() {
+        h.push_str("  wasmtime_memory_t memory;\n");
+    }
+    if let Some(a) = &m.allocator {
+        h.push_str("  wasmtime_func_t alloc;\n");
+        if a.free.is_some() {
+            h.push_str("  wasmtime_func_t dealloc;\n");
+        }
+    }
+    for f in m.wrapped_exports() {
+        let _ = writeln!(h, "  wasmtime_func_t fn_{};", c_export_ident(&f.ident));
+    }
+    let _ = write!(
+        h,
+        r#"  char error[256];
+}} {t};
+
+bool {p}_load({t} *{p}, const char *path);
+void {p}_free({t} *{p});
+
+/* Exports */
+"#
+    );
+    for f in m.wrapped_exports() {
+        let _ = writeln!(h, "{};", c_export_proto(f, &p, &t));
+    }
+    let skipped = m.skipped_exports();
+    if !skipped.is_empty() {
+        let _ = writeln!(h, "/* not wrapped (v128/reference types): {} */", skipped.join(", "));
+    }
+    if m.host_imports().next().is_some() {
+        let _ = writeln!(
+            h,
+            "\n/* Host imports. The defaults fail with \"not implemented\"; define {}_NO_DEFAULT_IMPORTS\n * and implement these yourself. Return false (after filling ->error) to trap. */",
+            p.to_uppercase()
+        );
+        for f in m.host_imports() {
+            let _ = writeln!(h, "{};", c_import_proto(f, &p, &t));
+        }
+    }
+    if m.memory.is_some() {
+        let _ = write!(
+            h,
+            r#"
+/* Linear memory. Pointers into it are invalidated by any call that grows memory. */
+uint8_t *{p}_memory({t} *{p}, size_t *size);
+/* Bounds-checked view of [ptr, ptr+len); NULL if out of range. Not NUL-terminated. */
+const char *{p}_str_at({t} *{p}, int32_t ptr, int32_t len);
+"#
+        );
+        if let Some(a) = &m.allocator {
+            let _ = write!(
+                h,
+                r#"/* Copy host data into guest memory via the module's `{alloc}` export. */
+bool {p}_write({t} *{p}, const void *data, size_t len, int32_t *ptr);
+bool {p}_write_str({t} *{p}, const char *s, int32_t *ptr, int32_t *len);
+"#,
+                alloc = a.alloc
+            );
+            if let Some(free) = &a.free {
+                let _ = writeln!(h, "/* Hand a buffer back to the module's `{}` export. */", free);
+                let _ = writeln!(h, "bool {p}_release({t} *{p}, int32_t ptr, int32_t len);");
+            }
+        }
+    }
+    let _ = writeln!(h, "\n#endif /* {guard} */");
+
+    // ---- implementation ----
+    let mut c = format!(
+        r#"/* Auto-generated by didspace bindgen: wasmtime C API host wrappers. */
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "{p}.h"
+
+static bool fail({t} *{p}, const char *what, wasmtime_error_t *err, wasm_trap_t *trap) {{
+  wasm_byte_vec_t msg = {{0}};
+  if (err) {{
+    wasmtime_error_message(err, &msg);
+    wasmtime_error_delete(err);
+  }} else if (trap) {{
+    wasm_trap_message(trap, &msg);
+    wasm_trap_delete(trap);
+  }}
+  snprintf({p}->error, sizeof {p}->error, "%s: %.*s", what, (int)msg.size, msg.data ? msg.data : "");
+  wasm_byte_vec_delete(&msg);
+  return false;
+}}
+
+static bool get_func({t} *{p}, const char *name, wasmtime_func_t *out) {{
+  wasmtime_extern_t item;
+  if (!wasmtime_instance_export_get({p}->context, &{p}->instance, name, strlen(name), &item) ||
+      item.kind != WASMTIME_EXTERN_FUNC) {{
+    snprintf({p}->error, sizeof {p}->error, "missing function export '%s'", name);
+    return false;
+  }}
+  *out = item.of.func;
+  return true;
+}}
+"#
+    );
+    if m.host_imports().next().is_some() {
+        c.push_str(
+            r#"
+static wasm_functype_t *functype(const wasm_valkind_t *p, size_t np, const wasm_valkind_t *r, size_t nr) {
+  wasm_valtype_vec_t params, results;
+  wasm_valtype_vec_new_uninitialized(&params, np);
+  for (size_t i = 0; i < np; i++) params.data[i] = wasm_valtype_new(p[i]);
+  wasm_valtype_vec_new_uninitialized(&results, nr);
+  for (size_t i = 0; i < nr; i++) results.data[i] = wasm_valtype_new(r[i]);
+  return wasm_functype_new(&params, &results);
+}
+"#,
+        );
+        let _ = writeln!(c, "\n#ifndef {}_NO_DEFAULT_IMPORTS", p.to_uppercase());
+        for f in m.host_imports() {
+            let _ = writeln!(c, "{} {{", c_import_proto(f, &p, &t));
+            for (i, _) in f.params.iter().enumerate() {
+                let _ = writeln!(c, "  (void)a{};", i);
+            }
+            for (i, _) in f.results.iter().enumerate() {
+                let _ = writeln!(c, "  (void)out{};", i);
+            }
+            let _ = writeln!(
+                c,
+                "  snprintf({p}->error, sizeof {p}->error, \"import {}.{} is not implemented\");\n  return false;\n}}",
+                f.module, f.name
+            );
+        }
+        let _ = writeln!(c, "#endif");
+
+        for f in m.host_imports() {
+            let id = import_ident(f);
+            let _ = writeln!(
+                c,
+                "\nstatic wasm_trap_t *cb_{id}(void *env, wasmtime_caller_t *caller, const wasmtime_val_t *args, size_t nargs,\n    wasmtime_val_t *results, size_t nresults) {{\n  {t} *{p} = env;\n  (void)caller; (void)args; (void)nargs; (void)results; (void)nresults;"
+            );
+            let mut call = vec![p.clone()];
+            for (i, (_, ty)) in f.params.iter().enumerate() {
+                call.push(format!("args[{}].of.{}", i, c_field(*ty)));
+            }
+            for (i, ty) in f.results.iter().enumerate() {
+                let _ = writeln!(c, "  {} out{} = 0;", c_type(*ty), i);
+                call.push(format!("&out{}", i));
+            }
+            let _ = writeln!(
+                c,
+                "  if (!{p}_import_{id}({})) return wasmtime_trap_new({p}->error, strlen({p}->error));",
+                call.join(", ")
+            );
+            for (i, ty) in f.results.iter().enumerate() {
+                let _ = writeln!(c, "  results[{i}].kind = {};\n  results[{i}].of.{} = out{i};", c_kind(*ty), c_field(*ty));
+            }
+            c.push_str("  return NULL;\n}\n");
+        }
+    }
+
+    let _ = write!(
+        c,
+        r#"
+bool {p}_load({t} *{p}, const char *path) {{
+  memset({p}, 0, sizeof *{p});
+  FILE *f = fopen(path, "rb");
+  if (!f) {{
+    snprintf({p}->error, sizeof {p}->error, "cannot open %s", path);
+    return false;
+  }}
+  fseek(f, 0, SEEK_END);
+  long size = ftell(f);
+  if (size < 0) {{
+    fclose(f);
+    snprintf({p}->error, sizeof {p}->error, "cannot read %s", path);
+    return false;
+  }}
+  fseek(f, 0, SEEK_SET);
+  wasm_byte_vec_t wasm;
+  wasm_byte_vec_new_uninitialized(&wasm, (size_t)size);
+  size_t n = fread(wasm.data, 1, (size_t)size, f);
+  fclose(f);
+  if (n != (size_t)size) {{
+    wasm_byte_vec_delete(&wasm);
+    snprintf({p}->error, sizeof {p}->error, "cannot read %s", path);
+    return false;
+  }}
+
+  {p}->engine = wasm_engine_new();
+  {p}->store = wasmtime_store_new({p}->engine, {p}, NULL);
+  {p}->context = wasmtime_store_context({p}->store);
+  wasmtime_error_t *err = wasmtime_module_new({p}->engine, (const uint8_t *)wasm.data, wasm.size, &{p}->module);
+  wasm_byte_vec_delete(&wasm);
+  if (err) return fail({p}, "compile", err, NULL);
+
+  wasmtime_linker_t *linker = wasmtime_linker_new({p}->engine);
+"#
+    );
+    if m.wasi {
+        let _ = write!(
+            c,
+            r#"  wasi_config_t *wasi = wasi_config_new();
+  wasi_config_inherit_argv(wasi);
+  wasi_config_inherit_env(wasi);
+  wasi_config_inherit_stdio(wasi);
+  err = wasmtime_context_set_wasi({p}->context, wasi);
+  if (!err) err = wasmtime_linker_define_wasi(linker);
+  if (err) {{
+    wasmtime_linker_delete(linker);
+    return fail({p}, "wasi", err, NULL);
+  }}
+"#
+        );
+    }
+    for f in m.host_imports() {
+        let id = import_ident(f);
+        let kinds = |ts: &[CoreType]| ts.iter().map(|t| c_valkind(*t)).collect::<Vec<_>>().join(", ");
+        let params: Vec<CoreType> = f.params.iter().map(|(_, t)| *t).collect();
+        let arr = |name: &str, ts: &[CoreType]| {
+            if ts.is_empty() {
+                ("NULL".to_string(), String::new())
+            } else {
+                (name.to_string(), format!("    static const wasm_valkind_t {}[] = {{{}}};\n", name, kinds(ts)))
+            }
+        };
+        let (pa, pdecl) = arr("params", &params);
+        let (ra, rdecl) = arr("results", &f.results);
+        let _ = write!(
+            c,
+            r#"  {{
+{pdecl}{rdecl}    wasm_functype_t *ty = functype({pa}, {np}, {ra}, {nr});
+    err = wasmtime_linker_define_func(linker, "{module}", {ml}, "{name}", {nl}, ty, cb_{id}, {p}, NULL);
+    wasm_functype_delete(ty);
+    if (err) {{
+      wasmtime_linker_delete(linker);
+      return fail({p}, "define {module}.{name}", err, NULL);
+    }}
+  }}
+"#,
+            np = params.len(),
+            nr = f.results.len(),
+            module = c_escape(&f.module),
+            name = c_escape(&f.name),
+            ml = f.module.len(),
+            nl = f.name.len(),
+        );
+    }
+    let _ = write!(
+        c,
+        r#"
+  wasm_trap_t *trap = NULL;
+  err = wasmtime_linker_instantiate(linker, {p}->context, {p}->module, &{p}->instance, &trap);
+  wasmtime_linker_delete(linker);
+  if (err || trap) return fail({p}, "instantiate", err, trap);
+"#
+    );
+    if let Some(mem) = &m.memory {
+        let _ = write!(
+            c,
+            r#"
+  wasmtime_extern_t item;
+  if (!wasmtime_instance_export_get({p}->context, &{p}->instance, "{mem}", {ml}, &item) ||
+      item.kind != WASMTIME_EXTERN_MEMORY) {{
+    snprintf({p}->error, sizeof {p}->error, "missing memory export '{mem}'");
+    return false;
+  }}
+  {p}->memory = item.of.memory;
+"#,
+            mem = c_escape(mem),
+            ml = mem.len()
+        );
+    }
+    let mut lookups = Vec::new();
+    if let Some(a) = &m.allocator {
+        lookups.push(("alloc".to_string(), a.alloc.clone()));
+        if let Some(free) = &a.free {
+            lookups.push(("dealloc".to_string(), free.clone()));
+        }
+    }
+    for f in m.wrapped_exports() {
+        lookups.push((format!("fn_{}", c_export_ident(&f.ident)), f.name.clone()));
+    }
+    for (field, name) in &lookups {
+        let _ = writeln!(c, "  if (!get_func({p}, \"{}\", &{p}->{field})) return false;", c_escape(name));
+    }
+    if m.is_reactor() {
+        let _ = write!(
+            c,
+            r#"  wasmtime_func_t init;
+  if (!get_func({p}, "_initialize", &init)) return false;
+  err = wasmtime_func_call({p}->context, &init, NULL, 0, NULL, 0, &trap);
+  if (err || trap) return fail({p}, "_initialize", err, trap);
+"#
+        );
+    }
+    let _ = write!(
+        c,
+        r#"  return true;
+}}
+
+void {p}_free({t} *{p}) {{
+  if ({p}->module) wasmtime_module_delete({p}->module);
+  if ({p}->store) wasmtime_store_delete({p}->store);
+  if ({p}->engine) wasm_engine_delete({p}->engine);
+  memset({p}, 0, sizeof *{p});
+}}
+"#
+    );
+
+    for f in m.wrapped_exports() {
+        let id = c_export_ident(&f.ident);
+        let _ = writeln!(c, "\n{} {{", c_export_proto(f, &p, &t));
+        let _ = writeln!(c, "  wasmtime_val_t args[{}], results[{}];", f.params.len().max(1), f.results.len().max(1));
+        for (i, (name, ty)) in f.params.iter().enumerate() {
+            let _ = writeln!(c, "  args[{i}].kind = {};\n  args[{i}].of.{} = {};", c_kind(*ty), c_field(*ty), c_ident(name));
+        }
+        let _ = writeln!(
+            c,
+            "  wasm_trap_t *trap = NULL;\n  wasmtime_error_t *err = wasmtime_func_call({p}->context, &{p}->fn_{id}, args, {}, results, {}, &trap);\n  if (err || trap) return fail({p}, \"{}\", err, trap);",
+            f.params.len(),
+            f.results.len(),
+            c_escape(&f.name)
+        );
+        for (i, ty) in f.results.iter().enumerate() {
+            let _ = writeln!(c, "  if (out{i}) *out{i} = results[{i}].of.{};", c_field(*ty));
+        }
+        c.push_str("  return true;\n}\n");
+    }
+
+    if m.memory.is_some() {
+        let _ = write!(
+            c,
+            r#"
+uint8_t *{p}_memory({t} *{p}, size_t *size) {{
+  *size = wasmtime_memory_data_size({p}->context, &{p}->memory);
+  return wasmtime_memory_data({p}->context, &{p}->memory);
+}}
+
+const char *{p}_str_at({t} *{p}, int32_t ptr, int32_t len) {{
+  size_t size;
+  uint8_t *mem = {p}_memory({p}, &size);
+  if (ptr < 0 || len < 0 || (size_t)ptr + (size_t)len > size) return NULL;
+  return (const char *)mem + ptr;
+}}
+"#
+        );
+        if let Some(a) = &m.allocator {
+            let alloc_args = if a.cabi {
+                "  args[0].kind = WASMTIME_I32; args[0].of.i32 = 0;\n  args[1].kind = WASMTIME_I32; args[1].of.i32 = 0;\n  args[2].kind = WASMTIME_I32; args[2].of.i32 = 1;\n  args[3].kind = WASMTIME_I32; args[3].of.i32 = (int32_t)len;\n"
+            } else {
+                "  args[0].kind = WASMTIME_I32; args[0].of.i32 = (int32_t)len;\n"
+            };
+            let _ = write!(
+                c,
+                r#"
+bool {p}_write({t} *{p}, const void *data, size_t len, int32_t *ptr) {{
+  wasmtime_val_t args[4], result;
+{alloc_args}  wasm_trap_t *trap = NULL;
+  wasmtime_error_t *err = wasmtime_func_call({p}->context, &{p}->alloc, args, {n}, &result, 1, &trap);
+  if (err || trap) return fail({p}, "{alloc}", err, trap);
+  *ptr = result.of.i32;
+  size_t size;
+  uint8_t *mem = {p}_memory({p}, &size); /* after the call: it may have grown memory */
+  if (*ptr == 0 || (size_t)*ptr + len > size) {{
+    snprintf({p}->error, sizeof {p}->error, "{alloc}(%zu) returned an unusable pointer", len);
+    return false;
+  }}
+  memcpy(mem + *ptr, data, len);
+  return true;
+}}
+
+bool {p}_write_str({t} *{p}, const char *s, int32_t *ptr, int32_t *len) {{
+  size_t n = strlen(s);
+  *len = (int32_t)n;
+  return {p}_write({p}, s, n, ptr);
+}}
+"#,
+                n = if a.cabi { 4 } else { 1 },
+                alloc = c_escape(&a.alloc)
+            );
+            if let Some(free) = &a.free {
+                let len_arg = if a.free_len {
+                    "  args[1].kind = WASMTIME_I32; args[1].of.i32 = len;\n"
+                } else {
+                    "  (void)len;\n"
+                };
+                let _ = write!(
+                    c,
+                    r#"
+bool {p}_release({t} *{p}, int32_t ptr, int32_t len) {{
+  wasmtime_val_t args[2];
+  args[0].kind = WASMTIME_I32; args[0].of.i32 = ptr;
+{len_arg}  wasm_trap_t *trap = NULL;
+  wasmtime_error_t *err = wasmtime_func_call({p}->context, &{p}->dealloc, args, {n}, NULL, 0, &trap);
+  if (err || trap) return fail({p}, "{free}", err, trap);
+  return true;
+}}
+"#,
+                    n = if a.free_len { 2 } else { 1 },
+                    free = c_escape(free)
+                );
+            }
+        }
+    }
+
+    vec![
+        GeneratedFile { name: format!("{}.h", p), contents: h },
+        GeneratedFile { name: format!("{}.c", p), contents: c },
+    ]
+}
+
+/// Typed wrappers over `wasm2c <file> -n <prefix>` output (wabt 1.0.33+
+/// naming), with trap capture through `wasm_rt_impl_try`.
+pub fn gen_c_wasm2c(m: &CoreModule, prefix: &str) -> Vec<GeneratedFile> {
+    let p = c_ident(prefix).to_lowercase();
+    let guard = format!("{}_HOST_H", p.to_uppercase());
+    let t = format!("{}_t", p);
+    let module_sym = w2c_mangle(&p, true);
+    let inst = format!("w2c_{}", module_sym);
+
+    // wasm2c passes one struct per imported module, in order of first use
+    let mut import_modules: Vec<&str> = Vec::new();
+    for f in &m.imports {
+        if !import_modules.contains(&f.module.as_str()) {
+            import_modules.push(&f.module);
+        }
+    }
+
+    let mut h = format!(
+        r#"/* Auto-generated by didspace bindgen: typed wrappers over wasm2c output.
+ *   wasm2c module.wasm -n {p} -o {p}.c      (wabt 1.0.33 or newer)
+ * Build with {p}.c and wasm-rt-impl.c. Calls return false on a trap; see ->trap. */
+#ifndef {guard}
+#define {guard}
+
+#include <stdbool.h>
+#include <stdint.h>
+#include <string.h>
+
+#include "wasm-rt-impl.h"
+#include "{p}.h"
+
+"#
+    );
+    for module in &import_modules {
+        let sym = w2c_mangle(module, true);
+        if *module == WASI_MODULE {
+            let _ = writeln!(h, "/* WASI: link a wasm2c WASI implementation (e.g. uvwasi) that defines struct w2c_{}. */", sym);
+        }
+        let _ = writeln!(h, "struct w2c_{};", sym);
+    }
+    let _ = writeln!(h, "\ntypedef struct {p} {{\n  {inst} instance;");
+    for module in &import_modules {
+        let _ = writeln!(h, "  struct w2c_{} *{};", w2c_mangle(module, true), c_ident(module));
+    }
+    let _ = write!(
+        h,
+        r#"  wasm_rt_trap_t trap;
+}} {t};
+
+/* Set the import pointers first (one per imported module). */
+static inline bool {p}_load({t} *{p}) {{
+  wasm_rt_init();
+  wasm2c_{module_sym}_instantiate(&{p}->instance{imports});
+{init}  return true;
+}}
+
+static inline void {p}_free({t} *{p}) {{
+  wasm2c_{module_sym}_free(&{p}->instance);
+  wasm_rt_free();
+}}
+
+static inline const char *{p}_trap_message(const {t} *{p}) {{
+  return wasm_rt_strerror({p}->trap);
+}}
+"#,
+        imports = import_modules.iter().map(|m| format!(", {}->{}", p, c_ident(m))).collect::<String>(),
+        init = if m.is_reactor() {
+            format!(
+                "  {p}->trap = wasm_rt_impl_try();\n  if ({p}->trap != WASM_RT_TRAP_NONE) return false;\n  w2c_{module_sym}_{}(&{p}->instance);\n",
+                w2c_mangle("_initialize", false)
+            )
+        } else {
+            String::new()
+        }
+    );
+
+    let host: Vec<&CoreFunc> = m.host_imports().collect();
+    if !host.is_empty() {
+        h.push_str("\n/* Host imports to implement (wasm2c calls them with your import struct):\n");
+        for f in &host {
+            let ret = match f.results.as_slice() {
+                [] => "void".to_string(),
+                [r] => w2c_type(*r).to_string(),
+                _ => "/* multi-value struct */".to_string(),
+            };
+            let mut params = vec![format!("struct w2c_{}*", w2c_mangle(&f.module, true))];
+            params.extend(f.params.iter().map(|(_, t)| w2c_type(*t).to_string()));
+            let _ = writeln!(
+                h,
+                " *   {} w2c_{}_{}({});",
+                ret,
+                w2c_mangle(&f.module, true),
+                w2c_mangle(&f.name, false),
+                params.join(", ")
+            );
+        }
+        h.push_str(" */\n");
+    }
+
+    for f in m.wrapped_exports() {
+        if f.results.len() > 1 {
+            let _ = writeln!(h, "\n/* {}: multi-value results; call w2c_{}_{} directly */", f.name, module_sym, w2c_mangle(&f.name, false));
+            continue;
+        }
+        let _ = writeln!(h, "\nstatic inline {} {{", c_export_proto(f, &p, &t));
+        let args: Vec<String> = std::iter::once(format!("&{}->instance", p))
+            .chain(f.params.iter().map(|(n, ty)| format!("({}){}", w2c_type(*ty), c_ident(n))))
+            .collect();
+        let call = format!("w2c_{}_{}({})", module_sym, w2c_mangle(&f.name, false), args.join(", "));
+        let _ = writeln!(h, "  {p}->trap = wasm_rt_impl_try();\n  if ({p}->trap != WASM_RT_TRAP_NONE) return false;");
+        match f.results.first() {
+            Some(r) => {
+                let _ = writeln!(h, "  {} r = {};\n  if (out0) *out0 = ({})r;", w2c_type(*r), call, c_type(*r));
+            }
+            None => {
+                let _ = writeln!(h, "  {};", call);
+            }
+        }
+        h.push_str("  return true;\n}\n");
+    }
+
+    if let Some(mem) = &m.memory {
+        let _ = write!(
+            h,
+            r#"
+static inline uint8_t *{p}_memory({t} *{p}, size_t *size) {{
+  wasm_rt_memory_t *mem = w2c_{module_sym}_{mem_sym}(&{p}->instance);
+  *size = (size_t)mem->size;
+  return mem->data;
+}}
+
+/* Bounds-checked view of [ptr, ptr+len); NULL if out of range. Not NUL-terminated. */
+static inline const char *{p}_str_at({t} *{p}, int32_t ptr, int32_t len) {{
+  size_t size;
+  uint8_t *mem = {p}_memory({p}, &size);
+  if (ptr < 0 || len < 0 || (size_t)ptr + (size_t)len > size) return NULL;
+  return (const char *)mem + ptr;
+}}
+"#,
+            mem_sym = w2c_mangle(mem, false)
+        );
+        if let Some(a) = &m.allocator {
+            let alloc = format!("w2c_{}_{}", module_sym, w2c_mangle(&a.alloc, false));
+            let call = if a.cabi {
+                format!("{}(&{}->instance, 0, 0, 1, (u32)len)", alloc, p)
+            } else {
+                format!("{}(&{}->instance, (u32)len)", alloc, p)
+            };
+            let _ = write!(
+                h,
+                r#"
+static inline bool {p}_write({t} *{p}, const void *data, size_t len, int32_t *ptr) {{
+  {p}->trap = wasm_rt_impl_try();
+  if ({p}->trap != WASM_RT_TRAP_NONE) return false;
+  *ptr = (int32_t){call};
+  size_t size;
+  uint8_t *mem = {p}_memory({p}, &size);
+  if (*ptr == 0 || (size_t)*ptr + len > size) {{
+    {p}->trap = WASM_RT_TRAP_OOB; /* the allocator handed back an unusable pointer */
+    return false;
+  }}
+  memcpy(mem + *ptr, data, len);
+  return true;
+}}
+
+static inline bool {p}_write_str({t} *{p}, const char *s, int32_t *ptr, int32_t *len) {{
+  size_t n = strlen(s);
+  *len = (int32_t)n;
+  return {p}_write({p}, s, n, ptr);
+}}
+"#
+            );
+            if let Some(free) = &a.free {
+                let args = if a.free_len { "(u32)ptr, (u32)len" } else { "(u32)ptr" };
+                let _ = write!(
+                    h,
+                    r#"
+static inline bool {p}_release({t} *{p}, int32_t ptr, int32_t len) {{
+  (void)len;
+  {p}->trap = wasm_rt_impl_try();
+  if ({p}->trap != WASM_RT_TRAP_NONE) return false;
+  w2c_{module_sym}_{free_sym}(&{p}->instance, {args});
+  return true;
+}}
+"#,
+                    free_sym = w2c_mangle(free, false)
+                );
+            }
+        }
+    }
+    let _ = writeln!(h, "\n#endif /* {guard} */");
+    vec![GeneratedFile { name: format!("{}_host.h", p), contents: h }]
+}
+
+/// Untyped loader for a component over wasmtime's component C API. C has no
+/// WIT-derived bindings, so exports are looked up by name and called with
+/// `wasmtime_component_val_t` arrays the caller shapes after the WIT.
+pub fn gen_c_component(prefix: &str) -> Vec<GeneratedFile> {
+    let p = c_ident(prefix).to_lowercase();
+    let guard = format!("{}_H", p.to_uppercase());
+    let t = format!("{}_t", p);
+    let h = format!(
+        r#"/* Auto-generated by didspace bindgen: untyped component loader over the wasmtime C API.
+ * Needs a libwasmtime with the component model C API (wasmtime/component.h); link with -lwasmtime.
+ *
+ * There are no typed wrappers: look an export up with {p}_func() and pass
+ * wasmtime_component_val_t arguments and results shaped after its WIT signature.
+ * WASI preview 2 is linked; any other import makes {p}_load() fail until you
+ * define it on the linker there. Every call returns false and fills ->error on failure. */
+#ifndef {guard}
+#define {guard}
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <wasmtime.h>
+
+typedef struct {p} {{
+  wasm_engine_t *engine;
+  wasmtime_store_t *store;
+  wasmtime_context_t *context;
+  wasmtime_component_t *component;
+  wasmtime_component_instance_t instance;
+  char error[256];
+}} {t};
+
+static inline bool {p}_fail({t} *{p}, const char *what, wasmtime_error_t *err) {{
+  wasm_byte_vec_t msg = {{0}};
+  wasmtime_error_message(err, &msg);
+  wasmtime_error_delete(err);
+  snprintf({p}->error, sizeof {p}->error, "%s: %.*s", what, (int)msg.size, msg.data ? msg.data : "");
+  wasm_byte_vec_delete(&msg);
+  return false;
+}}
+
+static inline bool {p}_load({t} *{p}, const char *path) {{
+  memset({p}, 0, sizeof *{p});
+  FILE *f = fopen(path, "rb");
+  if (!f) {{
+    snprintf({p}->error, sizeof {p}->error, "cannot open %s", path);
+    return false;
+  }}
+  fseek(f, 0, SEEK_END);
+  long size = ftell(f);
+  uint8_t *bytes = size < 0 ? NULL : malloc(size ? (size_t)size : 1);
+  fseek(f, 0, SEEK_SET);
+  size_t n = bytes ? fread(bytes, 1, (size_t)size, f) : 0;
+  fclose(f);
+  if (!bytes || n != (size_t)size) {{
+    free(bytes);
+    snprintf({p}->error, sizeof {p}->error, "cannot read %s", path);
+    return false;
+  }}
+
+  {p}->engine = wasm_engine_new();
+  {p}->store = wasmtime_store_new({p}->engine, {p}, NULL);
+  {p}->context = wasmtime_store_context({p}->store);
+  wasmtime_error_t *err = wasmtime_component_new({p}->engine, bytes, n, &{p}->component);
+  free(bytes);
+  if (err) return {p}_fail({p}, "compile", err);
+
+  wasmtime_component_linker_t *linker = wasmtime_component_linker_new({p}->engine);
+  wasi_config_t *wasi = wasi_config_new();
+  wasi_config_inherit_argv(wasi);
+  wasi_config_inherit_env(wasi);
+  wasi_config_inherit_stdio(wasi);
+  err = wasmtime_context_set_wasi({p}->context, wasi);
+  if (!err) err = wasmtime_component_linker_add_wasip2(linker);
+  /* define non-WASI imports on `linker` here */
+  if (!err) err = wasmtime_component_linker_instantiate(linker, {p}->context, {p}->component, &{p}->instance);
+  wasmtime_component_linker_delete(linker);
+  if (err) return {p}_fail({p}, "instantiate", err);
+  return true;
+}}
+
+static inline void {p}_free({t} *{p}) {{
+  if ({p}->component) wasmtime_component_delete({p}->component);
+  if ({p}->store) wasmtime_store_delete({p}->store);
+  if ({p}->engine) wasm_engine_delete({p}->engine);
+  memset({p}, 0, sizeof *{p});
+}}
+
+/* Find an exported function: `name` inside the exported instance `iface`
+ * (e.g. "wasi:cli/run@0.2.0"), or at the top level when `iface` is NULL. */
+static inline bool {p}_func({t} *{p}, const char *iface, const char *name, wasmtime_component_func_t *out) {{
+  wasmtime_component_export_index_t *parent = NULL;
+  if (iface) {{
+    parent = wasmtime_component_get_export_index({p}->component, NULL, iface, strlen(iface));
+    if (!parent) {{
+      snprintf({p}->error, sizeof {p}->error, "missing export instance '%s'", iface);
+      return false;
+    }}
+  }}
+  wasmtime_component_export_index_t *index = wasmtime_component_get_export_index({p}->component, parent, name, strlen(name));
+  bool found = index && wasmtime_component_instance_get_func(&{p}->instance, {p}->context, index, out);
+  if (index) wasmtime_component_export_index_delete(index);
+  if (parent) wasmtime_component_export_index_delete(parent);
+  if (!found) snprintf({p}->error, sizeof {p}->error, "missing function export '%s'", name);
+  return found;
+}}
+
+/* Call `func` with arguments and result slots matching its WIT signature.
+ * Results own their strings and lists: release each with wasmtime_component_val_delete. */
+static inline bool {p}_call({t} *{p}, const wasmtime_component_func_t *func, const wasmtime_component_val_t *args,
+    size_t nargs, wasmtime_component_val_t *results, size_t nresults) {{
+  wasmtime_error_t *err = wasmtime_component_func_call(func, {p}->context, args, nargs, results, nresults);
+  if (!err) err = wasmtime_component_func_post_return(func, {p}->context);
+  if (err) return {p}_fail({p}, "call", err);
+  return true;
+}}
+
+#endif /* {guard} */
+"#
+    );
+    vec![GeneratedFile { name: format!("{}.h", p), contents: h }]
+}
+
+/// A wasmtime-py class with one typed method per export, import stubs that
+/// can be overridden, and helpers for strings and buffers in linear memory.
+pub fn gen_python(m: &CoreModule, prefix: &str, wasm_name: &str) -> Vec<GeneratedFile> {
+    let class = pascal(prefix);
+    let mut py = format!(
+        r#""""Auto-generated by didspace bindgen: wasmtime-py wrapper for {wasm_name}.
+
+    from {module} import {class}
+    m = {class}("{wasm_name}")
+
+Traps raise wasmtime.Trap.
+"""
+from __future__ import annotations
+
+from pathlib import Path
+from typing import Callable, Optional
+
+import wasmtime
+
+
+def _missing(module: str, name: str) -> Callable:
+    def stub(*args):
+        raise NotImplementedError(f"import {{module}}.{{name}} is not implemented; pass it in `imports`")
+    return stub
+
+
+class {class}:
+    def __init__(
+        self,
+        path: str | Path = "{wasm_name}",
+        imports: Optional[dict[tuple[str, str], Callable]] = None,
+        store: Optional[wasmtime.Store] = None,
+    ):
+        """`imports` maps (module, name) to a Python callable; the rest raise when called."""
+        self.store = store or wasmtime.Store()
+        module = wasmtime.Module.from_file(self.store.engine, str(path))
+        linker = wasmtime.Linker(self.store.engine)
+"#,
+        module = py_ident(prefix).to_lowercase(),
+    );
+    if m.wasi {
+        py.push_str(
+            r#"        wasi = wasmtime.WasiConfig()
+        wasi.inherit_argv()
+        wasi.inherit_env()
+        wasi.inherit_stdout()
+        wasi.inherit_stderr()
+        self.store.set_wasi(wasi)
+        linker.define_wasi()
+"#,
+        );
+    }
+    if m.host_imports().next().is_some() {
+        py.push_str("        imports = imports or {}\n");
+    }
+    for f in m.host_imports() {
+        let params: Vec<&str> = f.params.iter().map(|(_, t)| py_valtype(*t)).collect();
+        let results: Vec<&str> = f.results.iter().map(|t| py_valtype(*t)).collect();
+        let key = format!("({}, {})", py_str(&f.module), py_str(&f.name));
+        let _ = writeln!(
+            py,
+            "        linker.define_func({m}, {n}, wasmtime.FuncType([{}], [{}]), imports.get({key}, _missing({m}, {n})))",
+            params.join(", "),
+            results.join(", "),
+            m = py_str(&f.module),
+            n = py_str(&f.name),
+        );
+    }
+    py.push_str("        self.instance = linker.instantiate(self.store, module)\n        exports = self.instance.exports(self.store)\n");
+    if let Some(mem) = &m.memory {
+        let _ = writeln!(py, "        self._memory: wasmtime.Memory = exports[{}]", py_str(mem));
+    }
+    if let Some(a) = &m.allocator {
+        let _ = writeln!(py, "        self._alloc: wasmtime.Func = exports[{}]", py_str(&a.alloc));
+        if let Some(free) = &a.free {
+            let _ = writeln!(py, "        self._free: wasmtime.Func = exports[{}]", py_str(free));
+        }
+    }
+    for f in m.wrapped_exports() {
+        let _ = writeln!(py, "        self._fn_{}: wasmtime.Func = exports[{}]", py_ident(&f.ident), py_str(&f.name));
+    }
+    if m.is_reactor() {
+        py.push_str("        exports[\"_initialize\"](self.store)\n");
+    }
+
+    let mut methods: Vec<String> = Vec::new();
+    for f in m.wrapped_exports() {
+        let id = py_ident(&f.ident);
+        // a leading `__` would be name-mangled inside the class
+        let base = match id.trim_start_matches('_') {
+            trimmed if trimmed.len() + 1 < id.len() => format!("_{}", trimmed),
+            _ if HELPER_NAMES.contains(&id.as_str()) => format!("{}_export", id),
+            _ => id.clone(),
+        };
+        // ...and trimming it can land on another export's name
+        let mut method = base.clone();
+        let mut n = 2;
+        while methods.contains(&method) {
+            method = format!("{}_{}", base, n);
+            n += 1;
+        }
+        methods.push(method.clone());
+        let params: Vec<String> = f.params.iter().map(|(n, t)| format!(", {}: {}", py_ident(n), py_hint(*t))).collect();
+        let names: Vec<String> = f.params.iter().map(|(n, _)| format!(", {}", py_ident(n))).collect();
+        let ret = match f.results.as_slice() {
+            [] => "None".to_string(),
+            [r] => py_hint(*r).to_string(),
+            rs if rs.iter().all(|r| py_hint(*r) == "int") => "list[int]".to_string(),
+            _ => "list[int | float]".to_string(),
+        };
+        let _ = write!(
+            py,
+            "\n    def {method}(self{}) -> {ret}:\n        return self._fn_{id}(self.store{})\n",
+            params.concat(),
+            names.concat()
+        );
+    }
+    let skipped = m.skipped_exports();
+    if !skipped.is_empty() {
+        let _ = writeln!(py, "\n    # not wrapped (v128/reference types): {}", skipped.join(", "));
+    }
+
+    if m.memory.is_some() {
+        py.push_str(
+            r#"
+    def read_bytes(self, ptr: int, length: int) -> bytes:
+        return bytes(self._memory.read(self.store, ptr, ptr + length))
+
+    def read_str(self, ptr: int, length: int) -> str:
+        return self.read_bytes(ptr, length).decode("utf-8")
+"#,
+        );
+        if let Some(a) = &m.allocator {
+            let call = if a.cabi { "self._alloc(self.store, 0, 0, 1, len(data))" } else { "self._alloc(self.store, len(data))" };
+            let _ = write!(
+                py,
+                r#"
+    def write_bytes(self, data: bytes) -> tuple[int, int]:
+        """Copy `data` into guest memory via `{alloc}`; returns (ptr, len)."""
+        ptr = {call}
+        self._memory.write(self.store, data, ptr)
+        return ptr, len(data)
+
+    def write_str(self, s: str) -> tuple[int, int]:
+        return self.write_bytes(s.encode("utf-8"))
+"#,
+                alloc = a.alloc
+            );
+            if a.free.is_some() {
+                let call = if a.free_len { "self._free(self.store, ptr, length)" } else { "self._free(self.store, ptr)" };
+                let _ = write!(py, "\n    def release(self, ptr: int, length: int) -> None:\n        {call}\n");
+            }
+        }
+    }
+    vec![GeneratedFile { name: format!("{}.py", py_ident(prefix).to_lowercase()), contents: py }]
+}
+
+/// A Rust module over wasmtime: an imports trait with trapping defaults, one
+/// typed method per export and helpers that pass `&str`/`&[u8]` through the
+/// module's allocator.
+pub fn gen_rust_wasmtime(m: &CoreModule, prefix: &str, wasm_name: &str) -> Vec<GeneratedFile> {
+    let name = pascal(prefix);
+    let host: Vec<&CoreFunc> = m.host_imports().filter(|f| f.is_numeric()).collect();
+    let mut rs = rust_header(m, &name, wasm_name, "wasmtime", if m.wasi { "`wasmtime`, `wasmtime-wasi` and `anyhow`" } else { "`wasmtime` and `anyhow`" });
+    rs.push_str("use wasmtime::{Caller, Engine, Instance, Linker, Memory, Module, Store, TypedFunc};\n");
+    if m.wasi {
+        rs.push_str("use wasmtime_wasi::preview1::{self, WasiP1Ctx};\nuse wasmtime_wasi::WasiCtxBuilder;\n");
+    }
+    rs.push_str(&rust_imports_trait(&name, &host, "memory: &mut [u8]", "`memory` is the module's exported linear memory (empty if it has none)."));
+
+    let _ = write!(rs, "\npub struct State<H> {{\n    pub host: H,\n");
+    if m.wasi {
+        rs.push_str("    wasi: WasiP1Ctx,\n");
+    }
+    let _ = write!(rs, "}}\n\npub struct {name}<H: {name}Imports = NoImports> {{\n    store: Store<State<H>>,\n    instance: Instance,\n");
+    rust_fields(&mut rs, m, "TypedFunc");
+    let _ = write!(
+        rs,
+        r#"}}
+
+impl<H: {name}Imports> {name}<H> {{
+    pub fn new(path: impl AsRef<Path>, host: H) -> Result<Self> {{
+        let engine = Engine::default();
+        let module = Module::from_file(&engine, path)?;
+        Self::instantiate(&engine, &module, host)
+    }}
+
+    pub fn from_bytes(bytes: &[u8], host: H) -> Result<Self> {{
+        let engine = Engine::default();
+        let module = Module::new(&engine, bytes)?;
+        Self::instantiate(&engine, &module, host)
+    }}
+
+    pub fn instantiate(engine: &Engine, module: &Module, host: H) -> Result<Self> {{
+        let mut linker = Linker::new(engine);
+"#
+    );
+    if m.wasi {
+        rs.push_str("        preview1::add_to_linker_sync(&mut linker, |s: &mut State<H>| &mut s.wasi)?;\n");
+    }
+    for f in &host {
+        let args: Vec<String> = (0..f.params.len()).map(|i| format!("a{}", i)).collect();
+        let typed: String = f.params.iter().enumerate().map(|(i, (_, t))| format!(", a{}: {}", i, rust_type(*t))).collect();
+        let _ = write!(
+            rs,
+            r#"        linker.func_wrap("{module}", "{fname}", |mut caller: Caller<'_, State<H>>{typed}| {{
+            let (memory, host) = split(&mut caller);
+            host.{method}(memory{args})
+        }})?;
+"#,
+            module = rust_escape(&f.module),
+            fname = rust_escape(&f.name),
+            method = rust_import_ident(f),
+            args = args.iter().map(|a| format!(", {}", a)).collect::<String>(),
+        );
+    }
+    if m.host_imports().any(|f| !f.is_numeric()) {
+        rs.push_str("        // imports with v128/reference types are left as traps\n");
+    }
+    rs.push_str("        linker.define_unknown_imports_as_traps(module)?;\n");
+    let state = if m.wasi {
+        "State { host, wasi: WasiCtxBuilder::new().inherit_stdio().inherit_args().inherit_env().build_p1() }"
+    } else {
+        "State { host }"
+    };
+    let _ = writeln!(rs, "        let mut store = Store::new(engine, {});", state);
+    rs.push_str("        let instance = linker.instantiate(&mut store, module)?;\n");
+    if let Some(mem) = &m.memory {
+        let _ = writeln!(
+            rs,
+            "        let memory = instance\n            .get_memory(&mut store, \"{0}\")\n            .ok_or_else(|| anyhow!(\"missing memory export '{0}'\"))?;",
+            rust_escape(mem)
+        );
+    }
+    if m.is_reactor() {
+        rs.push_str("        instance.get_typed_func::<(), ()>(&mut store, \"_initialize\")?.call(&mut store, ())?;\n");
+    }
+    rs.push_str("        Ok(Self {\n");
+    for (field, export) in rust_lookups(m) {
+        let _ = writeln!(rs, "            {}: instance.get_typed_func(&mut store, \"{}\")?,", field, rust_escape(&export));
+    }
+    if m.memory.is_some() {
+        rs.push_str("            memory,\n");
+    }
+    rs.push_str("            instance,\n            store,\n        })\n    }\n");
+
+    for f in m.wrapped_exports() {
+        let call_args = match f.params.len() {
+            0 => "()".to_string(),
+            1 => rust_ident(&f.params[0].0),
+            _ => format!("({})", f.params.iter().map(|(n, _)| rust_ident(n)).collect::<Vec<_>>().join(", ")),
+        };
+        let _ = write!(
+            rs,
+            "\n    pub fn {}(&mut self{}) -> Result<{}> {{\n        self.fn_{}.call(&mut self.store, {})\n    }}\n",
+            rust_method_ident(&f.ident),
+            rust_params(f),
+            rust_results(&f.results),
+            rust_ident(&f.ident),
+            call_args
+        );
+    }
+    rs.push_str(
+        r#"
+    pub fn host(&self) -> &H {
+        &self.store.data().host
+    }
+
+    pub fn host_mut(&mut self) -> &mut H {
+        &mut self.store.data_mut().host
+    }
+
+    pub fn instance(&self) -> &Instance {
+        &self.instance
+    }
+"#,
+    );
+    if m.memory.is_some() {
+        rs.push_str(
+            r#"
+    pub fn memory(&self) -> &[u8] {
+        self.memory.data(&self.store)
+    }
+
+    pub fn read_bytes(&self, ptr: i32, len: i32) -> Result<&[u8]> {
+        let start = ptr as u32 as usize;
+        let end = start + len as u32 as usize;
+        self.memory().get(start..end).ok_or_else(|| anyhow!("[{}, {}) is outside linear memory", start, end))
+    }
+
+    pub fn read_str(&self, ptr: i32, len: i32) -> Result<&str> {
+        Ok(std::str::from_utf8(self.read_bytes(ptr, len)?)?)
+    }
+"#,
+        );
+        if let Some(a) = &m.allocator {
+            let _ = write!(
+                rs,
+                r#"
+    /// Copy `data` into guest memory via `{alloc}`; returns (ptr, len).
+    pub fn write_bytes(&mut self, data: &[u8]) -> Result<(i32, i32)> {{
+        let len = i32::try_from(data.len())?;
+        let ptr = self.alloc.call(&mut self.store, {args})?;
+        self.memory.write(&mut self.store, ptr as u32 as usize, data)?;
+        Ok((ptr, len))
+    }}
+"#,
+                alloc = a.alloc,
+                args = if a.cabi { "(0, 0, 1, len)" } else { "len" }
+            );
+            rust_alloc_helpers(&mut rs, a, true);
+        }
+    }
+    rs.push_str("}\n");
+    if !host.is_empty() {
+        let _ = write!(
+            rs,
+            r#"
+/// The exported memory's bytes and the host, borrowed together.
+fn split<'a, H>(caller: &'a mut Caller<'_, State<H>>) -> (&'a mut [u8], &'a mut H) {{
+    match caller.get_export("{}").and_then(|e| e.into_memory()) {{
+        Some(memory) => {{
+            let (data, state) = memory.data_and_store_mut(caller);
+            (data, &mut state.host)
+        }}
+        None => (&mut [], &mut caller.data_mut().host),
+    }}
+}}
+"#,
+            rust_escape(m.memory.as_deref().unwrap_or("memory"))
+        );
+    }
+    vec![GeneratedFile { name: format!("{}.rs", rust_ident(&prefix.to_lowercase())), contents: rs }]
+}
+
+/// The same surface over wasmer (`memlayout`'s runtime). WASI imports are
+/// part of the trait here; wire them to wasmer-wasix if the module needs them.
+pub fn gen_rust_wasmer(m: &CoreModule, prefix: &str, wasm_name: &str) -> Vec<GeneratedFile> {
+    let name = pascal(prefix);
+    let host: Vec<&CoreFunc> = m.imports.iter().filter(|f| f.is_numeric()).collect();
+    let mut rs = rust_header(m, &name, wasm_name, "wasmer", "`wasmer` and `anyhow`");
+    rs.push_str("use wasmer::{imports, Function, FunctionEnv, FunctionEnvMut, Instance, Memory, MemoryView, Module, RuntimeError, Store, TypedFunction};\n");
+    rs.push_str(&rust_imports_trait(
+        &name,
+        &host,
+        "memory: Option<&MemoryView<'_>>",
+        "`memory` views the module's exported linear memory, if it has one.",
+    ));
+    let _ = write!(
+        rs,
+        r#"
+pub struct State<H> {{
+    pub host: H,
+    memory: Option<Memory>,
+}}
+
+pub struct {name}<H: {name}Imports = NoImports> {{
+    store: Store,
+    env: FunctionEnv<State<H>>,
+    instance: Instance,
+"#
+    );
+    rust_fields(&mut rs, m, "TypedFunction");
+    let _ = write!(
+        rs,
+        r#"}}
+
+impl<H: {name}Imports> {name}<H> {{
+    pub fn new(path: impl AsRef<Path>, host: H) -> Result<Self> {{
+        Self::from_bytes(&std::fs::read(path)?, host)
+    }}
+
+    pub fn from_bytes(bytes: &[u8], host: H) -> Result<Self> {{
+        let mut store = Store::default();
+        let module = Module::new(&store, bytes)?;
+        let env = FunctionEnv::new(&mut store, State {{ host, memory: None }});
+        let imports = imports! {{
+"#
+    );
+    let mut modules: Vec<&str> = Vec::new();
+    for f in &host {
+        if !modules.contains(&f.module.as_str()) {
+            modules.push(&f.module);
+        }
+    }
+    for module in modules {
+        let _ = writeln!(rs, "            \"{}\" => {{", rust_escape(module));
+        for f in host.iter().filter(|f| f.module == module) {
+            let args: String = (0..f.params.len()).map(|i| format!(", a{}", i)).collect();
+            let typed: String = f.params.iter().enumerate().map(|(i, (_, t))| format!(", a{}: {}", i, rust_type(*t))).collect();
+            let _ = write!(
+                rs,
+                r#"                "{fname}" => Function::new_typed_with_env(&mut store, &env, |mut env: FunctionEnvMut<State<H>>{typed}| -> Result<{ret}, RuntimeError> {{
+                    let (state, store) = env.data_and_store_mut();
+                    let view = state.memory.as_ref().map(|m| m.view(&store));
+                    state.host.{method}(view.as_ref(){args}).map_err(|e| RuntimeError::new(e.to_string()))
+                }}),
+"#,
+                fname = rust_escape(&f.name),
+                ret = rust_results(&f.results),
+                method = rust_import_ident(f),
+            );
+        }
+        rs.push_str("            },\n");
+    }
+    rs.push_str("        };\n        let instance = Instance::new(&mut store, &module, &imports)?;\n");
+    if let Some(mem) = &m.memory {
+        let _ = writeln!(rs, "        let memory = instance.exports.get_memory(\"{}\")?.clone();", rust_escape(mem));
+        rs.push_str("        env.as_mut(&mut store).memory = Some(memory.clone());\n");
+    }
+    if m.is_reactor() {
+        rs.push_str("        instance.exports.get_typed_function::<(), ()>(&store, \"_initialize\")?.call(&mut store)?;\n");
+    }
+    rs.push_str("        Ok(Self {\n");
+    for (field, export) in rust_lookups(m) {
+        let _ = writeln!(rs, "            {}: instance.exports.get_typed_function(&store, \"{}\")?,", field, rust_escape(&export));
+    }
+    if m.memory.is_some() {
+        rs.push_str("            memory,\n");
+    }
+    rs.push_str("            instance,\n            env,\n            store,\n        })\n    }\n");
+
+    for f in m.wrapped_exports() {
+        let args: String = f.params.iter().map(|(n, _)| format!(", {}", rust_ident(n))).collect();
+        let _ = write!(
+            rs,
+            "\n    pub fn {}(&mut self{}) -> Result<{}> {{\n        Ok(self.fn_{}.call(&mut self.store{})?)\n    }}\n",
+            rust_method_ident(&f.ident),
+            rust_params(f),
+            rust_results(&f.results),
+            rust_ident(&f.ident),
+            args
+        );
+    }
+    rs.push_str(
+        r#"
+    pub fn host(&self) -> &H {
+        &self.env.as_ref(&self.store).host
+    }
+
+    pub fn host_mut(&mut self) -> &mut H {
+        &mut self.env.as_mut(&mut self.store).host
+    }
+
+    pub fn instance(&self) -> &Instance {
+        &self.instance
+    }
+"#,
+    );
+    if m.memory.is_some() {
+        rs.push_str(
+            r#"
+    pub fn read_bytes(&self, ptr: i32, len: i32) -> Result<Vec<u8>> {
+        let start = ptr as u32 as u64;
+        Ok(self.memory.view(&self.store).copy_range_to_vec(start..start + len as u32 as u64)?)
+    }
+
+    pub fn read_str(&self, ptr: i32, len: i32) -> Result<String> {
+        Ok(String::from_utf8(self.read_bytes(ptr, len)?)?)
+    }
+"#,
+        );
+        if let Some(a) = &m.allocator {
+            let _ = write!(
+                rs,
+                r#"
+    /// Copy `data` into guest memory via `{alloc}`; returns (ptr, len).
+    pub fn write_bytes(&mut self, data: &[u8]) -> Result<(i32, i32)> {{
+        let len = i32::try_from(data.len())?;
+        let ptr = self.alloc.call(&mut self.store, {args})?;
+        self.memory.view(&self.store).write(ptr as u32 as u64, data)?;
+        Ok((ptr, len))
+    }}
+"#,
+                alloc = a.alloc,
+                args = if a.cabi { "0, 0, 1, len" } else { "len" }
+            );
+            rust_alloc_helpers(&mut rs, a, false);
+        }
+    }
+    rs.push_str("}\n");
+    vec![GeneratedFile { name: format!("{}.rs", rust_ident(&prefix.to_lowercase())), contents: rs }]
+}
+
+// ---------- helpers ----------
+
+fn core_type(t: &ValType) -> CoreType {
+    match t {
+        ValType::I32 => CoreType::I32,
+        ValType::I64 => CoreType::I64,
+        ValType::F32 => CoreType::F32,
+        ValType::F64 => CoreType::F64,
+        ValType::V128 => CoreType::V128,
+        ValType::Ref(_) => CoreType::Ref,
+    }
+}
+
+/// Sanitize `names` to identifiers; where that merges two (`a-b`, `a_b`) the
+/// later one gets a `_2`, `_3`.. suffix.
+fn unique_idents(names: &[String]) -> Vec<String> {
+    let mut out: Vec<String> = Vec::with_capacity(names.len());
+    for name in names {
+        let base = ident(name, &[]);
+        let mut id = base.clone();
+        let mut n = 2;
+        while out.contains(&id) {
+            id = format!("{}_{}", base, n);
+            n += 1;
+        }
+        out.push(id);
+    }
+    out
+}
+
+/// `cabi_realloc`, `malloc`/`free` or `alloc`/`dealloc`, checked by signature.
+fn find_allocator(exports: &[CoreFunc]) -> Option<Allocator> {
+    use CoreType::I32;
+    let sig = |name: &str, params: &[CoreType], results: &[CoreType]| {
+        exports.iter().any(|f| {
+            f.name == name && f.params.iter().map(|(_, t)| *t).eq(params.iter().copied()) && f.results == results
+        })
+    };
+    if sig("cabi_realloc", &[I32, I32, I32, I32], &[I32]) {
+        return Some(Allocator { alloc: "cabi_realloc".into(), cabi: true, free: None, free_len: false });
+    }
+    for (alloc, free, free_len) in [("malloc", "free", false), ("alloc", "dealloc", true), ("allocate", "deallocate", true)] {
+        if sig(alloc, &[I32], &[I32]) {
+            let (free, free_len) = if sig(free, &[I32, I32], &[]) {
+                (Some(free.to_string()), true)
+            } else if sig(free, &[I32], &[]) {
+                (Some(free.to_string()), false)
+            } else {
+                (None, free_len)
+            };
+            return Some(Allocator { alloc: alloc.into(), cabi: false, free, free_len });
+        }
+    }
+    None
+}
+
+fn c_type(t: CoreType) -> &'static str {
+    match t {
+        CoreType::I32 => "int32_t",
+        CoreType::I64 => "int64_t",
+        CoreType::F32 => "float",
+        CoreType::F64 => "double",
+        CoreType::V128 | CoreType::Ref => "void *",
+    }
+}
+
+fn w2c_type(t: CoreType) -> &'static str {
+    match t {
+        CoreType::I32 => "u32",
+        CoreType::I64 => "u64",
+        CoreType::F32 => "f32",
+        CoreType::F64 => "f64",
+        CoreType::V128 => "v128",
+        CoreType::Ref => "wasm_rt_funcref_t",
+    }
+}
+
+fn c_kind(t: CoreType) -> &'static str {
+    match t {
+        CoreType::I32 => "WASMTIME_I32",
+        CoreType::I64 => "WASMTIME_I64",
+        CoreType::F32 => "WASMTIME_F32",
+        CoreType::F64 => "WASMTIME_F64",
+        CoreType::V128 => "WASMTIME_V128",
+        CoreType::Ref => "WASMTIME_FUNCREF",
+    }
+}
+
+fn c_valkind(t: CoreType) -> &'static str {
+    match t {
+        CoreType::I32 => "WASM_I32",
+        CoreType::I64 => "WASM_I64",
+        CoreType::F32 => "WASM_F32",
+        CoreType::F64 => "WASM_F64",
+        CoreType::V128 => "WASMTIME_V128",
+        CoreType::Ref => "WASM_FUNCREF",
+    }
+}
+
+fn c_field(t: CoreType) -> &'static str {
+    match t {
+        CoreType::I32 => "i32",
+        CoreType::I64 => "i64",
+        CoreType::F32 => "f32",
+        CoreType::F64 => "f64",
+        CoreType::V128 => "v128",
+        CoreType::Ref => "funcref",
+    }
+}
+
+/// `bool app_add(app_t *app, int32_t a, int32_t b, int32_t *out0)`
+fn c_export_proto(f: &CoreFunc, p: &str, t: &str) -> String {
+    let mut params = vec![format!("{} *{}", t, p)];
+    params.extend(f.params.iter().map(|(n, ty)| format!("{} {}", c_type(*ty), c_ident(n))));
+    params.extend(f.results.iter().enumerate().map(|(i, ty)| format!("{} *out{}", c_type(*ty), i)));
+    format!("bool {}_{}({})", p, c_export_ident(&f.ident), params.join(", "))
+}
+
+/// `bool app_import_env_log(app_t *app, int32_t a0)`
+fn c_import_proto(f: &CoreFunc, p: &str, t: &str) -> String {
+    let mut params = vec![format!("{} *{}", t, p)];
+    params.extend(f.params.iter().enumerate().map(|(i, (_, ty))| format!("{} a{}", c_type(*ty), i)));
+    params.extend(f.results.iter().enumerate().map(|(i, ty)| format!("{} *out{}", c_type(*ty), i)));
+    format!("bool {}_import_{}({})", p, import_ident(f), params.join(", "))
+}
+
+fn import_ident(f: &CoreFunc) -> String {
+    c_ident(&f.ident)
+}
+
+const C_KEYWORDS: &[&str] = &[
+    "auto", "bool", "break", "case", "char", "const", "continue", "default", "do", "double", "else", "enum",
+    "extern", "float", "for", "goto", "if", "inline", "int", "long", "register", "restrict", "return", "short",
+    "signed", "sizeof", "static", "struct", "switch", "typedef", "union", "unsigned", "void", "volatile", "while",
+    "args", "results", "trap", "err",
+];
+
+const HELPER_NAMES: &[&str] = &[
+    "load", "free", "memory", "str_at", "write", "write_str", "release", "trap_message",
+    "read_bytes", "read_str", "write_bytes", "store", "instance",
+];
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false",
+    "fn", "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+    "self", "static", "struct", "super", "trait", "true", "try", "type", "unsafe", "use", "where", "while", "yield",
+];
+
+/// Methods the Rust wrappers define next to the exports.
+const RUST_METHODS: &[&str] = &[
+    "new", "from_bytes", "instantiate", "host", "host_mut", "instance", "memory", "read_bytes", "read_str",
+    "write_bytes", "write_str", "release", "with_bytes", "with_str",
+];
+
+const PY_KEYWORDS: &[&str] = &[
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+    "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda", "nonlocal",
+    "not", "or", "pass", "raise", "return", "try", "while", "with", "yield", "self",
+];
+
+/// Replace anything that isn't `[A-Za-z0-9_]`, avoid a leading digit and keywords.
+fn ident(name: &str, keywords: &[&str]) -> String {
+    let mut s: String = name.chars().map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' }).collect();
+    if s.is_empty() || s.starts_with(|c: char| c.is_ascii_digit()) {
+        s.insert(0, '_');
+    }
+    if keywords.contains(&s.as_str()) {
+        s.push('_');
+    }
+    s
+}
+
+fn c_ident(name: &str) -> String {
+    ident(name, C_KEYWORDS)
+}
+
+/// Export wrappers share the `<prefix>_` namespace with the generated helpers.
+fn c_export_ident(name: &str) -> String {
+    let id = c_ident(name);
+    if HELPER_NAMES.contains(&id.as_str()) || id.starts_with("import_") { format!("{}_export", id) } else { id }
+}
+
+fn py_ident(name: &str) -> String {
+    ident(name, PY_KEYWORDS)
+}
+
+fn pascal(s: &str) -> String {
+    let p: String = s
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w[..1].to_uppercase() + &w[1..])
+        .collect();
+    if p.is_empty() || p.starts_with(|c: char| c.is_ascii_digit()) { format!("M{}", p) } else { p }
+}
+
+fn c_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn py_str(s: &str) -> String {
+    format!("{:?}", s)
+}
+
+fn rust_header(m: &CoreModule, name: &str, wasm_name: &str, runtime: &str, deps: &str) -> String {
+    let example = m
+        .wrapped_exports()
+        .find(|f| f.name != "_initialize")
+        .map(|f| {
+            let args = f.params.iter().map(|(_, t)| if matches!(t, CoreType::F32 | CoreType::F64) { "0.0" } else { "0" });
+            format!("//!     let out = m.{}({})?;\n", rust_method_ident(&f.ident), args.collect::<Vec<_>>().join(", "))
+        })
+        .unwrap_or_default();
+    format!(
+        r#"//! Auto-generated by didspace bindgen: typed {runtime} host wrapper for {wasm_name}.
+//!
+//!     let mut m = {name}::new("{wasm_name}", NoImports)?;
+{example}//!
+//! Needs {deps}.
+#![allow(dead_code, unused_imports, non_snake_case, clippy::too_many_arguments)]
+
+use std::path::Path;
+
+use anyhow::{{anyhow, bail, Result}};
+"#
+    )
+}
+
+fn rust_imports_trait(name: &str, host: &[&CoreFunc], memory_param: &str, memory_doc: &str) -> String {
+    let mut out = format!(
+        "\n/// The module's imports. Each method traps until you implement it;\n/// {}\npub trait {}Imports: Send + 'static {{\n",
+        memory_doc, name
+    );
+    for (i, f) in host.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let params: String = f.params.iter().enumerate().map(|(i, (_, t))| format!(", a{}: {}", i, rust_type(*t))).collect();
+        let unused: Vec<String> = std::iter::once("memory".to_string()).chain((0..f.params.len()).map(|i| format!("a{}", i))).collect();
+        let unused = if unused.len() == 1 { unused.join("") } else { format!("({})", unused.join(", ")) };
+        let _ = write!(
+            out,
+            "    fn {}(&mut self, {}{}) -> Result<{}> {{\n        let _ = {};\n        bail!(\"import {}.{} is not implemented\")\n    }}\n",
+            rust_import_ident(f),
+            memory_param,
+            params,
+            rust_results(&f.results),
+            unused,
+            rust_escape(&f.module),
+            rust_escape(&f.name)
+        );
+    }
+    out.push_str("}\n\n/// A host that implements none of the imports.\npub struct NoImports;\n\n");
+    let _ = writeln!(out, "impl {}Imports for NoImports {{}}", name);
+    out
+}
+
+fn rust_fields(rs: &mut String, m: &CoreModule, kind: &str) {
+    if m.memory.is_some() {
+        rs.push_str("    memory: Memory,\n");
+    }
+    if let Some(a) = &m.allocator {
+        let params = if a.cabi { "(i32, i32, i32, i32)" } else { "i32" };
+        let _ = writeln!(rs, "    alloc: {}<{}, i32>,", kind, params);
+        if a.free.is_some() {
+            let _ = writeln!(rs, "    dealloc: {}<{}, ()>,", kind, if a.free_len { "(i32, i32)" } else { "i32" });
+        }
+    }
+    for f in m.wrapped_exports() {
+        let params: Vec<CoreType> = f.params.iter().map(|(_, t)| *t).collect();
+        let _ = writeln!(rs, "    fn_{}: {}<{}, {}>,", rust_ident(&f.ident), kind, rust_results(&params), rust_results(&f.results));
+    }
+}
+
+/// (field, export name) pairs to look up after instantiation.
+fn rust_lookups(m: &CoreModule) -> Vec<(String, String)> {
+    let mut out = Vec::new();
+    if let Some(a) = &m.allocator {
+        out.push(("alloc".to_string(), a.alloc.clone()));
+        if let Some(free) = &a.free {
+            out.push(("dealloc".to_string(), free.clone()));
+        }
+    }
+    for f in m.wrapped_exports() {
+        out.push((format!("fn_{}", rust_ident(&f.ident)), f.name.clone()));
+    }
+    out
+}
+
+/// `release`, `with_bytes` and `with_str`; `tuple` is whether the runtime
+/// takes multiple arguments as a tuple (wasmtime) or spread out (wasmer).
+fn rust_alloc_helpers(rs: &mut String, a: &Allocator, tuple: bool) {
+    match &a.free {
+        Some(free) => {
+            let args = match (a.free_len, tuple) {
+                (true, true) => "(ptr, len)",
+                (true, false) => "ptr, len",
+                (false, _) => "ptr",
+            };
+            let _ = write!(
+                rs,
+                r#"
+    /// Hand a buffer back to `{free}`.
+    pub fn release(&mut self, ptr: i32, len: i32) -> Result<()> {{
+        let _ = len;
+        self.dealloc.call(&mut self.store, {args})?;
+        Ok(())
+    }}
+
+    /// Copy `data` in, call `f` with (ptr, len), then release the buffer.
+    pub fn with_bytes<R>(&mut self, data: &[u8], f: impl FnOnce(&mut Self, i32, i32) -> Result<R>) -> Result<R> {{
+        let (ptr, len) = self.write_bytes(data)?;
+        let out = f(self, ptr, len);
+        self.release(ptr, len)?;
+        out
+    }}
+"#
+            );
+        }
+        None => rs.push_str(
+            r#"
+    /// Copy `data` in and call `f` with (ptr, len). The module exports no
+    /// free function, so the buffer stays allocated.
+    pub fn with_bytes<R>(&mut self, data: &[u8], f: impl FnOnce(&mut Self, i32, i32) -> Result<R>) -> Result<R> {
+        let (ptr, len) = self.write_bytes(data)?;
+        f(self, ptr, len)
+    }
+"#,
+        ),
+    }
+    rs.push_str(
+        r#"
+    pub fn write_str(&mut self, s: &str) -> Result<(i32, i32)> {
+        self.write_bytes(s.as_bytes())
+    }
+
+    pub fn with_str<R>(&mut self, s: &str, f: impl FnOnce(&mut Self, i32, i32) -> Result<R>) -> Result<R> {
+        self.with_bytes(s.as_bytes(), f)
+    }
+"#,
+    );
+}
+
+fn rust_type(t: CoreType) -> &'static str {
+    match t {
+        CoreType::I32 => "i32",
+        CoreType::I64 => "i64",
+        CoreType::F32 => "f32",
+        CoreType::F64 => "f64",
+        CoreType::V128 => "u128",
+        CoreType::Ref => "()",
+    }
+}
+
+fn rust_params(f: &CoreFunc) -> String {
+    f.params.iter().map(|(n, t)| format!(", {}: {}", rust_ident(n), rust_type(*t))).collect()
+}
+
+/// `()`, `i32` or `(i64, f32)`
+fn rust_results(ts: &[CoreType]) -> String {
+    match ts {
+        [t] => rust_type(*t).to_string(),
+        _ => format!("({})", ts.iter().map(|t| rust_type(*t)).collect::<Vec<_>>().join(", ")),
+    }
+}
+
+fn rust_ident(name: &str) -> String {
+    ident(name, RUST_KEYWORDS)
+}
+
+fn rust_method_ident(name: &str) -> String {
+    let id = rust_ident(name);
+    if RUST_METHODS.contains(&id.as_str()) { format!("{}_export", id) } else { id }
+}
+
+fn rust_import_ident(f: &CoreFunc) -> String {
+    rust_ident(&f.ident)
+}
+
+fn rust_escape(s: &str) -> String {
+    s.escape_default().to_string()
+}
+
+fn py_hint(t: CoreType) -> &'static str {
+    match t {
+        CoreType::F32 | CoreType::F64 => "float",
+        _ => "int",
+    }
+}
+
+fn py_valtype(t: CoreType) -> &'static str {
+    match t {
+        CoreType::I32 => "wasmtime.ValType.i32()",
+        CoreType::I64 => "wasmtime.ValType.i64()",
+        CoreType::F32 => "wasmtime.ValType.f32()",
+        CoreType::F64 => "wasmtime.ValType.f64()",
+        CoreType::V128 => "wasmtime.ValType.v128()",
+        CoreType::Ref => "wasmtime.ValType.funcref()",
+    }
+}
+
+/// wasm2c's name mangling (wabt 1.0.33+): alphanumerics stay, `_` stays unless
+/// leading, trailing or doubled, `A`-`F` after "0x" and everything else become
+/// `0xXX`. Module names double their underscores instead.
+fn w2c_mangle(name: &str, module: bool) -> String {
+    let bytes = name.as_bytes();
+    let mut out = String::new();
+    for (i, &b) in bytes.iter().enumerate() {
+        let escape = match b {
+            b'_' if module => {
+                out.push_str("__");
+                continue;
+            }
+            b'_' => i == 0 || i + 1 == bytes.len() || bytes[i - 1] == b'_',
+            b'A'..=b'F' => i >= 2 && &bytes[i - 2..i] == b"0x",
+            _ => !b.is_ascii_alphanumeric(),
+        };
+        if escape {
+            let _ = write!(out, "0x{:02X}", b);
+        } else {
+            out.push(b as char);
+        }
+    }
+    out
+}
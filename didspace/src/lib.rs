@@ -0,0 +1,58 @@
+//! didspace as a library: the analyses behind the CLI, callable in-process.
+//!
+//! Every entry point takes bytes or an options struct and returns a typed,
+//! `Serialize`-able result; the analyses themselves don't print. Turn a result into a
+//! [`Report`] with its `to_report()` / `to_sections()` view and render that
+//! as text, JSON, Markdown or HTML, or serialize the result itself.
+//!
+//! ```no_run
+//! use didspace::{doctor_report, doctor_sections, DoctorOptions, Format, Report};
+//!
+//! let bytes = std::fs::read("app.wasm")?;
+//! let rep = doctor_report(&bytes, DoctorOptions { module_path: Some("app.wasm"), ..Default::default() })?;
+//! println!("{} module, {} imports", rep.kind, rep.core.as_ref().map_or(0, |c| c.imports_count));
+//!
+//! let html = Report::new("Doctor").sections(doctor_sections(&rep)).render(Format::Html, false)?;
+//! std::fs::write("doctor.html", html)?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! Builds (`builder`, `cargo`, `wasm2c`) run external tools whose diagnostics go
+//! straight to the inherited stderr (`cargo`'s stdout is captured for its JSON);
+//! `echo: true` also prints each command line to stderr before running it.
+
+pub mod analysis;
+pub mod batch;
+pub mod bindgen;
+pub mod builder;
+pub mod cargo;
+pub mod compat;
+pub mod config;
+pub mod converter;
+pub mod doctor;
+pub mod fmt;
+pub mod hex_dump;
+pub mod hostgen;
+pub mod lint;
+pub mod report;
+pub mod roundtrip;
+pub mod runtime;
+pub mod toolchain;
+pub mod utils;
+pub mod wasi;
+pub mod wasm2c;
+
+pub use analysis::WasmAnalysis;
+pub use batch::{run_batch, BatchOptions, BatchReport};
+pub use compat::{compat_report, load_profiles, CompatReport};
+pub use config::Config;
+pub use converter::{compile_wat, wasm_to_wat, wat_to_wasm};
+pub use doctor::{doctor_report, doctor_sections, DoctorOptions, DoctorReport};
+pub use fmt::{fmt_files, FmtOptions, FmtReport, Layout};
+pub use hostgen::{CoreModule, GeneratedFile};
+pub use lint::{lint_module, validate_module, LintReport, ValidationReport};
+pub use report::{Format, Report, Section};
+pub use roundtrip::{roundtrip_file, RoundtripReport};
+pub use runtime::{smoke_run, InstanceOptions, InvokeReport, LiveInstance, SmokeOptions, SmokeReport};
+pub use toolchain::{toolchain_check, ToolchainOptions, ToolchainReport};
+pub use wasi::{analyze_component, detect_component_model, detect_wasi_imports, ComponentAnalysis};
@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use serde::Serialize;
+use wasmparser::{ExternalKind, Parser, Payload, Validator, WasmFeatures};
+
+use crate::report::Section;
+
+/// Lint rules and what they flag. Every rule defaults to "warn";
+/// `[lint.rules]` in didspace.toml can set any of them to "off" or "error".
+pub const RULES: &[(&str, &str)] = &[
+    ("memory-not-exported", "module defines a memory but does not export it as \"memory\""),
+    ("env-imports", "imports from \"env\" are not provided by WASI runtimes"),
+    ("no-name-section", "no name section: traps and profiles show func[N] only"),
+    ("debug-sections", "DWARF .debug_* sections present"),
+    ("start-and-initialize", "exports both _start and _initialize"),
+    ("large-data", "data segments larger than 1 MiB"),
+];
+
+const LARGE_DATA: usize = 1024 * 1024;
+
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub error: Option<String>,
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LintFinding {
+    pub rule: String,
+    pub level: String, // "warn" | "error"
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LintReport {
+    pub findings: Vec<LintFinding>,
+    /// Set when the rules could not be applied (e.g. components)
+    pub skipped: Option<String>,
+}
+
+impl LintReport {
+    pub fn has_errors(&self) -> bool {
+        self.findings.iter().any(|f| f.level == "error")
+    }
+}
+
+/// Full validation with every proposal wasmparser knows enabled.
+pub fn validate_module(bytes: &[u8]) -> ValidationReport {
+    match Validator::new_with_features(WasmFeatures::all()).validate_all(bytes) {
+        Ok(_) => ValidationReport { valid: true, error: None, offset: None },
+        Err(e) => ValidationReport {
+            valid: false,
+            error: Some(e.message().to_string()),
+            offset: Some(e.offset()),
+        },
+    }
+}
+
+pub fn lint_module(bytes: &[u8], kind: &str, levels: &BTreeMap<String, String>) -> Result<LintReport> {
+    if kind == "component" {
+        return Ok(LintReport {
+            findings: Vec::new(),
+            skipped: Some("lint rules apply to core modules only".to_string()),
+        });
+    }
+
+    let mut hits: Vec<(&str, String)> = Vec::new();
+    let mut defines_memory = false;
+    let mut exports_memory = false;
+    let mut has_names = false;
+    let mut debug_bytes = 0usize;
+    let mut data_bytes = 0usize;
+    let mut env_imports = Vec::new();
+    let mut has_start = false;
+    let mut has_initialize = false;
+
+    for payload in Parser::new(0).parse_all(bytes) {
+        match payload? {
+            Payload::ImportSection(reader) => {
+                for im in reader {
+                    let im = im?;
+                    if im.module == "env" {
+                        env_imports.push(im.name.to_string());
+                    }
+                }
+            }
+            Payload::MemorySection(reader) => {
+                defines_memory |= reader.count() > 0;
+            }
+            Payload::ExportSection(reader) => {
+                for ex in reader {
+                    let ex = ex?;
+                    match (ex.kind, ex.name) {
+                        (ExternalKind::Memory, "memory") => exports_memory = true,
+                        (ExternalKind::Func, "_start") => has_start = true,
+                        (ExternalKind::Func, "_initialize") => has_initialize = true,
+                        _ => {}
+                    }
+                }
+            }
+            Payload::DataSection(reader) => {
+                for d in reader {
+                    data_bytes += d?.data.len();
+                }
+            }
+            Payload::CustomSection(c) => {
+                if c.name() == "name" {
+                    has_names = true;
+                } else if c.name().starts_with(".debug_") {
+                    debug_bytes += c.data().len();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if defines_memory && !exports_memory {
+        hits.push(("memory-not-exported", "memory is defined but not exported as \"memory\"".to_string()));
+    }
+    if !env_imports.is_empty() {
+        let shown: Vec<_> = env_imports.iter().take(5).cloned().collect();
+        let more = env_imports.len().saturating_sub(shown.len());
+        hits.push((
+            "env-imports",
+            format!(
+                "{} import(s) from env: {}{}",
+                env_imports.len(),
+                shown.join(", "),
+                if more > 0 { format!(" (+{} more)", more) } else { String::new() }
+            ),
+        ));
+    }
+    if !has_names {
+        hits.push(("no-name-section", "no name section (build with debug symbols or keep names)".to_string()));
+    }
+    if debug_bytes > 0 {
+        hits.push(("debug-sections", format!("{} bytes of DWARF sections", debug_bytes)));
+    }
+    if has_start && has_initialize {
+        hits.push(("start-and-initialize", "exports both _start and _initialize".to_string()));
+    }
+    if data_bytes > LARGE_DATA {
+        hits.push(("large-data", format!("{} bytes of data segments", data_bytes)));
+    }
+
+    let findings = hits
+        .into_iter()
+        .filter_map(|(rule, message)| {
+            let level = levels.get(rule).map(String::as_str).unwrap_or("warn");
+            (level != "off").then(|| LintFinding {
+                rule: rule.to_string(),
+                level: level.to_string(),
+                message,
+            })
+        })
+        .collect();
+
+    Ok(LintReport { findings, skipped: None })
+}
+
+pub fn validation_section(v: &ValidationReport) -> Section {
+    let s = Section::new("Validation");
+    match (&v.error, v.offset) {
+        (Some(err), Some(off)) => s.finding("error", format!("{} (at offset 0x{:x})", err, off)),
+        (Some(err), None) => s.finding("error", err.clone()),
+        _ => s.finding("ok", "valid"),
+    }
+}
+
+pub fn lint_section(l: &LintReport) -> Section {
+    let mut s = Section::new("Lint");
+    if let Some(why) = &l.skipped {
+        return s.kv("skipped", why);
+    }
+    if l.findings.is_empty() {
+        s = s.finding("ok", "no findings");
+    }
+    for f in &l.findings {
+        s = s.finding(&f.level, format!("[{}] {}", f.rule, f.message));
+    }
+    s
+}
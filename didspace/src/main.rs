@@ -1,89 +1,152 @@
-use clap::{Arg, Parser, Subcommand};
+use clap::{Arg, CommandFactory, FromArgMatches, Subcommand};
 mod cli;
-use cli::{Cli, Commands};
-mod hex_dump;
-use hex_dump::wasm_to_hex;
-mod analysis;
-use analysis::WasmAnalysis;
+use cli::{BatchFlags, Cli, Commands};
+use didspace::hex_dump::wasm_to_hex;
+use didspace::analysis::WasmAnalysis;
 mod repl;
-use repl::{start_repl, CommandCompleter};
-mod utils; 
-mod converter;
+use repl::{run_script, start_repl};
+use didspace::converter::compile_wat;
 use std::fs;
-mod wasi;
-use wasi::{detect_wasi_imports, detect_component_model, analyze_component};
-mod doctor;
-use doctor::{doctor_report, report_to_text, DoctorOptions};
-mod toolchain;
-use toolchain::{toolchain_check, ToolchainOptions};
-mod bindgen;
-use bindgen::gen_rust_host_bindings;
+use didspace::wasi::{detect_wasi_imports, wasi_imports_section, analyze_component};
+use didspace::doctor::{doctor_findings, doctor_report, doctor_sections, DoctorOptions};
+use didspace::toolchain::{toolchain_check, ToolchainOptions};
+use didspace::bindgen::{gen_rust_host_bindings, WitWorld};
+use didspace::hostgen::{self, CoreModule, GeneratedFile};
+use didspace::runtime::{invoke_section, run_component, smoke_run, smoke_section, InstanceOptions, LiveInstance, SmokeOptions};
+use didspace::config::Config;
+use didspace::builder::{self, BuildFlags, BuildReport, BuildRequest, Lang};
+use didspace::cargo::{cargo_build, CargoOptions};
+use didspace::wasm2c::{wasm2c_build, Wasm2cOptions};
+use didspace::lint::{lint_module, lint_section, validate_module, validation_section};
+use didspace::fmt::{fmt_files, FmtOptions, Layout};
+use didspace::roundtrip::roundtrip_file;
+use didspace::batch::{is_batch, run_batch, BatchOptions, BatchReport};
+mod watch;
+use watch::{watch, WatchStep};
+use didspace::report::{Format, Report, Section};
+use std::path::{Path, PathBuf};
 use std::process::Command as SysCommand;
 use anyhow::anyhow;
 
 fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches)?;
+    let config = Config::load(cli.config.as_deref())?;
+    let command_name = matches.subcommand_name().unwrap_or("didspace").to_string();
+    let (stdout_format, pretty) = stdout_format(&cli.command, &config);
 
-    let result: anyhow::Result<String> = match cli.command {
+    let result: anyhow::Result<Report> = match cli.command {
         Commands::Wasm2Hex { file } => {
-            let bytes = fs::read(&file).expect("Failed to read WASM file");
+            ensure_file_exists(&file)?;
+            let bytes = fs::read(&file)?;
             let dump = wasm_to_hex(&bytes);
-            println!("{}", dump);
-            let mut out = String::new();
-            out.push_str("Wasm2Hex\n");
-            out.push_str(&format!("  file: {}\n\n", file));
-            out.push_str(&dump);
-
-            Ok(out)
+            Ok(Report::new("Wasm2Hex")
+                .section(Section::untitled().kv("file", &file))
+                .section(Section::untitled().text(dump)))
         }
 
         Commands::WasmWat { input, output } => {
             ensure_file_exists(&input)?;
-            let mut report = String::new();
-            report.push_str("Wasm2Wat\n");
             let wat = wasmprinter::print_file(&input)
                 .map_err(|e| anyhow::anyhow!("Failed to convert WASM to WAT: {}", e))?;
             fs::write(&output, wat)?;
-            println!("✅ Converted {} → {}", input, output);
-            report.push_str(&format!("  Compiled WASM → WAT: {}\n", output));
-            Ok(report)
+            Ok(Report::new("Wasm2Wat").section(
+                Section::untitled()
+                    .kv("input", &input)
+                    .kv("output", &output)
+                    .finding("ok", format!("Converted {} → {}", input, output)),
+            ))
         }
 
-        Commands::WatWasm { input, output } => {
+        Commands::WatWasm { input, output, validate, watch: watching } => {
             ensure_file_exists(&input)?;
-            let wat_src = fs::read_to_string(&input)?;
-            let mut report = String::new();
-            report.push_str("Wat2Wasm\n");
-            report.push_str(&format!("  input:  {}\n", input));
-            report.push_str(&format!("  output: {}\n", output));
-            let wasm_bytes = wat::parse_str(&wat_src)
-                .map_err(|e| anyhow::anyhow!("Failed to convert WAT to WASM: {}", e))?;
-            fs::write(&output, wasm_bytes)?;
-            println!("✅ Converted {} → {}", input, output);
-            report.push_str(&format!("  - compiled C → WASM: {}\n", output));
+            let run = || -> anyhow::Result<Report> {
+                let wat_src = fs::read_to_string(&input)?;
+                let wasm_bytes = compile_wat(&wat_src, &input, validate)
+                    .map_err(|e| anyhow::anyhow!("Failed to convert WAT to WASM:\n{}", e))?;
+                let size = wasm_bytes.len();
+                fs::write(&output, wasm_bytes)?;
+                Ok(Report::new("Wat2Wasm").section(
+                    Section::untitled()
+                        .kv("input", &input)
+                        .kv("output", &output)
+                        .kv("size", format!("{} bytes", size))
+                        .kv("validated", validate)
+                        .finding("ok", format!("Converted {} → {}", input, output)),
+                ))
+            };
+            if watching {
+                watch(vec![PathBuf::from(&input)], &config.lint.rules, || {
+                    Ok(WatchStep { report: run()?, wasm: output.clone(), deps: Vec::new() })
+                })
+            } else {
+                run()
+            }
+        }
+
+        Commands::Fmt { files, fold, unfold, check, stdout } => {
+            let layout = if fold {
+                Layout::Folded
+            } else if unfold {
+                Layout::Flat
+            } else {
+                Layout::Keep
+            };
+            let rep = fmt_files(FmtOptions { files: &files, layout, check, stdout })?;
+            for text in rep.files.iter().filter_map(|f| f.formatted.as_deref()) {
+                print!("{}", text);
+            }
+            let mut report = rep.to_report().with_data(&rep)?;
+            if !report.ok {
+                report.push(Section::untitled().finding(
+                    "error",
+                    format!("{} file(s) not formatted; run didspace fmt", rep.unformatted()),
+                ));
+            }
             Ok(report)
         }
 
-        Commands::WasmC { input, output } => {
+        Commands::Roundtrip { file } => {
+            ensure_file_exists(&file)?;
+            let rep = roundtrip_file(&file)?;
+            rep.to_report().with_data(&rep)
+        }
+
+        Commands::WasmC { input, output, build, exe, invoke, cc, wasm2c_runtime, sanitize, opt_level } => {
             ensure_file_exists(&input)?;
-            let status = SysCommand::new("wasm2c")
-                .arg(&input)
-                .arg("-o")
-                .arg(&output)
-                .status()
-                .map_err(|e| anyhow::anyhow!("Failed to run wasm2c: {}", e))?;
-            if !status.success() {
-                Err(anyhow::anyhow!("wasm2c command failed"))
+            if build {
+                let cc = cc.unwrap_or_else(|| config.native_cc());
+                let rep = wasm2c_build(Wasm2cOptions {
+                    input: &input,
+                    output: &output,
+                    exe: exe.as_deref(),
+                    invoke: invoke.as_deref(),
+                    cc: &cc,
+                    runtime_dir: wasm2c_runtime.as_deref(),
+                    sanitize: sanitize.as_deref(),
+                    opt_level: opt_level.as_deref(),
+                    echo: true,
+                })?;
+                let done = Section::untitled().finding("ok", format!("Built native {} from {}", rep.exe, input));
+                Report::new("WasmC").section(done).sections(rep.to_sections()).with_data(&rep)
             } else {
-                let mut report = String::new();
-                report.push_str("WasmC\n");
-                report.push_str(&format!("  input: {}\n", input));
-                report.push_str(&format!("  output: {}\n", output));
-                report.push_str("✅ Steps:\n");
-                report.push_str(&format!("  - ran: wasm2c {} -o {}\n", input, output));
-                println!("✅ Converted {} → {}", input, output);
-                Ok(report)
-        
+                let status = SysCommand::new("wasm2c")
+                    .arg(&input)
+                    .arg("-o")
+                    .arg(&output)
+                    .status()
+                    .map_err(|e| anyhow::anyhow!("Failed to run wasm2c: {}", e))?;
+                if !status.success() {
+                    Err(anyhow::anyhow!("wasm2c command failed"))
+                } else {
+                    Ok(Report::new("WasmC").section(
+                        Section::untitled()
+                            .kv("input", &input)
+                            .kv("output", &output)
+                            .kv("ran", format!("wasm2c {} -o {}", input, output))
+                            .finding("ok", format!("Converted {} → {}", input, output)),
+                    ))
+                }
             }
         }
 
@@ -93,24 +156,27 @@ fn main() -> anyhow::Result<()> {
             minimal,
             wasi,
             wasi_sysroot,
+            watch: watching,
+            mut build,
         } => {
-            let mut report = String::new();
-            report.push_str("CWAT\n");
-            report.push_str(&format!("  input:  {}\n", input));
-            report.push_str(&format!("  output: {}\n", output));
-            report.push_str(&format!("  mode:   {}\n", if minimal { "minimal" } else if wasi { "wasi" } else { "none" }));
-            if wasi {
-                report.push_str(&format!(
-                    "  wasi_sysroot: {}\n",
-                    wasi_sysroot.as_deref().unwrap_or("<missing>")
-                ));
+            let wasi_sysroot = config.sysroot(wasi_sysroot.as_deref());
+            let output = config.output_path(&output)?;
+            build.incremental = watching;
+            let run = || -> anyhow::Result<(Report, BuildReport)> {
+                let br = compile_c_to_wasm(&input, &output, minimal, wasi, &wasi_sysroot, &build, &config)?;
+                let head = compile_head(&input, &output, minimal, wasi, &wasi_sysroot)
+                    .finding("ok", format!("compiled C → WASM: {}", output));
+                let report = Report::new("C2Wasm").section(head).sections(br.to_sections()).with_data(&br)?;
+                Ok((report, br))
+            };
+            if watching {
+                watch(build_inputs(&input, &build), &config.lint.rules, || {
+                    let (report, br) = run()?;
+                    Ok(WatchStep { report, wasm: br.wasm.clone(), deps: builder::object_deps(&br.objects) })
+                })
+            } else {
+                run().map(|(report, _)| report)
             }
-            report.push('\n');
-            compile_c_to_wasm(&input, &output, minimal, wasi, &wasi_sysroot)?;
-            report.push_str("✅ Steps:\n");
-            report.push_str(&format!("  - compiled C → WASM: {}\n", output));
-
-            Ok(report)
         }
 
         Commands::CWAT {
@@ -119,247 +185,550 @@ fn main() -> anyhow::Result<()> {
             minimal,
             wasi,
             wasi_sysroot,
+            build,
         } => {
+            let wasi_sysroot = config.sysroot(wasi_sysroot.as_deref());
+            let output = config.output_path(&output)?;
             let temp_wasm = "temp.wasm";
-            let mut report = String::new();
-            report.push_str("CWAT\n");
-            report.push_str(&format!("  input:  {}\n", input));
-            report.push_str(&format!("  output: {}\n", output));
-            report.push_str(&format!("  mode:   {}\n", if minimal { "minimal" } else if wasi { "wasi" } else { "none" }));
-            if wasi {
-                report.push_str(&format!(
-                    "  wasi_sysroot: {}\n",
-                    wasi_sysroot.as_deref().unwrap_or("<missing>")
-                ));
-            }
-            report.push('\n');
 
-            compile_c_to_wasm(&input, temp_wasm, minimal, wasi, &wasi_sysroot)?;
+            let br = compile_c_to_wasm(&input, temp_wasm, minimal, wasi, &wasi_sysroot, &build, &config)?;
             let wat = wasmprinter::print_file(temp_wasm)?;
             fs::write(&output, wat)?;
-            report.push_str("✅ Steps:\n");
-            report.push_str(&format!("  - compiled C → WASM: {}\n", temp_wasm));
-            report.push_str(&format!("  - printed WASM → WAT (wasmprinter)\n"));
-            report.push_str(&format!("  - wrote WAT to: {}\n", output));
-            report.push_str(&format!("  - removed temp: {}\n", temp_wasm));
-
             std::fs::remove_file(temp_wasm)?;
 
-            println!("✅ Converted {} → {}", input, output);
-
-            Ok(report)
+            let head = compile_head(&input, &output, minimal, wasi, &wasi_sysroot);
+            let steps = Section::new("Steps")
+                .finding("ok", format!("compiled C → WASM: {}", temp_wasm))
+                .finding("ok", "printed WASM → WAT (wasmprinter)")
+                .finding("ok", format!("wrote WAT to: {}", output))
+                .finding("ok", format!("removed temp: {}", temp_wasm));
+            Report::new("CWAT").section(head).section(steps).sections(br.to_sections()).with_data(&br)
         }
 
 
         Commands::Cpp2Wat {
             input,
             output,
+            minimal,
             wasi,
             wasi_sysroot,
+            build,
         } => {
             let temp_wasm = "temp_cpp.wasm";
-            ensure_file_exists(&input)?;
-            if wasi {
-                println!("🔹 Compiling C++ in WASI mode...");
-                let sysroot = wasi_sysroot
-                    .as_ref()
-                    .ok_or_else(|| anyhow::anyhow!("--wasi requires --wasi-sysroot"))?;
-
-                let status = SysCommand::new("wasm32-wasi-clang++")
-                    .args([
-                        "--target=wasm32-wasi",
-                        "--sysroot", sysroot,
-                        "-D_WASI_EMULATED_SIGNAL", "-D_WASI_EMULATED_MMAN",
-                        //"-fno-exceptions",
-                        "-fno-rtti",
-                        "-o", temp_wasm,
-                        &input,
-                        "-lc++", "-lc++abi", "-lwasi-emulated-signal", "-lwasi-emulated-mman",
-                    ])
-                    .status()?;
+            let output = config.output_path(&output)?;
+            let sysroot = config.sysroot(wasi_sysroot.as_deref());
 
-                if !status.success() {
-                    return Err(anyhow::anyhow!("clang++ failed in WASI mode"));
-                }
-                println!("✅ C++ → WAT done: {}", temp_wasm);
-
-                let wat = wasmprinter::print_file(temp_wasm)?;
-                fs::write(&output, &wat)?;
-                println!("✅ Converted {} → {}", input, output);
-                std::fs::remove_file(temp_wasm)?;
-                
-                let mut report = String::new();
-                report.push_str("Cpp2Wat\n");
-                report.push_str(&format!("  input:       {}\n", input));
-                report.push_str(&format!("  output:      {}\n", output));
-                report.push_str(&format!("  wat:         {}\n", wat));
-                report.push_str(&format!("  wasi_sysroot: {}\n\n", sysroot));
-
-                report.push_str("✅ Steps:\n");
-                /*report.push_str(&format!("  - compiled C++ → WASM: {}\n", temp_wasm));*/
-                report.push_str(&format!("  - converted WASM → WAT: {}\n", output));
-                report.push_str(&format!("  - removed temp: {}\n", temp_wasm));
+            let br = compile_cpp_to_wasm(&input, temp_wasm, minimal, wasi, &sysroot, &build, &config)?;
+            let wat = wasmprinter::print_file(temp_wasm)?;
+            fs::write(&output, &wat)?;
+            std::fs::remove_file(temp_wasm)?;
 
-                Ok(report)
-            } else {
-                Err(anyhow::anyhow!("Currently only --wasi mode is supported for C++"))
-            }
+            let head = compile_head(&input, &output, minimal, wasi, &sysroot);
+            let steps = Section::new("Steps")
+                .finding("ok", format!("compiled C++ → WASM: {}", temp_wasm))
+                .finding("ok", format!("converted WASM → WAT: {}", output))
+                .finding("ok", format!("removed temp: {}", temp_wasm));
+            Report::new("Cpp2Wat").section(head).section(steps).sections(br.to_sections()).with_data(&br)
         }
 
         Commands::Cpp2Wasm {
             input,
             output,
+            minimal,
             wasi,
             wasi_sysroot,
+            watch: watching,
+            mut build,
         } => {
-            ensure_file_exists(&input)?;
-            if wasi {
-                println!("🔹 Compiling C++ in WASI mode with signal emulation...");
-                let sysroot = wasi_sysroot
-                    .as_ref()
-                    .ok_or_else(|| anyhow::anyhow!("--wasi requires --wasi-sysroot"))?;
-
-                let status = SysCommand::new("wasm32-wasi-clang++")
-                    .args([
-                        "--target=wasm32-wasi",
-                        "--sysroot", sysroot,
-                        "-D_WASI_EMULATED_SIGNAL", "-D_WASI_EMULATED_MMAN",
-                        "-fno-exceptions",
-                        "-fno-rtti",
-                        "-o", &output,
-                        &input,
-                        "-lc++", "-lc++abi", "-lwasi-emulated-signal", "-lwasi-emulated-mman",
-                    ])
-                    .status()?;
+            let output = config.output_path(&output)?;
+            let sysroot = config.sysroot(wasi_sysroot.as_deref());
+            build.incremental = watching;
+
+            let run = || -> anyhow::Result<(Report, BuildReport)> {
+                let br = compile_cpp_to_wasm(&input, &output, minimal, wasi, &sysroot, &build, &config)?;
+                let head = compile_head(&input, &output, minimal, wasi, &sysroot)
+                    .finding("ok", format!("compiled C++ → WASM: {}", output));
+                let report = Report::new("Cpp2Wasm").section(head).sections(br.to_sections()).with_data(&br)?;
+                Ok((report, br))
+            };
+            if watching {
+                watch(build_inputs(&input, &build), &config.lint.rules, || {
+                    let (report, br) = run()?;
+                    Ok(WatchStep { report, wasm: br.wasm.clone(), deps: builder::object_deps(&br.objects) })
+                })
+            } else {
+                run().map(|(report, _)| report)
+            }
+        }
 
-                if !status.success() {
-                    return Err(anyhow::anyhow!("clang++ failed in WASI mode"));
+        Commands::Build {
+            sources,
+            output,
+            minimal,
+            wasi,
+            wasi_sysroot,
+            build,
+        } => {
+            if minimal == wasi {
+                return Err(anyhow::anyhow!("Specify exactly one of --minimal or --wasi."));
+            }
+            let output = config.output_path(&output)?;
+            let sysroot = config.sysroot(wasi_sysroot.as_deref());
+            let mut sources = sources;
+            sources.extend(build.extra_sources.iter().cloned());
+            let br = builder::build(
+                BuildRequest {
+                    lang: Lang::from_sources(&sources),
+                    sources,
+                    output: &output,
+                    minimal,
+                    sysroot: sysroot.as_deref(),
+                    flags: &build,
+                    echo: true,
+                },
+                &config,
+            )?;
+            Report::new("Build").sections(br.to_sections()).with_data(&br)
+        }
+
+        Commands::CargoBuild {
+            manifest_path,
+            target,
+            profile,
+            package,
+            bin,
+            wasi_sysroot,
+            max_list,
+            validate,
+            lint,
+        } => {
+            let cb = cargo_build(CargoOptions {
+                manifest_path: manifest_path.as_deref(),
+                target: &target,
+                profile: profile.as_deref(),
+                package: package.as_deref(),
+                bin: bin.as_deref(),
+                echo: true,
+            })?;
+            let bytes = fs::read(&cb.artifact)?;
+
+            let mut report = Report::new("Cargo build")
+                .section(cb.to_section())
+                .sections(WasmAnalysis::analyze(&bytes)?.to_sections());
+
+            let wasi_sysroot = config.sysroot(wasi_sysroot.as_deref());
+            let compat_table = config.compat_table(None);
+            let rep = doctor_report(
+                &bytes,
+                DoctorOptions {
+                    wasi_sysroot: wasi_sysroot.as_deref(),
+                    max_list: config.max_list(max_list),
+                    compat_table: compat_table.as_deref(),
+                    expect_eh: None,
+                    module_path: Some(&cb.artifact),
+                },
+            )?;
+            report = report.sections(doctor_sections(&rep));
+
+            let mut failed = Vec::new();
+            if validate {
+                let v = validate_module(&bytes);
+                report.push(validation_section(&v));
+                if !v.valid {
+                    failed.push("validation");
                 }
-                println!("✅ C++ → WASM done: {}", output);
-                //Ok("Conversion from C++ to WASM completed".to_string())
-                let mut report = String::new();
-                report.push_str("Cpp2Wasm\n");
-                report.push_str(&format!("  input:       {}\n", input));
-                report.push_str(&format!("  output:      {}\n", output));
-                report.push_str(&format!("  wasi_sysroot: {}\n\n", sysroot));
+            }
+            if lint {
+                let l = lint_module(&bytes, &rep.kind, &config.lint.rules)?;
+                report.push(lint_section(&l));
+                if l.has_errors() {
+                    failed.push("lint");
+                }
+            }
 
-                report.push_str("✅ Steps:\n");
-                report.push_str(&format!("  - compiled C++ → WASM: {}\n", output));
+            if !failed.is_empty() {
+                report.ok = false;
+                report.push(Section::untitled().finding(
+                    "error",
+                    format!("{} failed for {}", failed.join(" and "), cb.artifact),
+                ));
+            }
+            report.with_data(&serde_json::json!({ "cargo": cb, "doctor": rep }))
+        }
 
-                Ok(report)
-            } else {
-                Err(anyhow::anyhow!("Currently only --wasi mode is supported for C++"))
+        Commands::Analyze { files, batch } if is_batch(&files, batch.recursive, batch.format.as_deref()) => {
+            if batch.watch {
+                return Err(anyhow!("--watch takes a single module, not a batch"));
             }
+            let rep = run_batch(BatchOptions {
+                command: "analyze",
+                inputs: &files,
+                recursive: batch.recursive,
+                jobs: batch.jobs,
+                wasi_sysroot: None,
+                compat_table: config.compat_table(None).as_deref(),
+                expect_eh: None,
+                lint_levels: &config.lint.rules,
+            })?;
+            batch_report(&rep, &batch)
         }
-        Commands::Analyze { file } => {
-            let bytes = fs::read(&file).expect("Failed to read WASM file");
-            let analysis = WasmAnalysis::analyze(&bytes);
-            println!("{}", analysis?.report());
-            Ok(WasmAnalysis::analyze_report(&bytes)?)
+
+        Commands::Analyze { files, batch } => {
+            let file = &files[0];
+            let compat_table = config.compat_table(None);
+            let run = || -> anyhow::Result<Report> {
+                let bytes = fs::read(file)?;
+                let analysis = WasmAnalysis::analyze(&bytes)?;
+                let rep = doctor_report(
+                    &bytes,
+                    DoctorOptions {
+                        wasi_sysroot: None,
+                        max_list: 0,
+                        compat_table: compat_table.as_deref(),
+                        expect_eh: None,
+                        module_path: Some(file),
+                    },
+                )?;
+                let lint = lint_module(&bytes, &rep.kind, &config.lint.rules)?;
+                Report::new("WASM Analysis")
+                    .section(
+                        Section::untitled()
+                            .kv("file", file)
+                            .kv("size", format!("{} bytes", bytes.len()))
+                            .kv("kind", &rep.kind)
+                            .kv("WASI", &rep.wasi.flavor),
+                    )
+                    .sections(analysis.to_sections())
+                    .sections(doctor_findings(&rep))
+                    .section(lint_section(&lint))
+                    .with_data(&analysis)
+            };
+            if batch.watch {
+                watch(vec![PathBuf::from(file)], &config.lint.rules, || {
+                    Ok(WatchStep { report: run()?, wasm: file.clone(), deps: Vec::new() })
+                })
+            } else {
+                run()
+            }
         }
 
         Commands::Profile { file } => {
             let bytes = std::fs::read(&file)?;
-            Ok(WasmAnalysis::profile_functions(&bytes)?)
-            //Ok("WASM profiling completed".to_string())
+            let analysis = WasmAnalysis::analyze(&bytes)?;
+            let rep = doctor_report(
+                &bytes,
+                DoctorOptions {
+                    wasi_sysroot: None,
+                    max_list: 0,
+                    compat_table: config.compat_table(None).as_deref(),
+                    expect_eh: None,
+                    module_path: Some(&file),
+                },
+            )?;
+            Report::new("Profile Functions")
+                .section(
+                    Section::untitled()
+                        .kv("file", &file)
+                        .kv("size", format!("{} bytes", bytes.len()))
+                        .kv("kind", &rep.kind)
+                        .kv("WASI", &rep.wasi.flavor),
+                )
+                .sections(analysis.to_sections())
+                .sections(analysis.profile_sections())
+                .sections(doctor_findings(&rep))
+                .with_data(&analysis)
         }
 
         Commands::Wasi { file } => {
             let bytes = std::fs::read(&file)?;
-            let (found, report) = detect_wasi_imports(&bytes)?;
-            let mut out = String::new();
-            out.push_str(&format!("file: {}\n\n", file));
-            out.push_str(&report);
-            out.push_str(&format!("\nSummary: wasi_found={}\n", found));
-            Ok(out)
-            //Ok("WASI analysis completed".to_string())
+            let imports = detect_wasi_imports(&bytes)?;
+            Report::new("WASI Imports")
+                .section(Section::untitled().kv("file", &file).kv("wasi_found", !imports.is_empty()))
+                .section(wasi_imports_section(&imports))
+                .with_data(&imports)
         }
 
         Commands::Component { file } => {
             let bytes = std::fs::read(&file)?;
-            //detect_component_model(&bytes);
-            let report = analyze_component(&bytes).map_err(|e| anyhow!(e))?;
-            // Ritorna comunque una String, per uniformarsi agli altri rami
-            Ok(report)
+            let analysis = analyze_component(&bytes)?;
+            analysis.to_report().with_data(&analysis)
         }
 
-        Commands::Doctor { file, wasi_sysroot, max_list, json, pretty, check_toolchain } => {
-            let bytes = std::fs::read(&file)?;
-            let mut rep = doctor_report(
-                &bytes,
-                DoctorOptions {
-                    wasi_sysroot: wasi_sysroot.as_deref(),
-                    max_list,
-                },
-            )?;
-
-            if check_toolchain {
-                let tc = toolchain_check(ToolchainOptions {
-                    wasi_sysroot: wasi_sysroot.as_deref(),
-                    check_cpp: true,
-                    check_wasmtime: true,
-                });
-                rep.toolchain = Some(tc);
+        Commands::Doctor { files, batch, wasi_sysroot, compat_table, expect_eh, check_toolchain, smoke, .. }
+            if is_batch(&files, batch.recursive, batch.format.as_deref()) =>
+        {
+            if smoke || check_toolchain || batch.watch {
+                return Err(anyhow!("--smoke, --check-toolchain and --watch take a single module, not a batch"));
             }
+            let wasi_sysroot = config.sysroot(wasi_sysroot.as_deref());
+            let compat_table = config.compat_table(compat_table.as_deref());
+            let rep = run_batch(BatchOptions {
+                command: "doctor",
+                inputs: &files,
+                recursive: batch.recursive,
+                jobs: batch.jobs,
+                wasi_sysroot: wasi_sysroot.as_deref(),
+                compat_table: compat_table.as_deref(),
+                expect_eh: expect_eh.as_deref(),
+                lint_levels: &config.lint.rules,
+            })?;
+            batch_report(&rep, &batch)
+        }
 
-            let out = if json {
-                if pretty {
-                    serde_json::to_string_pretty(&rep)?
-                } else {
-                    serde_json::to_string(&rep)?
+        Commands::Doctor { files, batch, wasi_sysroot, max_list, check_toolchain, compat_table, expect_eh, smoke, invoke, fuel, timeout_ms, .. } => {
+            let file = &files[0];
+            let wasi_sysroot = config.sysroot(wasi_sysroot.as_deref());
+            let compat_table = config.compat_table(compat_table.as_deref());
+            let run = || -> anyhow::Result<Report> {
+                let bytes = std::fs::read(file)?;
+                let mut rep = doctor_report(
+                    &bytes,
+                    DoctorOptions {
+                        wasi_sysroot: wasi_sysroot.as_deref(),
+                        max_list: config.max_list(max_list),
+                        compat_table: compat_table.as_deref(),
+                        expect_eh: expect_eh.as_deref(),
+                        module_path: Some(file),
+                    },
+                )?;
+
+                if check_toolchain {
+                    let tc = toolchain_check(ToolchainOptions {
+                        wasi_sysroot: wasi_sysroot.as_deref(),
+                        check_cpp: true,
+                        check_wasmtime: true,
+                        detect_versions: true,
+                        discover_sdks: true,
+                        native_cc: Some(&config.native_cc()),
+                    });
+                    rep.toolchain = Some(tc);
                 }
-            } else {
-                report_to_text(&rep)
-            };
 
-            Ok(out)
+                if smoke {
+                    rep.smoke = Some(smoke_run(
+                        &bytes,
+                        &rep.kind,
+                        SmokeOptions {
+                            invoke: invoke.as_deref(),
+                            fuel,
+                            timeout_ms,
+                        },
+                    )?);
+                }
+
+                Report::new("didspace doctor")
+                    .section(Section::untitled().kv("file", file))
+                    .sections(doctor_sections(&rep))
+                    .with_data(&rep)
+            };
+            if batch.watch {
+                watch(vec![PathBuf::from(file)], &config.lint.rules, || {
+                    Ok(WatchStep { report: run()?, wasm: file.clone(), deps: Vec::new() })
+                })
+            } else {
+                run()
+            }
         }
 
-        Commands::Bindgen { file, lang, runtime, wit, world/*, out*/ } => {
+        Commands::Bindgen { file, lang, runtime, wit, world, out } => {
             let bytes = std::fs::read(&file)?;
-            // safety: ensure it's a component
-            if crate::doctor::detect_kind(&bytes)? != "component" {
-                return Err(anyhow::anyhow!("bindgen works only for components"));
-            }
-            match lang.as_str() {
-                "rust" => {
+            let component = didspace::doctor::detect_kind(&bytes)? == "component";
+            let stem = Path::new(&file).file_stem().map_or("module".into(), |s| s.to_string_lossy().into_owned());
+            let name = world.clone().unwrap_or(stem);
+            let mut summary = Section::untitled().kv("file", &file).kv("lang", &lang);
+            let mut note = None;
+            // components: WIT from --wit, else decoded from the component, else the untyped MVP
+            let mut load_wit = || match WitWorld::load(&bytes, wit.as_deref(), world.as_deref()) {
+                Ok(w) => Ok(Some(w)),
+                // an explicit --wit that doesn't resolve is a mistake, not a reason to go untyped
+                Err(e) if wit.is_some() => Err(e),
+                Err(e) => {
+                    note = Some(format!("could not decode WIT from the component ({}); generated the untyped MVP wrapper", e));
+                    Ok(None)
+                }
+            };
+            let files = match (lang.as_str(), component) {
+                ("rust", true) => {
                     let wit = wit.as_deref().ok_or_else(|| anyhow::anyhow!("--wit is required for --lang rust"))?;
-                    Ok(crate::bindgen::gen_rust_host_bindings(wit, &world)?)
+                    let world = world.as_deref().ok_or_else(|| anyhow::anyhow!("--world is required for --lang rust"))?;
+                    summary = summary.kv("world", world).kv("types", "wit");
+                    vec![GeneratedFile { name: "bindings.rs".into(), contents: didspace::bindgen::gen_rust_host_bindings(wit, world)? }]
                 }
-                "ts" => {
+                ("ts", true) => {
                     let rt = runtime.as_deref().ok_or_else(|| anyhow::anyhow!("--runtime is required for --lang ts"))?;
                     if rt != "node" {
                         return Err(anyhow::anyhow!("Only --runtime node is supported for --lang ts"));
                     }
-                    Ok(crate::bindgen::gen_ts_node_wrapper(&world)?)
+                    let contents = match load_wit()? {
+                        Some(w) => {
+                            let name = world.clone().unwrap_or_else(|| w.name().to_string());
+                            summary = summary.kv("world", &name).kv("types", "wit");
+                            didspace::bindgen::gen_ts_typed(&w, &name)?
+                        }
+                        None => {
+                            summary = summary.kv("world", &name).kv("types", "mvp");
+                            didspace::bindgen::gen_ts_node_wrapper(&name)?
+                        }
+                    };
+                    vec![GeneratedFile { name: "bindings.ts".into(), contents }]
                 }
-                _ => Err(anyhow::anyhow!("Unsupported --lang. Use rust or ts")),
+                ("python", true) => {
+                    let w = load_wit()?;
+                    let name = match (&world, &w) {
+                        (None, Some(w)) => w.name().to_string(),
+                        _ => name,
+                    };
+                    summary = summary.kv("world", &name).kv("types", if w.is_some() { "wit" } else { "mvp" });
+                    vec![GeneratedFile { name: "bindings.py".into(), contents: didspace::bindgen::gen_python_component(w.as_ref(), &name) }]
+                }
+                ("c", true) => {
+                    match runtime.as_deref() {
+                        None | Some("wasmtime") => {}
+                        Some("wasm2c") => return Err(anyhow::anyhow!("wasm2c only translates core modules; use --runtime wasmtime for components")),
+                        Some(rt) => return Err(anyhow::anyhow!("--runtime {} is not supported for --lang c", rt)),
+                    }
+                    summary = summary.kv("world", &name).kv("types", "untyped").finding(
+                        "warn",
+                        "C has no typed component bindings: generated a loader over wasmtime's component C API; \
+                         look exports up with _func() and pass wasmtime_component_val_t values shaped after the WIT",
+                    );
+                    hostgen::gen_c_component(&name)
+                }
+                ("ts", false) => {
+                    return Err(anyhow::anyhow!("--lang ts needs a component; for core modules use --lang rust, c or python"));
+                }
+                ("rust" | "c" | "python", false) => {
+                    let m = CoreModule::parse(&bytes)?;
+                    m.check_imports()?;
+                    summary = summary
+                        .kv("world", &name)
+                        .kv("types", "core")
+                        .kv("exports", m.wrapped_exports().count())
+                        .kv("host imports", m.host_imports().count())
+                        .kv("allocator", m.allocator.as_ref().map_or("none (no string/buffer helpers)".into(), |a| a.alloc.clone()));
+                    let skipped = m.skipped_exports();
+                    if !skipped.is_empty() {
+                        summary = summary.finding("warn", format!("not wrapped (v128/reference types): {}", skipped.join(", ")));
+                    }
+                    let wasm_name = Path::new(&file).file_name().map_or(file.clone(), |s| s.to_string_lossy().into_owned());
+                    match (lang.as_str(), runtime.as_deref()) {
+                        ("rust", None | Some("wasmtime")) => hostgen::gen_rust_wasmtime(&m, &name, &wasm_name),
+                        ("rust", Some("wasmer")) => hostgen::gen_rust_wasmer(&m, &name, &wasm_name),
+                        ("c", None | Some("wasmtime")) => hostgen::gen_c_wasmtime(&m, &name),
+                        ("c", Some("wasm2c")) => hostgen::gen_c_wasm2c(&m, &name),
+                        ("python", None | Some("wasmtime")) => hostgen::gen_python(&m, &name, &wasm_name),
+                        (l, Some(rt)) => return Err(anyhow::anyhow!("--runtime {} is not supported for --lang {}", rt, l)),
+                        _ => unreachable!(),
+                    }
+                }
+                _ => return Err(anyhow::anyhow!("Unsupported --lang. Use rust, ts, c or python")),
+            };
+            if let Some(note) = note {
+                summary = summary.finding("warn", note);
+            }
+            if let Some(dir) = out {
+                fs::create_dir_all(&dir)?;
+                for f in &files {
+                    let path = Path::new(&dir).join(&f.name);
+                    fs::write(&path, &f.contents).map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+                    summary = summary.kv("written", path.display().to_string());
+                }
+                Ok(Report::new("Bindgen").section(summary))
+            } else {
+                Ok(Report::new("Bindgen")
+                    .section(summary)
+                    .sections(files.into_iter().map(|f| Section::new(format!("Generated: {}", f.name)).text(f.contents))))
             }
-     //           return Err(anyhow::anyhow!("only --lang rust supported for now"));
-       //     }
+        }
+
+        Commands::Run { file, invoke, dirs, envs, fuel, timeout_ms, max_memory, args } => {
+            ensure_file_exists(&file)?;
+            let bytes = if file.ends_with(".wat") {
+                compile_wat(&fs::read_to_string(&file)?, &file, true).map_err(|e| anyhow!("{}", e))?
+            } else {
+                fs::read(&file)?
+            };
+            let kind = didspace::doctor::detect_kind(&bytes)?;
+            // wasmtime spelling: --invoke 'add(1, 2)'
+            let (export, call_args) = match invoke.as_deref().map(|i| i.split_once('(')) {
+                Some(Some((name, rest))) => {
+                    let inner = rest.trim_end().strip_suffix(')').ok_or_else(|| anyhow!("unbalanced --invoke {}", rest))?;
+                    let parsed: Vec<String> = inner.split(',').map(|a| a.trim().to_string()).filter(|a| !a.is_empty()).collect();
+                    (Some(name.trim().to_string()), parsed.into_iter().chain(args.iter().cloned()).collect())
+                }
+                Some(None) => (invoke.clone(), args.clone()),
+                // no export: ARGS are the program's
+                None => (None, Vec::new()),
+            };
+            let argv: Vec<String> = std::iter::once(file.clone()).chain(args.iter().cloned()).collect();
+            let opts = InstanceOptions {
+                fuel,
+                timeout_ms,
+                max_memory: max_memory.map(|mib| mib * 1024 * 1024),
+                dirs: &dirs,
+                env: &envs,
+                args: if export.is_some() { &argv[..1] } else { &argv },
+                stub_imports: false,
+            };
+            let head = Section::untitled().kv("file", &file).kv("kind", &kind);
 
-            //let src = crate::bindgen::gen_rust_host_bindings(&wit, &world)?;
-            //std::fs::write(&out, src)?;
-            //Ok(format!("✅ Wrote Rust host bindings to {}", out))
-            //Ok(src)
+            if kind == "component" {
+                if !call_args.is_empty() {
+                    return Err(anyhow!("components take no call arguments here; only nullary exports can be invoked"));
+                }
+                let rep = run_component(&bytes, export.as_deref(), opts)?;
+                let mut report = Report::new("Run").section(head).section(smoke_section(&rep)).with_data(&rep)?;
+                report.ok = rep.outcome == "success";
+                Ok(report)
+            } else {
+                let entry = export.unwrap_or_else(|| "_start".to_string());
+                let call_args: Vec<&str> = call_args.iter().map(String::as_str).collect();
+                let rep = LiveInstance::new(&bytes, opts)?.invoke(&entry, &call_args)?;
+                let mut report = Report::new("Run").section(head).section(invoke_section(&rep)).with_data(&rep)?;
+                report.ok = rep.outcome == "ok" || rep.exit_code == Some(0);
+                Ok(report)
+            }
         }
 
-        Commands::Repl => {
-            start_repl();
-            Ok("Started REPL".to_string())
+        Commands::Repl { source: Some(script) } => run_script(&script),
+
+        Commands::Repl { source: None } => {
+            start_repl()?;
+            Ok(Report::new("REPL").section(Section::untitled().finding("ok", "REPL session ended")))
         }
+    };
 
-        _ => Ok("No command provided".to_string()),
+    let failed = !matches!(&result, Ok(r) if r.ok);
+    let errored = result.is_err();
+    let report = match result {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("❌ {:#}", e);
+            Report::failure(format!("didspace {}", command_name), &e)
+        }
     };
 
     if let Some(path) = cli.report {
-        let content = result?;
-        std::fs::write(&path, content)?;
-        println!("✅ Output written to {}", path);
-    } else {
-        println!("{:?}", result);
+        let path = config.report_path(&path)?;
+        let format = Format::from_path(&path);
+        fs::write(&path, report.render(format, true)?)?;
+        println!("✅ Report written to {} ({})", path, format.name());
+        if failed && !errored {
+            eprintln!("❌ didspace {} failed; see {}", command_name, path);
+        }
+    } else if let Some(format) = stdout_format {
+        // errors already went to stderr; only machine-readable formats repeat them on stdout
+        if !errored || format == Format::Json {
+            print!("{}", report.render(format, pretty)?);
+            if format == Format::Json {
+                println!();
+            }
+        }
     }
 
+    if failed {
+        std::process::exit(1);
+    }
     Ok(())
 }
 
@@ -369,52 +738,118 @@ fn compile_c_to_wasm(
     minimal: bool,
     wasi: bool,
     wasi_sysroot: &Option<String>,
-) -> anyhow::Result<()> {
+    build: &BuildFlags,
+    config: &Config,
+) -> anyhow::Result<BuildReport> {
     ensure_file_exists(&input)?;
     if minimal && wasi {
         return Err(anyhow::anyhow!("Cannot use both --minimal and --wasi."));
     }
     if minimal {
-        println!("🔹 Compiling in minimal mode...");
-        let status = SysCommand::new("wasm32-wasi-clang")
-            .args([
-                "--target=wasm32",
-                //"-nostdlib",
-                "-fno-exceptions",
-                "-fno-rtti",
-                "-Wl,--no-entry",
-                "-Wl,--export-all",
-                "-o",
-                output,
-                input,
-            ])
-            .status()?;
-        if !status.success() {
-            return Err(anyhow::anyhow!("clang failed in minimal mode"));
-        }
+        eprintln!("🔹 Compiling in minimal mode...");
     } else if wasi {
-        println!("🔹 Compiling in WASI mode with signal emulation...");
-        let sysroot = wasi_sysroot
-            .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("--wasi requires --wasi-sysroot"))?;
-        let status = SysCommand::new("wasm32-wasi-clang")
-            .args([
-                "--target=wasm32-wasi",
-                "--sysroot", sysroot,
-                "-D_WASI_EMULATED_SIGNAL", "-D_WASI_EMULATED_MMAN",
-                "-o", output,
-                input,
-                "-lwasi-emulated-signal", "-lwasi-emulated-mman",
-            ])
-            .status()?;
-        if !status.success() {
-            return Err(anyhow::anyhow!("clang failed in WASI mode"));
-        }
+        eprintln!("🔹 Compiling in WASI mode...");
     } else {
         return Err(anyhow::anyhow!("Specify --minimal or --wasi."));
     }
-    println!("✅ C → WASM done: {}", output);
-    Ok(())
+    let mut sources = vec![input.to_string()];
+    sources.extend(build.extra_sources.iter().cloned());
+    let report = builder::build(
+        BuildRequest {
+            sources,
+            output,
+            lang: Lang::C,
+            minimal,
+            sysroot: wasi_sysroot.as_deref(),
+            flags: build,
+            echo: true,
+        },
+        config,
+    )?;
+    eprintln!("✅ C → WASM done: {}", output);
+    Ok(report)
+}
+
+fn compile_cpp_to_wasm(
+    input: &str,
+    output: &str,
+    minimal: bool,
+    wasi: bool,
+    sysroot: &Option<String>,
+    build: &BuildFlags,
+    config: &Config,
+) -> anyhow::Result<BuildReport> {
+    ensure_file_exists(input)?;
+    if minimal && wasi {
+        return Err(anyhow::anyhow!("Cannot use both --minimal and --wasi."));
+    }
+    if minimal {
+        eprintln!("🔹 Compiling C++ in freestanding (minimal) mode...");
+    } else if wasi {
+        eprintln!("🔹 Compiling C++ in WASI mode...");
+    } else {
+        return Err(anyhow::anyhow!("Specify --minimal or --wasi."));
+    }
+    let mut sources = vec![input.to_string()];
+    sources.extend(build.extra_sources.iter().cloned());
+    builder::build(
+        BuildRequest {
+            sources,
+            output,
+            lang: Lang::Cxx,
+            minimal,
+            sysroot: sysroot.as_deref(),
+            flags: build,
+            echo: true,
+        },
+        config,
+    )
+}
+
+/// Files a watched build starts from; headers join once the first build writes depfiles.
+fn build_inputs(input: &str, build: &BuildFlags) -> Vec<PathBuf> {
+    std::iter::once(input)
+        .chain(build.extra_sources.iter().map(String::as_str))
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Batch reports: `--format csv` prints the CSV export itself; the model still goes to `--report`.
+fn batch_report(rep: &BatchReport, flags: &BatchFlags) -> anyhow::Result<Report> {
+    if flags.format.as_deref() == Some("csv") {
+        print!("{}", rep.to_csv());
+    }
+    rep.to_report().with_data(rep)
+}
+
+/// How the report goes to stdout when there is no `--report` file; `None` when the
+/// command already prints its output there (`fmt --stdout`, `--format csv`).
+fn stdout_format(command: &Commands, config: &Config) -> (Option<Format>, bool) {
+    match command {
+        Commands::Fmt { stdout: true, .. } => (None, false),
+        Commands::Analyze { batch, .. } | Commands::Doctor { batch, .. } if batch.format.is_some() => {
+            match batch.format.as_deref() {
+                Some("json") => (Some(Format::Json), true),
+                Some("text") => (Some(Format::Text), false),
+                _ => (None, false),
+            }
+        }
+        Commands::Doctor { json, pretty, .. } => (Some(config.report_format(*json)), config.wants_pretty(*pretty)),
+        _ => (Some(config.report_format(false)), config.wants_pretty(false)),
+    }
+}
+
+/// Input/output/mode fields shared by the single-source compile commands.
+fn compile_head(input: &str, output: &str, minimal: bool, wasi: bool, sysroot: &Option<String>) -> Section {
+    let s = Section::untitled()
+        .kv("input", input)
+        .kv("output", output)
+        .kv("mode", if minimal { "minimal" } else if wasi { "wasi" } else { "none" });
+    if wasi {
+        s.kv("wasi_sysroot", sysroot.as_deref().unwrap_or("<missing>"))
+    } else {
+        s
+    }
 }
 
 fn ensure_file_exists(path: &str) -> anyhow::Result<()> {
@@ -1,22 +1,99 @@
 use rustyline::{Editor, history::DefaultHistory};
-use rustyline::completion::{Completer, Pair};
+use rustyline::completion::{Completer, FilenameCompleter, Pair};
 use rustyline::hint::Hinter;
 use rustyline::highlight::Highlighter;
-use rustyline::Helper;
 use rustyline::error::ReadlineError;
 use rustyline::validate::{Validator, ValidationResult, ValidationContext};
+use colored::Colorize;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fs;
-use crate::utils::highlight_wat;
-use crate::hex_dump::wasm_to_hex;
-use crate::converter::{wat_to_wasm, wasm_to_wat};
-use crate::analysis::WasmAnalysis;
-use crate::wasi::{detect_wasi_imports, detect_component_model, analyze_component};
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, Context, Result};
+use didspace::utils::highlight_wat;
+use didspace::hex_dump::wasm_to_hex;
+use didspace::converter::{wat_to_wasm, wasm_to_wat};
+use didspace::analysis::WasmAnalysis;
+use didspace::doctor::detect_kind;
+use didspace::report::{Format, Report, Section};
+use didspace::runtime::{invoke_section, InstanceOptions, LiveInstance};
+use didspace::wasi::{detect_wasi_imports, wasi_imports_section, analyze_component};
 
-const COMMANDS: &[&str] = &["wat2wasm", "wasm2wat", "hex", "analyze", "help", "exit"];
+/// Command, usage, description. Drives `help`, completion and the inline hints.
+const COMMANDS: &[(&str, &str, &str)] = &[
+    ("load", "<file> [as <name>]", "parse a module once and make it current"),
+    ("use", "<name>", "switch the current module"),
+    ("modules", "", "list loaded modules"),
+    ("unload", "<name>", "drop a module from the session"),
+    ("wat2wasm", "<wat>", "compile inline WAT into the module `wat` (or just type `(module ...)`)"),
+    ("invoke", "<export> [args...]", "call an export of the current module; memory persists"),
+    ("reset", "", "re-instantiate the current module"),
+    ("wasm2wat", "[module|file]", "print the module as WAT"),
+    ("hex", "[module|file]", "hex dump"),
+    ("analyze", "[module|file]", "sections, functions, imports/exports, opcodes"),
+    ("profile", "[module|file]", "functions ranked by instruction count"),
+    ("wasi", "[module|file]", "WASI imports"),
+    ("component", "[module|file]", "component-model types, imports and exports"),
+    ("func", "<export|name|index>", "one function of the current module"),
+    ("show", "", "print the last report again"),
+    ("save", "<file>", "write the last report (.json, .md, .html, else text)"),
+    ("export", "<file>", "same as save"),
+    ("write-module", "<file>", "write the current module (.wat as text, else binary)"),
+    ("source", "<script.dsp>", "run a command file; stops at the first error"),
+    ("help", "", "this list"),
+    ("exit", "", "leave the REPL"),
+];
 
-pub struct CommandCompleter;
+/// Commands whose argument is a loaded module or a file.
+const MODULE_ARGS: &[&str] = &["wasm2wat", "hex", "analyze", "profile", "wasi", "component"];
+
+/// Fuel for each `invoke`.
+const INVOKE_FUEL: u64 = 100_000_000;
+/// Wall-clock limit for each `invoke`, in milliseconds.
+const INVOKE_TIMEOUT_MS: u64 = 5000;
+
+/// Keywords and instructions offered after `(` or a space in inline WAT.
+const WAT_WORDS: &[&str] = &[
+    "module", "func", "param", "result", "local", "global", "mut", "export", "import", "memory", "table", "data",
+    "elem", "type", "start", "block", "loop", "if", "then", "else", "end", "br", "br_if", "br_table", "return",
+    "call", "call_indirect", "unreachable", "nop", "drop", "select", "local.get", "local.set", "local.tee",
+    "global.get", "global.set", "memory.size", "memory.grow", "memory.copy", "memory.fill", "i32.load",
+    "i64.load", "f32.load", "f64.load", "i32.load8_s", "i32.load8_u", "i32.load16_s", "i32.load16_u", "i32.store",
+    "i64.store", "f32.store", "f64.store", "i32.store8", "i32.store16", "i32.const", "i64.const", "f32.const",
+    "f64.const", "i32.eqz", "i32.eq", "i32.ne", "i32.lt_s", "i32.lt_u", "i32.gt_s", "i32.gt_u", "i32.le_s",
+    "i32.le_u", "i32.ge_s", "i32.ge_u", "i64.eqz", "i64.eq", "i64.ne", "i64.lt_s", "i64.gt_s", "f32.eq",
+    "f32.lt", "f32.gt", "f64.eq", "f64.lt", "f64.gt", "i32.clz", "i32.ctz", "i32.popcnt", "i32.add", "i32.sub",
+    "i32.mul", "i32.div_s", "i32.div_u", "i32.rem_s", "i32.rem_u", "i32.and", "i32.or", "i32.xor", "i32.shl",
+    "i32.shr_s", "i32.shr_u", "i32.rotl", "i32.rotr", "i64.add", "i64.sub", "i64.mul", "i64.div_s", "i64.div_u",
+    "i64.rem_s", "i64.and", "i64.or", "i64.xor", "i64.shl", "i64.shr_s", "i64.shr_u", "f32.abs", "f32.neg",
+    "f32.sqrt", "f32.add", "f32.sub", "f32.mul", "f32.div", "f32.min", "f32.max", "f64.abs", "f64.neg",
+    "f64.sqrt", "f64.add", "f64.sub", "f64.mul", "f64.div", "f64.min", "f64.max", "i32.wrap_i64",
+    "i64.extend_i32_s", "i64.extend_i32_u", "i32.trunc_f32_s", "i32.trunc_f64_s", "f32.convert_i32_s",
+    "f64.convert_i32_s", "f64.convert_i64_s", "f32.demote_f64", "f64.promote_f32", "i32.reinterpret_f32",
+    "f32.reinterpret_i32", "i32", "i64", "f32", "f64", "v128", "funcref", "externref",
+];
+
+/// Completion, hints and highlighting for the REPL line editor.
+pub struct CommandCompleter {
+    files: FilenameCompleter,
+    /// Loaded module names
+    modules: Vec<String>,
+    /// Export and function names of the current module
+    symbols: Vec<String>,
+}
+
+impl CommandCompleter {
+    pub fn new() -> Self {
+        Self { files: FilenameCompleter::new(), modules: Vec::new(), symbols: Vec::new() }
+    }
+
+    /// Pick up modules and symbols after each command.
+    pub fn refresh(&mut self, session: &Session) {
+        self.modules = session.modules.keys().cloned().collect();
+        self.symbols = session.symbols();
+    }
+}
 
-// ✅ Implement all required traits here
 impl rustyline::Helper for CommandCompleter {}
 
 impl Completer for CommandCompleter {
@@ -25,123 +102,553 @@ impl Completer for CommandCompleter {
         &self, 
         line: &str, 
         pos: usize,
-        _ctx: &rustyline::Context<'_>
+        ctx: &rustyline::Context<'_>
     ) -> Result<(usize, Vec<Self::Candidate>), rustyline::error::ReadlineError> {
-        let candidates: Vec<Self::Candidate> = COMMANDS
-            .iter()
-            .filter(|cmd| cmd.starts_with(line))
-            .map(|cmd| rustyline::completion::Pair {
-                display: cmd.to_string(),
-                replacement: cmd.to_string(),
-            })
-            .collect();
-        Ok((pos, candidates))
+        let before = &line[..pos];
+        let start = before.rfind(|c: char| c.is_whitespace() || c == '(' || c == '|').map_or(0, |i| i + 1);
+        let word = &before[start..];
+        let cmd = current_command(before);
+        let mut words: Vec<&str> = cmd.split_whitespace().collect();
+        if !word.is_empty() {
+            words.pop();
+        }
+
+        let pool: Vec<&str> = match words.first().copied() {
+            None | Some("help") => COMMANDS.iter().map(|c| c.0).collect(),
+            Some("wat2wasm") => WAT_WORDS.to_vec(),
+            Some(w) if w.starts_with('(') => WAT_WORDS.to_vec(),
+            Some("use") | Some("unload") => self.modules.iter().map(String::as_str).collect(),
+            Some("func") | Some("invoke") if words.len() == 1 => self.symbols.iter().map(String::as_str).collect(),
+            Some("load") | Some("save") | Some("export") | Some("write-module") | Some("source") => return self.files.complete(line, pos, ctx),
+            Some(c) if MODULE_ARGS.contains(&c) && words.len() == 1 => {
+                let (file_start, mut files) = self.files.complete(line, pos, ctx)?;
+                if file_start == start {
+                    files.splice(0..0, candidates(self.modules.iter().map(String::as_str), word));
+                }
+                return Ok((file_start, files));
+            }
+            _ => Vec::new(),
+        };
+        Ok((start, candidates(pool.into_iter(), word)))
     }
 }
 
 impl Hinter for CommandCompleter {
     type Hint = String;
-    fn hint(&self, _line: &str, _pos: usize, _ctx: &rustyline::Context<'_>) -> Option<String> {
-        None
+    fn hint(&self, line: &str, pos: usize, _ctx: &rustyline::Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+        let cmd = current_command(line).trim_start();
+        if cmd.is_empty() {
+            return None;
+        }
+        match cmd.split_once(char::is_whitespace) {
+            // typing the command name: the rest of it, then its usage
+            None => {
+                let mut matches = COMMANDS.iter().filter(|c| c.0.starts_with(cmd));
+                let (name, usage, _) = matches.next()?;
+                if matches.next().is_some() {
+                    return None;
+                }
+                let rest = &name[cmd.len()..];
+                Some(if usage.is_empty() { rest.to_string() } else { format!("{} {}", rest, usage) })
+            }
+            // command typed, no argument yet: its usage
+            Some((name, args)) if args.trim().is_empty() => {
+                let (_, usage, _) = COMMANDS.iter().find(|c| c.0 == name)?;
+                let sep = if args.is_empty() { " " } else { "" };
+                (!usage.is_empty()).then(|| format!("{}{}", sep, usage))
+            }
+            _ => None,
+        }
     }
 }
 
 impl Validator for CommandCompleter {
-    fn validate(&self, _ctx: &mut ValidationContext) -> Result<ValidationResult, ReadlineError> {
-        Ok(ValidationResult::Valid(None))
+    /// Keep reading lines until the parentheses of inline WAT are balanced.
+    fn validate(&self, ctx: &mut ValidationContext) -> Result<ValidationResult, ReadlineError> {
+        Ok(match scan(ctx.input()).1 {
+            d if d > 0 => ValidationResult::Incomplete,
+            d if d < 0 => ValidationResult::Invalid(Some(" ⟵ unbalanced ')'".to_string())),
+            _ => ValidationResult::Valid(None),
+        })
+    }
+}
+
+impl Highlighter for CommandCompleter {
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(hint.dimmed().to_string())
+    }
+}
+
+/// A module held by the REPL: read and parsed once, then shared by every command.
+pub struct LoadedModule {
+    pub path: Option<String>,
+    pub bytes: Vec<u8>,
+    pub kind: String, // "core module" | "component"
+    /// Core modules only
+    pub analysis: Option<WasmAnalysis>,
+    /// Created by the first `invoke`, dropped by `reset`
+    pub instance: Option<LiveInstance>,
+}
+
+impl LoadedModule {
+    pub fn new(path: Option<String>, bytes: Vec<u8>) -> Result<Self> {
+        let kind = detect_kind(&bytes)?;
+        let analysis = if kind == "component" { None } else { Some(WasmAnalysis::analyze(&bytes)?) };
+        Ok(Self { path, bytes, kind, analysis, instance: None })
+    }
+
+    fn analysis(&self) -> Result<&WasmAnalysis> {
+        self.analysis.as_ref().ok_or_else(|| anyhow!("this is a component; try `component`"))
     }
 }
 
-impl rustyline::highlight::Highlighter for CommandCompleter {}
+/// Loaded modules, the current one and the last report.
+#[derive(Default)]
+pub struct Session {
+    pub modules: BTreeMap<String, LoadedModule>,
+    pub current: Option<String>,
+    pub last: Option<Report>,
+}
 
-pub fn start_repl() -> anyhow::Result<()> where CommandCompleter: Helper {
+impl Session {
+    /// Run one input line (possibly a `|` chain); `Ok(false)` means exit.
+    pub fn run_line(&mut self, line: &str) -> Result<bool> {
+        if line.trim_start().starts_with('#') {
+            return Ok(true);
+        }
+        for cmd in split_pipeline(line) {
+            let parts: Vec<&str> = cmd.split_whitespace().collect();
+            if parts.is_empty() {
+                continue;
+            }
+            // bare WAT, e.g. a multi-line `(module ...)`
+            if parts[0].starts_with('(') {
+                self.exec("wat2wasm", &[], &format!("wat2wasm {}", cmd.trim()))?;
+                continue;
+            }
+            if !self.exec(parts[0], &parts[1..], cmd)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn exec(&mut self, name: &str, args: &[&str], cmd: &str) -> Result<bool> {
+        match name {
+            "exit" | "quit" => return Ok(false),
+            "help" => {
+                for (cmd, usage, about) in COMMANDS {
+                    println!("  {:<34} {}", format!("{} {}", cmd, usage), about);
+                }
+                println!("Chain commands with `|`, e.g. `wat2wasm (module (func (export \"f\"))) | analyze`.");
+            }
+            "source" => {
+                let file = args.first().ok_or_else(|| anyhow!("Usage: source <script.dsp>"))?;
+                let (run, exited) = self.source(file)?;
+                println!("✅ {}: {} command(s)", file, run);
+                if exited {
+                    return Ok(false);
+                }
+            }
+            "load" => {
+                let file = args.first().ok_or_else(|| anyhow!("Usage: load <file> [as <name>]"))?;
+                let alias = match args.get(1..) {
+                    Some(["as", n]) => Some(n.to_string()),
+                    Some([]) => None,
+                    _ => return Err(anyhow!("Usage: load <file> [as <name>]")),
+                };
+                let name = self.load(file, alias)?;
+                self.describe(&name);
+            }
+            "use" => {
+                let name = args.first().ok_or_else(|| anyhow!("Usage: use <name>"))?;
+                if !self.modules.contains_key(*name) {
+                    return Err(anyhow!("no module `{}`; see `modules`", name));
+                }
+                self.current = Some(name.to_string());
+                self.describe(name);
+            }
+            "modules" => {
+                let rows = self
+                    .modules
+                    .iter()
+                    .map(|(n, m)| {
+                        let mark = if self.current.as_deref() == Some(n.as_str()) { "*" } else { "" };
+                        vec![
+                            format!("{}{}", mark, n),
+                            m.kind.clone(),
+                            m.bytes.len().to_string(),
+                            m.path.clone().unwrap_or_else(|| "-".into()),
+                        ]
+                    })
+                    .collect();
+                self.emit(Report::new("Modules").section(Section::untitled().table(&["name", "kind", "bytes", "source"], rows)));
+            }
+            "unload" => {
+                let name = args.first().ok_or_else(|| anyhow!("Usage: unload <name>"))?;
+                self.modules.remove(*name).ok_or_else(|| anyhow!("no module `{}`", name))?;
+                if self.current.as_deref() == Some(*name) {
+                    self.current = self.modules.keys().next_back().cloned();
+                }
+            }
+            "wat2wasm" => {
+                let wat = cmd.trim_start().strip_prefix("wat2wasm").unwrap_or("").trim();
+                if wat.is_empty() {
+                    return Err(anyhow!("Usage: wat2wasm <wat>"));
+                }
+                let bytes = wat_to_wasm(wat).map_err(anyhow::Error::msg)?;
+                self.modules.insert("wat".into(), LoadedModule::new(None, bytes)?);
+                self.current = Some("wat".into());
+                self.describe("wat");
+            }
+            "wasm2wat" => {
+                let m = self.target(args)?;
+                let wat = wasm_to_wat(&self.modules[&m].bytes).map_err(anyhow::Error::msg)?;
+                println!("{}", highlight_wat(&wat));
+                self.last = Some(Report::new(format!("WAT of {}", m)).section(Section::untitled().text(wat)));
+            }
+            "hex" => {
+                let m = self.target(args)?;
+                let dump = wasm_to_hex(&self.modules[&m].bytes);
+                println!("{}", dump);
+                self.last = Some(Report::new(format!("Hex dump of {}", m)).section(Section::untitled().text(dump)));
+            }
+            "analyze" => {
+                let m = self.target(args)?;
+                let a = self.modules[&m].analysis()?;
+                let report = Report::new(format!("WASM Analysis: {}", m)).sections(a.to_sections()).with_data(a)?;
+                self.emit(report);
+            }
+            "profile" => {
+                let m = self.target(args)?;
+                let a = self.modules[&m].analysis()?;
+                let report = Report::new(format!("Profile Functions: {}", m))
+                    .sections(a.to_sections())
+                    .sections(a.profile_sections())
+                    .with_data(a)?;
+                self.emit(report);
+            }
+            "wasi" => {
+                let m = self.target(args)?;
+                let imports = detect_wasi_imports(&self.modules[&m].bytes)?;
+                let report = Report::new(format!("WASI Imports: {}", m))
+                    .section(wasi_imports_section(&imports))
+                    .with_data(&imports)?;
+                self.emit(report);
+            }
+            "component" => {
+                let m = self.target(args)?;
+                let analysis = analyze_component(&self.modules[&m].bytes)?;
+                let report = analysis.to_report().with_data(&analysis)?;
+                self.emit(report);
+            }
+            "func" => {
+                let which = args.first().ok_or_else(|| anyhow!("Usage: func <export|name|index>"))?;
+                let which = which.strip_prefix('$').unwrap_or(which);
+                let m = self.target(&[])?;
+                let a = self.modules[&m].analysis()?;
+                let index = match which.parse::<u32>() {
+                    Ok(i) => i,
+                    Err(_) => a
+                        .export_list
+                        .iter()
+                        .find(|e| e.kind == "func" && e.name == which)
+                        .map(|e| e.index)
+                        .or_else(|| a.functions.iter().find(|f| f.name.as_deref() == Some(which)).map(|f| f.index))
+                        .ok_or_else(|| anyhow!("no function `{}` in {}", which, m))?,
+                };
+                let f = a
+                    .functions
+                    .iter()
+                    .find(|f| f.index == index)
+                    .ok_or_else(|| anyhow!("function {} is imported or out of range", index))?;
+                let exported: Vec<String> = a
+                    .export_list
+                    .iter()
+                    .filter(|e| e.kind == "func" && e.index == index)
+                    .map(|e| e.name.clone())
+                    .collect();
+                let section = Section::untitled()
+                    .kv("index", f.index)
+                    .kv("name", f.name.as_deref().unwrap_or("-"))
+                    .kv("exported as", if exported.is_empty() { "-".to_string() } else { exported.join(", ") })
+                    .kv("offset", f.offset)
+                    .kv("bytes", f.size)
+                    .kv("instructions", f.instructions);
+                let report = Report::new(format!("Function {}", f.label())).section(section).with_data(f)?;
+                self.emit(report);
+            }
+            "invoke" => {
+                let export = args.first().ok_or_else(|| anyhow!("Usage: invoke <export> [args...]"))?;
+                let m = self.target(&[])?;
+                let module = self.modules.get_mut(&m).ok_or_else(|| anyhow!("no module `{}`", m))?;
+                if module.kind == "component" {
+                    return Err(anyhow!("invoke works on core modules; {} is a component", m));
+                }
+                if module.instance.is_none() {
+                    let opts = InstanceOptions {
+                        fuel: INVOKE_FUEL,
+                        timeout_ms: INVOKE_TIMEOUT_MS,
+                        stub_imports: true,
+                        ..Default::default()
+                    };
+                    let instance = LiveInstance::new(&module.bytes, opts).with_context(|| format!("instantiating {}", m))?;
+                    module.instance = Some(instance);
+                }
+                let instance = module.instance.as_mut().ok_or_else(|| anyhow!("{} is not instantiated", m))?;
+                let rep = instance.invoke(export, &args[1..])?;
+                let mut report = Report::new(format!("Invoke: {}", m)).section(invoke_section(&rep)).with_data(&rep)?;
+                report.ok = rep.outcome == "ok";
+                self.emit(report);
+            }
+            "reset" => {
+                let m = self.target(&[])?;
+                if let Some(module) = self.modules.get_mut(&m) {
+                    module.instance = None;
+                }
+                println!("🔄 {} will be re-instantiated on the next invoke", m);
+            }
+            "show" => {
+                let report = self.last.as_ref().ok_or_else(|| anyhow!("no report yet"))?;
+                print!("{}", report.to_text());
+            }
+            "save" | "export" => {
+                let file = args.first().ok_or_else(|| anyhow!("Usage: {} <file>", name))?;
+                let report = self.last.as_ref().ok_or_else(|| anyhow!("no report yet"))?;
+                let format = Format::from_path(file);
+                fs::write(file, report.render(format, true)?)?;
+                println!("✅ Report written to {} ({})", file, format.name());
+            }
+            "write-module" => {
+                let file = args.first().ok_or_else(|| anyhow!("Usage: write-module <file>"))?;
+                let m = self.target(&[])?;
+                let bytes = &self.modules[&m].bytes;
+                if file.ends_with(".wat") {
+                    fs::write(file, wasm_to_wat(bytes).map_err(anyhow::Error::msg)?)?;
+                } else {
+                    fs::write(file, bytes)?;
+                }
+                println!("✅ {} written to {}", m, file);
+            }
+            _ => return Err(anyhow!("Unknown command: {} (try `help`)", name)),
+        }
+        Ok(true)
+    }
+
+    /// Run every line of a command file, skipping blanks and `#` comments.
+    /// Returns the number of commands run and whether the script asked to exit.
+    pub fn source(&mut self, file: &str) -> Result<(usize, bool)> {
+        let text = fs::read_to_string(file).map_err(|e| anyhow!("{}: {}", file, e))?;
+        let mut run = 0;
+        // (first line, text) of a command whose WAT spans several lines
+        let mut pending: Option<(usize, String)> = None;
+        for (n, line) in text.lines().enumerate() {
+            let (first, input) = match pending.take() {
+                Some((first, mut buf)) => {
+                    buf.push('\n');
+                    buf.push_str(line);
+                    (first, buf)
+                }
+                None if line.trim().is_empty() || line.trim_start().starts_with('#') => continue,
+                None => (n + 1, line.to_string()),
+            };
+            if scan(&input).1 > 0 {
+                pending = Some((first, input));
+                continue;
+            }
+            run += 1;
+            if !self.run_line(&input).with_context(|| format!("{}:{}", file, first))? {
+                return Ok((run, true));
+            }
+        }
+        if let Some((first, _)) = pending {
+            return Err(anyhow!("{}:{}: unbalanced parentheses at end of file", file, first));
+        }
+        Ok((run, false))
+    }
+
+    /// Export and function names of the current module, for completion.
+    pub fn symbols(&self) -> Vec<String> {
+        let Some(a) = self.current.as_ref().and_then(|c| self.modules[c].analysis.as_ref()) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = a.export_list.iter().map(|e| e.name.clone()).collect();
+        names.extend(a.functions.iter().filter_map(|f| f.name.clone()));
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Read and parse `file`, register it (by file stem unless `alias`) and make it current.
+    fn load(&mut self, file: &str, alias: Option<String>) -> Result<String> {
+        let bytes = fs::read(file).map_err(|e| anyhow!("{}: {}", file, e))?;
+        let bytes = if file.ends_with(".wat") {
+            wat_to_wasm(std::str::from_utf8(&bytes)?).map_err(anyhow::Error::msg)?
+        } else {
+            bytes
+        };
+        let name = alias.unwrap_or_else(|| {
+            Path::new(file).file_stem().and_then(|s| s.to_str()).unwrap_or(file).to_string()
+        });
+        self.modules.insert(name.clone(), LoadedModule::new(Some(file.to_string()), bytes)?);
+        self.current = Some(name.clone());
+        Ok(name)
+    }
+
+    /// The module a command works on: a loaded name, a file (loaded on the fly) or the current module.
+    fn target(&mut self, args: &[&str]) -> Result<String> {
+        match args.first() {
+            Some(a) if self.modules.contains_key(*a) => Ok(a.to_string()),
+            Some(file) => self.load(file, None),
+            None => self.current.clone().ok_or_else(|| anyhow!("no module loaded; use `load <file>`")),
+        }
+    }
+
+    fn describe(&self, name: &str) {
+        let m = &self.modules[name];
+        match &m.analysis {
+            Some(a) => println!(
+                "📦 {}: {}, {} bytes, {} functions, {} imports, {} exports",
+                name, m.kind, m.bytes.len(), a.function_count, a.imports, a.exports
+            ),
+            None => println!("📦 {}: {}, {} bytes", name, m.kind, m.bytes.len()),
+        }
+    }
+
+    fn emit(&mut self, report: Report) {
+        print!("{}", report.to_text());
+        self.last = Some(report);
+    }
+}
+
+pub fn start_repl() -> Result<()> {
     println!("Welcome to WASM REPL! Type 'help' for commands, 'exit' to quit.");
     let mut rl = Editor::<CommandCompleter, DefaultHistory>::new()?;
+    rl.set_helper(Some(CommandCompleter::new()));
+    let history = history_path();
+    if let Some(h) = &history {
+        let _ = rl.load_history(h);
+    }
+    let mut session = Session::default();
 
-    loop {
-        let line = rl.readline("didspace> ");
-        rl.set_helper(Some(CommandCompleter));
-        match line {
-            Ok(input) => {
-                let parts: Vec<&str> = input.trim().split_whitespace().collect();
-                if parts.is_empty() {
-                    continue;
-                }
-                match parts[0] {
-                    "exit" => break,
-                    "help" => println!("Commands: wat2wasm, wasm2wat, hex, analyze, exit"),
-                    "wat2wasm" => {
-                        let wat_code = parts[1..].join(" ");
-                        match wat_to_wasm(&wat_code) {
-                            Ok(bytes) => println!("WASM bytes: {:?}", bytes),
-                            Err(e) => eprintln!("Error: {}", e),
-                        }
-                    }
-                    "wasm2wat" => {
-                        if parts.len() < 2 {
-                            println!("Usage: wasm2wat <file>");
-                            continue;
-                        }
-                        let file = parts[1];
-                        let bytes = fs::read(file)?;
-                        match wasm_to_wat(&bytes) {
-                            Ok(wat) => println!("{}", highlight_wat(&wat)),
-                            Err(e) => eprintln!("Error: {}", e),
-                        }
-                    }
-                    "hex" => {
-                        if parts.len() < 2 {
-                            println!("Usage: hex <file>");
-                            continue;
-                        }
-                        let file = parts[1];
-                        let bytes = fs::read(file)?;
-                        println!("{}", wasm_to_hex(&bytes));
-                    }
-                    "analyze" => {
-                        if parts.len() < 2 {
-                            println!("Usage: analyze <file>");
-                            continue;
-                        }
-                        let file = parts[1];
-                        let bytes = fs::read(file)?;
-                        let report = WasmAnalysis::analyze(&bytes).map_err(anyhow::Error::msg)?;
-                        println!("{}", report.report());
-                    }
-                    "profile" => {
-                        if parts.len() < 2 {
-                            println!("Usage: profile <file>");
-                            continue;
-                        }
-                        let file = parts[1];
-                        let bytes = fs::read(file)?;
-                        WasmAnalysis::profile_functions(&bytes);
-                    }
-                    "wasi" => {
-                        if parts.len() < 2 {
-                            println!("Usage: wasi <file>");
-                            continue;
-                        }
-                        let file = parts[1];
-                        let bytes = fs::read(file)?;
-                        detect_wasi_imports(&bytes);
-                    }
-                    "component" => {
-                        if parts.len() < 2 {
-                            println!("Usage: component <file>");
-                            continue;
-                        }
-                        let file = parts[1];
-                        let bytes = fs::read(file)?;
-                        //detect_component_model(&bytes);
-                        analyze_component(&bytes);
-                    }
-                        
-                    _ => println!("Unknown command: {}", parts[0]),
+    while let Ok(mut input) = rl.readline("didspace> ") {
+        if input.trim().is_empty() {
+            continue;
+        }
+        // the validator already does this on a terminal; piped input arrives line by line
+        while scan(&input).1 > 0 {
+            match rl.readline("      ... ") {
+                Ok(more) => {
+                    input.push('\n');
+                    input.push_str(&more);
                 }
+                Err(_) => break,
             }
-            Err(_) => break,
         }
+        let _ = rl.add_history_entry(input.as_str());
+        let result = session.run_line(&input);
+        if let Some(helper) = rl.helper_mut() {
+            helper.refresh(&session);
+        }
+        match result {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => eprintln!("❌ {:#}", e),
+        }
+    }
+
+    if let Some(h) = &history {
+        if let Some(dir) = h.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        rl.save_history(h)?;
     }
     Ok(())
 }
+
+/// `didspace repl --source script.dsp`: run a command file without a prompt.
+pub fn run_script(file: &str) -> Result<Report> {
+    let mut session = Session::default();
+    let (run, _) = session.source(file)?;
+    Ok(Report::new("REPL script").section(Section::untitled().finding("ok", format!("{}: {} command(s)", file, run))))
+}
+
+// ---------- helpers ----------
+
+/// `$XDG_DATA_HOME/didspace/history.txt`, falling back to `~/.local/share` (`%APPDATA%` on Windows).
+fn history_path() -> Option<PathBuf> {
+    let data = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|h| Path::new(&h).join(".local").join("share")))?;
+    Some(data.join("didspace").join("history.txt"))
+}
+
+/// The text of the last `|` segment: the command being typed.
+fn current_command(line: &str) -> &str {
+    split_pipeline(line).pop().unwrap_or(line)
+}
+
+fn candidates<'a>(pool: impl Iterator<Item = &'a str>, word: &str) -> Vec<Pair> {
+    pool.filter(|w| w.starts_with(word))
+        .map(|w| Pair { display: w.to_string(), replacement: w.to_string() })
+        .collect()
+}
+
+/// Split on `|` outside parentheses, strings and comments, so inline WAT can be piped.
+fn split_pipeline(line: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut start = 0;
+    for pipe in scan(line).0 {
+        out.push(&line[start..pipe]);
+        start = pipe + 1;
+    }
+    out.push(&line[start..]);
+    out
+}
+
+/// Byte offsets of top-level `|` and the final parenthesis depth, skipping
+/// string literals and `;;` / `(; ;)` comments.
+fn scan(text: &str) -> (Vec<usize>, i32) {
+    let b = text.as_bytes();
+    let (mut i, mut depth, mut pipes) = (0, 0i32, Vec::new());
+    while i < b.len() {
+        match b[i] {
+            b'"' => {
+                i += 1;
+                while i < b.len() && b[i] != b'"' {
+                    i += if b[i] == b'\\' { 2 } else { 1 };
+                }
+            }
+            b';' if b.get(i + 1) == Some(&b';') => {
+                while i < b.len() && b[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'(' if b.get(i + 1) == Some(&b';') => {
+                let mut level = 1;
+                i += 2;
+                while i < b.len() && level > 0 {
+                    if b[i..].starts_with(b"(;") {
+                        level += 1;
+                        i += 1;
+                    } else if b[i..].starts_with(b";)") {
+                        level -= 1;
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                continue;
+            }
+            b'(' => depth += 1,
+            b')' => depth -= 1,
+            b'|' if depth <= 0 => pipes.push(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    (pipes, depth)
+}
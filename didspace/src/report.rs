@@ -0,0 +1,696 @@
+use std::path::Path;
+
+use anyhow::Result;
+use serde::Serialize;
+
+/// What every command returns: titled sections of key/value lists, tables,
+/// findings and preformatted text, rendered once at the end of `main`.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub title: String,
+    /// false when the command ran but its checks failed (exit code 1)
+    pub ok: bool,
+    pub sections: Vec<Section>,
+    /// The command's own result (e.g. the doctor report), included verbatim in JSON
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Section {
+    pub heading: Option<String>,
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Block {
+    KeyValue { fields: Vec<Field> },
+    Table { columns: Vec<String>, rows: Vec<Vec<String>> },
+    Findings { findings: Vec<Finding> },
+    List { items: Vec<String> },
+    /// Preformatted output (hex dumps, generated code), printed as is
+    Text { text: String },
+    /// Parts of one whole, drawn as a single proportional bar (section sizes)
+    Stacked { unit: String, items: Vec<Measure> },
+    /// One bar per item, scaled to the largest (opcode counts)
+    Histogram { unit: String, items: Vec<Measure> },
+    /// Nested rectangles sized by value (function sizes); text shows the largest
+    Treemap { unit: String, items: Vec<Measure> },
+}
+
+#[derive(Debug, Serialize)]
+pub struct Field {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Measure {
+    pub label: String,
+    pub value: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Finding {
+    pub level: String, // "ok" | "info" | "warn" | "error"
+    pub message: String,
+    pub details: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Markdown,
+    Html,
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "markdown" | "md" => Some(Format::Markdown),
+            "html" => Some(Format::Html),
+            _ => None,
+        }
+    }
+
+    /// `.json`, `.md`/`.markdown`, `.html`/`.htm`; anything else is text.
+    pub fn from_path(path: &str) -> Self {
+        let ext = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        match ext.as_str() {
+            "json" => Format::Json,
+            "md" | "markdown" => Format::Markdown,
+            "html" | "htm" => Format::Html,
+            _ => Format::Text,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Text => "text",
+            Format::Json => "json",
+            Format::Markdown => "markdown",
+            Format::Html => "html",
+        }
+    }
+}
+
+impl Report {
+    pub fn new(title: impl Into<String>) -> Self {
+        Report { title: title.into(), ok: true, sections: Vec::new(), data: None }
+    }
+
+    /// The report written for a command that failed before producing one.
+    pub fn failure(title: impl Into<String>, err: &anyhow::Error) -> Self {
+        let mut r = Report::new(title);
+        r.ok = false;
+        r.push(Section::untitled().finding("error", format!("{:#}", err)));
+        r
+    }
+
+    pub fn section(mut self, s: Section) -> Self {
+        self.sections.push(s);
+        self
+    }
+
+    pub fn sections(mut self, s: impl IntoIterator<Item = Section>) -> Self {
+        self.sections.extend(s);
+        self
+    }
+
+    pub fn push(&mut self, s: Section) {
+        self.sections.push(s);
+    }
+
+    pub fn with_data<T: Serialize>(mut self, data: &T) -> Result<Self> {
+        self.data = Some(serde_json::to_value(data)?);
+        Ok(self)
+    }
+
+    pub fn render(&self, format: Format, pretty: bool) -> Result<String> {
+        match format {
+            Format::Text => Ok(self.to_text()),
+            Format::Json => self.to_json(pretty),
+            Format::Markdown => Ok(self.to_markdown()),
+            Format::Html => Ok(self.to_html()),
+        }
+    }
+
+    pub fn to_json(&self, pretty: bool) -> Result<String> {
+        Ok(if pretty { serde_json::to_string_pretty(self)? } else { serde_json::to_string(self)? })
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!("{}\n", self.title));
+        out.push_str(&format!("{}\n", "=".repeat(self.title.chars().count())));
+        for s in &self.sections {
+            out.push('\n');
+            let indent = if let Some(h) = &s.heading {
+                out.push_str(&format!("{}:\n", h));
+                "  "
+            } else {
+                ""
+            };
+            for (i, b) in s.blocks.iter().enumerate() {
+                if i > 0 && matches!(b, Block::Table { .. } | Block::Text { .. }) {
+                    out.push('\n');
+                }
+                text_block(&mut out, b, indent);
+            }
+        }
+        out
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&format!("# {}\n", self.title));
+        for s in &self.sections {
+            if let Some(h) = &s.heading {
+                out.push_str(&format!("\n## {}\n", h));
+            }
+            for b in &s.blocks {
+                out.push('\n');
+                markdown_block(&mut out, b);
+            }
+        }
+        out
+    }
+
+    /// A standalone page: inline CSS, no scripts, nothing fetched.
+    pub fn to_html(&self) -> String {
+        let mut out = String::new();
+        out.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+        out.push_str(&format!("<title>{}</title>\n", esc(&self.title)));
+        out.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", CSS));
+        out.push_str(&format!(
+            "<h1>{} <span class=\"badge {}\">{}</span></h1>\n",
+            esc(&self.title),
+            if self.ok { "ok" } else { "error" },
+            if self.ok { "ok" } else { "failed" }
+        ));
+        for s in &self.sections {
+            out.push_str("<section>\n");
+            if let Some(h) = &s.heading {
+                out.push_str(&format!("<h2>{}</h2>\n", esc(h)));
+            }
+            for b in &s.blocks {
+                html_block(&mut out, b);
+            }
+            out.push_str("</section>\n");
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+impl Section {
+    pub fn new(heading: impl Into<String>) -> Self {
+        Section { heading: Some(heading.into()), blocks: Vec::new() }
+    }
+
+    pub fn untitled() -> Self {
+        Section { heading: None, blocks: Vec::new() }
+    }
+
+    /// Add a field, extending the key/value block the section ends with.
+    pub fn kv(mut self, key: impl Into<String>, value: impl ToString) -> Self {
+        let field = Field { key: key.into(), value: value.to_string() };
+        match self.blocks.last_mut() {
+            Some(Block::KeyValue { fields }) => fields.push(field),
+            _ => self.blocks.push(Block::KeyValue { fields: vec![field] }),
+        }
+        self
+    }
+
+    pub fn table(mut self, columns: &[&str], rows: Vec<Vec<String>>) -> Self {
+        self.blocks.push(Block::Table {
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            rows,
+        });
+        self
+    }
+
+    /// Add a finding, extending the findings block the section ends with.
+    pub fn finding(self, level: &str, message: impl Into<String>) -> Self {
+        self.finding_with(level, message, Vec::new())
+    }
+
+    pub fn finding_with(mut self, level: &str, message: impl Into<String>, details: Vec<String>) -> Self {
+        let f = Finding { level: level.to_string(), message: message.into(), details };
+        match self.blocks.last_mut() {
+            Some(Block::Findings { findings }) => findings.push(f),
+            _ => self.blocks.push(Block::Findings { findings: vec![f] }),
+        }
+        self
+    }
+
+    pub fn list(mut self, items: Vec<String>) -> Self {
+        self.blocks.push(Block::List { items });
+        self
+    }
+
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.blocks.push(Block::Text { text: text.into() });
+        self
+    }
+
+    pub fn stacked(mut self, unit: &str, items: Vec<Measure>) -> Self {
+        self.blocks.push(Block::Stacked { unit: unit.to_string(), items });
+        self
+    }
+
+    pub fn histogram(mut self, unit: &str, items: Vec<Measure>) -> Self {
+        self.blocks.push(Block::Histogram { unit: unit.to_string(), items });
+        self
+    }
+
+    pub fn treemap(mut self, unit: &str, mut items: Vec<Measure>) -> Self {
+        items.sort_by(|a, b| b.value.cmp(&a.value).then_with(|| a.label.cmp(&b.label)));
+        self.blocks.push(Block::Treemap { unit: unit.to_string(), items });
+        self
+    }
+}
+
+impl Measure {
+    pub fn new(label: impl Into<String>, value: impl TryInto<u64>) -> Self {
+        Measure { label: label.into(), value: value.try_into().unwrap_or(u64::MAX) }
+    }
+}
+
+// ---------- helpers ----------
+
+const CSS: &str = "body{font-family:system-ui,sans-serif;margin:2em auto;max-width:72em;padding:0 1em;color:#222}\
+h1{font-size:1.5em}h2{font-size:1.15em;border-bottom:1px solid #ddd;padding-bottom:.2em}\
+table{border-collapse:collapse;margin:.5em 0}th,td{border:1px solid #ddd;padding:.2em .6em;text-align:left}\
+th{background:#f4f4f4}td.num{text-align:right;font-variant-numeric:tabular-nums}\
+dl{display:grid;grid-template-columns:max-content auto;gap:.2em 1em}dt{font-weight:600}dd{margin:0}\
+pre{background:#f6f8fa;padding:.8em;overflow-x:auto}ul.findings{list-style:none;padding-left:0}\
+.badge{font-size:.6em;padding:.1em .5em;border-radius:.3em;vertical-align:middle}\
+.ok{color:#1a7f37}.badge.ok{background:#dafbe1}.info{color:#0969da}.warn{color:#9a6700}\
+.error{color:#cf222e}.badge.error{background:#ffebe9}.dim{color:#666;font-size:.9em}\
+.stack{display:flex;height:1.6em;border-radius:.3em;overflow:hidden;margin:.5em 0}.stack span{min-width:1px}\
+ul.legend{list-style:none;padding:0;columns:3}ul.legend i{display:inline-block;width:.8em;height:.8em;margin-right:.4em}\
+table.hist td{border:none;padding:.1em .6em}td.bar{width:50%}td.bar div{background:#4e79a7;height:.9em}\
+.treemap{position:relative;width:100%;aspect-ratio:2.5;background:#eee}\
+.treemap div{position:absolute;box-sizing:border-box;border:1px solid #fff;overflow:hidden;color:#fff;font-size:11px;padding:2px}";
+
+fn icon(level: &str) -> &'static str {
+    match level {
+        "ok" => "✅",
+        "warn" => "⚠️",
+        "error" => "❌",
+        _ => "•",
+    }
+}
+
+fn text_block(out: &mut String, b: &Block, indent: &str) {
+    match b {
+        Block::KeyValue { fields } => {
+            let w = fields.iter().map(|f| f.key.chars().count()).max().unwrap_or(0) + 1;
+            for f in fields {
+                out.push_str(&format!("{}{:<w$} {}\n", indent, format!("{}:", f.key), f.value, w = w));
+            }
+        }
+        Block::Table { rows, .. } if rows.is_empty() => out.push_str(&format!("{}(none)\n", indent)),
+        Block::Table { columns, rows } => {
+            let mut widths: Vec<usize> = columns.iter().map(|c| c.chars().count()).collect();
+            for row in rows {
+                for (i, cell) in row.iter().enumerate() {
+                    if let Some(w) = widths.get_mut(i) {
+                        *w = (*w).max(cell.chars().count());
+                    }
+                }
+            }
+            // numeric columns are right-aligned, header included
+            let numeric: Vec<bool> = (0..columns.len())
+                .map(|i| !rows.is_empty() && rows.iter().all(|r| r.get(i).is_none_or(|c| c.is_empty() || c == "-" || is_number(c))))
+                .collect();
+            let line = |cells: &[String]| {
+                let mut s = String::from(indent);
+                for (i, cell) in cells.iter().enumerate() {
+                    let w = widths.get(i).copied().unwrap_or(0);
+                    if numeric.get(i).copied().unwrap_or(false) {
+                        s.push_str(&format!("{:>w$}  ", cell, w = w));
+                    } else if i + 1 == cells.len() {
+                        s.push_str(cell);
+                    } else {
+                        s.push_str(&format!("{:<w$}  ", cell, w = w));
+                    }
+                }
+                format!("{}\n", s.trim_end())
+            };
+            let header: Vec<String> = columns.iter().map(|c| c.to_uppercase()).collect();
+            out.push_str(&line(&header));
+            for row in rows {
+                out.push_str(&line(row));
+            }
+        }
+        Block::Findings { findings } => {
+            for f in findings {
+                out.push_str(&format!("{}{} {}\n", indent, icon(&f.level), f.message));
+                for d in &f.details {
+                    out.push_str(&format!("{}     - {}\n", indent, d));
+                }
+            }
+        }
+        Block::List { items } => {
+            if items.is_empty() {
+                out.push_str(&format!("{}(none)\n", indent));
+            }
+            for item in items {
+                out.push_str(&format!("{} -  {}\n", indent, item));
+            }
+        }
+        Block::Text { text } => {
+            out.push_str(text);
+            if !text.ends_with('\n') {
+                out.push('\n');
+            }
+        }
+        Block::Stacked { unit, items } | Block::Histogram { unit, items } => {
+            text_measures(out, indent, unit, items, items.len())
+        }
+        Block::Treemap { unit, items } => text_measures(out, indent, unit, items, TREEMAP_ROWS),
+    }
+}
+
+/// Label, value, share of the total and a bar scaled to the largest value.
+fn text_measures(out: &mut String, indent: &str, unit: &str, items: &[Measure], limit: usize) {
+    let total: u64 = items.iter().map(|m| m.value).fold(0, u64::saturating_add);
+    let max = items.iter().map(|m| m.value).max().unwrap_or(0);
+    let lw = items.iter().take(limit).map(|m| short(&m.label).chars().count()).max().unwrap_or(0);
+    let vw = max.to_string().len();
+    for m in items.iter().take(limit) {
+        // in u128: values saturate at u64::MAX
+        let bar = (u128::from(m.value) * u128::from(BAR_WIDTH)).checked_div(u128::from(max)).unwrap_or(0) as usize;
+        out.push_str(&format!(
+            "{}{:<lw$}  {:>vw$} {}  {:>5.1}%  {}\n",
+            indent,
+            short(&m.label),
+            m.value,
+            unit,
+            share(m.value, total),
+            "█".repeat(bar),
+            lw = lw,
+            vw = vw
+        ));
+    }
+    if items.len() > limit {
+        let rest: u64 = items[limit..].iter().map(|m| m.value).fold(0, u64::saturating_add);
+        out.push_str(&format!("{}… {} more ({} {})\n", indent, items.len() - limit, rest, unit));
+    }
+}
+
+fn markdown_block(out: &mut String, b: &Block) {
+    let cell = |s: &str| s.replace('|', "\\|").replace('\n', " ");
+    match b {
+        Block::KeyValue { fields } => {
+            for f in fields {
+                out.push_str(&format!("- **{}:** {}\n", f.key, cell(&f.value)));
+            }
+        }
+        Block::Table { rows, .. } if rows.is_empty() => out.push_str("_(none)_\n"),
+        Block::Table { columns, rows } => {
+            out.push_str(&format!("| {} |\n", columns.iter().map(|c| cell(c)).collect::<Vec<_>>().join(" | ")));
+            let align: Vec<&str> = (0..columns.len())
+                .map(|i| {
+                    let numeric = !rows.is_empty() && rows.iter().all(|r| r.get(i).is_none_or(|c| c.is_empty() || c == "-" || is_number(c)));
+                    if numeric { "--:" } else { "---" }
+                })
+                .collect();
+            out.push_str(&format!("| {} |\n", align.join(" | ")));
+            for row in rows {
+                out.push_str(&format!("| {} |\n", row.iter().map(|c| cell(c)).collect::<Vec<_>>().join(" | ")));
+            }
+        }
+        Block::Findings { findings } => {
+            for f in findings {
+                out.push_str(&format!("- {} {}\n", icon(&f.level), f.message));
+                for d in &f.details {
+                    out.push_str(&format!("  - {}\n", d));
+                }
+            }
+        }
+        Block::List { items } => {
+            if items.is_empty() {
+                out.push_str("_(none)_\n");
+            }
+            for item in items {
+                out.push_str(&format!("- `{}`\n", item));
+            }
+        }
+        Block::Text { text } => {
+            // a fence longer than any backtick run inside, so the text can't close it
+            let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+            let fence = "`".repeat(longest.max(2) + 1);
+            out.push_str(&format!("{}\n{}", fence, text));
+            if !text.ends_with('\n') {
+                out.push('\n');
+            }
+            out.push_str(&fence);
+            out.push('\n');
+        }
+        Block::Stacked { unit, items } | Block::Histogram { unit, items } => {
+            markdown_measures(out, unit, items, items.len())
+        }
+        Block::Treemap { unit, items } => markdown_measures(out, unit, items, TREEMAP_ROWS),
+    }
+}
+
+fn markdown_measures(out: &mut String, unit: &str, items: &[Measure], limit: usize) {
+    let total: u64 = items.iter().map(|m| m.value).fold(0, u64::saturating_add);
+    out.push_str(&format!("| item | {} | share |\n| --- | --: | --: |\n", unit));
+    for m in items.iter().take(limit) {
+        out.push_str(&format!(
+            "| {} | {} | {:.1}% |\n",
+            m.label.replace('|', "\\|"),
+            m.value,
+            share(m.value, total)
+        ));
+    }
+    if items.len() > limit {
+        let rest: u64 = items[limit..].iter().map(|m| m.value).fold(0, u64::saturating_add);
+        out.push_str(&format!("| … {} more | {} | {:.1}% |\n", items.len() - limit, rest, share(rest, total)));
+    }
+}
+
+fn html_block(out: &mut String, b: &Block) {
+    match b {
+        Block::KeyValue { fields } => {
+            out.push_str("<dl>\n");
+            for f in fields {
+                out.push_str(&format!("<dt>{}</dt><dd>{}</dd>\n", esc(&f.key), esc(&f.value)));
+            }
+            out.push_str("</dl>\n");
+        }
+        Block::Table { rows, .. } if rows.is_empty() => out.push_str("<p><em>(none)</em></p>\n"),
+        Block::Table { columns, rows } => {
+            out.push_str("<table>\n<thead><tr>");
+            for c in columns {
+                out.push_str(&format!("<th>{}</th>", esc(c)));
+            }
+            out.push_str("</tr></thead>\n<tbody>\n");
+            for row in rows {
+                out.push_str("<tr>");
+                for c in row {
+                    let class = if is_number(c) { " class=\"num\"" } else { "" };
+                    out.push_str(&format!("<td{}>{}</td>", class, esc(c)));
+                }
+                out.push_str("</tr>\n");
+            }
+            out.push_str("</tbody>\n</table>\n");
+        }
+        Block::Findings { findings } => {
+            out.push_str("<ul class=\"findings\">\n");
+            for f in findings {
+                out.push_str(&format!(
+                    "<li class=\"{}\">{} {}",
+                    esc(&f.level),
+                    icon(&f.level),
+                    esc(&f.message)
+                ));
+                if !f.details.is_empty() {
+                    out.push_str("<ul>");
+                    for d in &f.details {
+                        out.push_str(&format!("<li>{}</li>", esc(d)));
+                    }
+                    out.push_str("</ul>");
+                }
+                out.push_str("</li>\n");
+            }
+            out.push_str("</ul>\n");
+        }
+        Block::List { items } => {
+            if items.is_empty() {
+                out.push_str("<p><em>(none)</em></p>\n");
+                return;
+            }
+            out.push_str("<ul>\n");
+            for item in items {
+                out.push_str(&format!("<li><code>{}</code></li>\n", esc(item)));
+            }
+            out.push_str("</ul>\n");
+        }
+        Block::Text { text } => out.push_str(&format!("<pre>{}</pre>\n", esc(text))),
+        Block::Stacked { unit, items } => {
+            let total: u64 = items.iter().map(|m| m.value).fold(0, u64::saturating_add);
+            out.push_str("<div class=\"stack\">");
+            for (i, m) in items.iter().enumerate() {
+                out.push_str(&format!(
+                    "<span style=\"flex-grow:{};background:{}\" title=\"{}\"></span>",
+                    m.value,
+                    color(i),
+                    esc(&tooltip(m, unit, total))
+                ));
+            }
+            out.push_str("</div>\n<ul class=\"legend\">\n");
+            for (i, m) in items.iter().enumerate() {
+                out.push_str(&format!(
+                    "<li><i style=\"background:{}\"></i>{} <span class=\"dim\">{} {} ({:.1}%)</span></li>\n",
+                    color(i),
+                    esc(&m.label),
+                    m.value,
+                    esc(unit),
+                    share(m.value, total)
+                ));
+            }
+            out.push_str("</ul>\n");
+        }
+        Block::Histogram { unit, items } => {
+            let max = items.iter().map(|m| m.value).max().unwrap_or(0).max(1);
+            out.push_str("<table class=\"hist\">\n");
+            for m in items {
+                out.push_str(&format!(
+                    "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"bar\"><div style=\"width:{:.2}%\"></div></td></tr>\n",
+                    esc(&m.label),
+                    m.value,
+                    m.value as f64 * 100.0 / max as f64
+                ));
+            }
+            out.push_str(&format!("</table>\n<p class=\"dim\">unit: {}</p>\n", esc(unit)));
+        }
+        Block::Treemap { unit, items } => {
+            let total: u64 = items.iter().map(|m| m.value).fold(0, u64::saturating_add);
+            let mut cells: Vec<Measure> = items.iter().filter(|m| m.value > 0).take(TREEMAP_CELLS).cloned().collect();
+            let shown = cells.len();
+            let rest: u64 = items.iter().filter(|m| m.value > 0).skip(TREEMAP_CELLS).map(|m| m.value).fold(0, u64::saturating_add);
+            if rest > 0 {
+                cells.push(Measure::new(format!("{} smaller items", items.len() - shown), rest));
+            }
+            let values: Vec<u64> = cells.iter().map(|m| m.value).collect();
+            let mut rects = Vec::new();
+            if !values.is_empty() {
+                split(&values, Rect { x: 0.0, y: 0.0, w: 100.0, h: 100.0 }, &mut rects);
+            }
+            out.push_str("<div class=\"treemap\">\n");
+            for (i, (m, r)) in cells.iter().zip(&rects).enumerate() {
+                out.push_str(&format!(
+                    "<div style=\"left:{:.3}%;top:{:.3}%;width:{:.3}%;height:{:.3}%;background:{}\" title=\"{}\"><span>{}</span></div>\n",
+                    r.x,
+                    r.y,
+                    r.w,
+                    r.h,
+                    color(i),
+                    esc(&tooltip(m, unit, total)),
+                    esc(&m.label)
+                ));
+            }
+            out.push_str(&format!(
+                "</div>\n<p class=\"dim\">{} item(s), {} {} in total; hover a cell for details</p>\n",
+                items.len(),
+                total,
+                esc(unit)
+            ));
+        }
+    }
+}
+
+/// Rows shown for a treemap outside HTML.
+const TREEMAP_ROWS: usize = 20;
+/// Cells drawn in an HTML treemap; smaller items are merged into one.
+const TREEMAP_CELLS: usize = 400;
+const BAR_WIDTH: u64 = 30;
+/// Width/height ratio of the treemap box (see `.treemap` in CSS).
+const TREEMAP_ASPECT: f64 = 2.5;
+
+const PALETTE: &[&str] = &[
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#ff9da7", "#9c755f", "#79706e",
+];
+
+struct Rect {
+    x: f64,
+    y: f64,
+    w: f64,
+    h: f64,
+}
+
+/// Binary-split treemap: cut the (descending) values where the running sum reaches half,
+/// across the longer side of the rectangle, and recurse. Coordinates are percentages.
+fn split(values: &[u64], r: Rect, out: &mut Vec<Rect>) {
+    if values.len() == 1 {
+        out.push(r);
+        return;
+    }
+    let total: u128 = values.iter().map(|&v| u128::from(v)).sum();
+    let mut acc = 0u128;
+    let mut k = values.len() - 1;
+    for (i, &v) in values.iter().enumerate() {
+        acc += u128::from(v);
+        if acc * 2 >= total {
+            k = (i + 1).min(values.len() - 1);
+            break;
+        }
+    }
+    let left: u128 = values[..k].iter().map(|&v| u128::from(v)).sum();
+    let f = if total > 0 { left as f64 / total as f64 } else { k as f64 / values.len() as f64 };
+    let (a, b) = if r.w * TREEMAP_ASPECT >= r.h {
+        let w = r.w * f;
+        (Rect { x: r.x, y: r.y, w, h: r.h }, Rect { x: r.x + w, y: r.y, w: r.w - w, h: r.h })
+    } else {
+        let h = r.h * f;
+        (Rect { x: r.x, y: r.y, w: r.w, h }, Rect { x: r.x, y: r.y + h, w: r.w, h: r.h - h })
+    };
+    split(&values[..k], a, out);
+    split(&values[k..], b, out);
+}
+
+fn color(i: usize) -> &'static str {
+    PALETTE[i % PALETTE.len()]
+}
+
+fn share(value: u64, total: u64) -> f64 {
+    if total == 0 { 0.0 } else { value as f64 * 100.0 / total as f64 }
+}
+
+fn tooltip(m: &Measure, unit: &str, total: u64) -> String {
+    format!("{}: {} {} ({:.1}%)", m.label, m.value, unit, share(m.value, total))
+}
+
+/// Long labels (demangled C++ names) are cut in text output.
+fn short(label: &str) -> String {
+    if label.chars().count() <= 48 {
+        return label.to_string();
+    }
+    let cut: String = label.chars().take(47).collect();
+    format!("{}…", cut)
+}
+
+pub fn esc(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn is_number(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_digit() || c == '.' || c == '-' || c == '+' || c == '%')
+        && s.chars().any(|c| c.is_ascii_digit())
+}
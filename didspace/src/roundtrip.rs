@@ -0,0 +1,333 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use wasmparser::{KnownCustom, Name, Parser, Payload, TypeRef};
+
+use crate::converter::{compile_wat, wasm_to_wat};
+use crate::fmt::first_diff;
+use crate::report::{Report, Section};
+
+const SECTION_NAMES: &[&str] = &[
+    "custom", "type", "import", "function", "table", "memory", "global", "export", "start",
+    "element", "code", "data", "datacount", "tag",
+];
+
+#[derive(Debug, Serialize)]
+pub struct SectionDiff {
+    /// Section name; custom sections are `custom:<name>`
+    pub section: String,
+    pub status: String, // "same" | "changed" | "missing" | "added"
+    pub original_size: Option<usize>,
+    pub roundtrip_size: Option<usize>,
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RoundtripReport {
+    pub file: String,
+    pub kind: String, // "wasm" | "wat"
+    /// Binary after the round trip is byte-for-byte the original
+    pub identical: bool,
+    pub sections: Vec<SectionDiff>,
+    pub first_difference: Option<String>,
+    /// WAT only: printing the re-parsed module gives the same text again
+    pub text_idempotent: Option<bool>,
+    pub text_first_diff_line: Option<usize>,
+}
+
+impl RoundtripReport {
+    pub fn ok(&self) -> bool {
+        self.identical && self.text_idempotent != Some(false)
+    }
+
+    pub fn to_report(&self) -> Report {
+        let path = if self.kind == "wat" {
+            "WAT → wasm → WAT → wasm → WAT"
+        } else {
+            "wasm → WAT (wasmprinter) → wasm (wat)"
+        };
+        let head = Section::untitled().kv("file", format!("{} ({})", self.file, self.kind)).kv("path", path);
+
+        let size = |n: Option<usize>| n.map_or("-".to_string(), |n| n.to_string());
+        let rows = self
+            .sections
+            .iter()
+            .map(|s| {
+                vec![
+                    s.section.clone(),
+                    size(s.original_size),
+                    size(s.roundtrip_size),
+                    s.status.clone(),
+                    s.detail.clone().unwrap_or_default(),
+                ]
+            })
+            .collect();
+        let sections = Section::new("Sections").table(&["section", "original", "roundtrip", "status", "detail"], rows);
+
+        let mut result = Section::new("Result");
+        if let Some(same) = self.text_idempotent {
+            result = match (same, self.text_first_diff_line) {
+                (true, _) => result.finding("ok", "printing is idempotent"),
+                (false, Some(line)) => result.finding(
+                    "error",
+                    format!("printing is not idempotent (second print differs at line {})", line),
+                ),
+                (false, None) => result.finding("error", "printing is not idempotent"),
+            };
+        }
+        result = match &self.first_difference {
+            None if self.identical => result.finding("ok", "round trip is lossless (byte-identical)"),
+            None => result.finding("error", "binaries differ"),
+            Some(d) => result.finding("error", format!("first difference: {}", d)),
+        };
+
+        let mut r = Report::new("Roundtrip").section(head).section(sections).section(result);
+        r.ok = self.ok();
+        r
+    }
+}
+
+pub fn roundtrip_file(path: &str) -> Result<RoundtripReport> {
+    let bytes = fs::read(path).map_err(|e| anyhow!("Failed to read {}: {}", path, e))?;
+    if bytes.starts_with(b"\0asm") {
+        roundtrip_wasm(path, &bytes)
+    } else {
+        let src = String::from_utf8(bytes).map_err(|_| anyhow!("{} is neither a wasm binary nor UTF-8 WAT", path))?;
+        roundtrip_wat(path, &src)
+    }
+}
+
+/// wasm → WAT → wasm, compared section by section against the original bytes.
+pub fn roundtrip_wasm(path: &str, original: &[u8]) -> Result<RoundtripReport> {
+    let text = wasm_to_wat(original).map_err(|e| anyhow!("Failed to print {}: {}", path, e))?;
+    // errors point into the printed text, not the file on disk
+    let back = compile_wat(&text, &format!("{} (printed)", path), false)
+        .map_err(|e| anyhow!("Printed WAT of {} does not parse back:\n{}", path, e))?;
+    let (sections, first_difference) = compare(original, &back)?;
+    Ok(RoundtripReport {
+        file: path.to_string(),
+        kind: "wasm".to_string(),
+        identical: original == back.as_slice(),
+        sections,
+        first_difference,
+        text_idempotent: None,
+        text_first_diff_line: None,
+    })
+}
+
+/// WAT → wasm → WAT twice: the second print must match the first, and so must the binaries.
+pub fn roundtrip_wat(path: &str, src: &str) -> Result<RoundtripReport> {
+    let first = compile_wat(src, path, false).map_err(|e| anyhow!("Failed to compile {}:\n{}", path, e))?;
+    let text1 = wasm_to_wat(&first).map_err(|e| anyhow!("Failed to print {}: {}", path, e))?;
+    let second = compile_wat(&text1, &format!("{} (printed)", path), false)
+        .map_err(|e| anyhow!("Printed WAT of {} does not parse back:\n{}", path, e))?;
+    let text2 = wasm_to_wat(&second).map_err(|e| anyhow!("Failed to print {}: {}", path, e))?;
+
+    let (sections, mut first_difference) = compare(&first, &second)?;
+    let line = first_diff(&text1, &text2);
+    if first_difference.is_none()
+        && let Some(line) = line
+    {
+        first_difference = Some(format!("printed text, line {}", line));
+    }
+    Ok(RoundtripReport {
+        file: path.to_string(),
+        kind: "wat".to_string(),
+        identical: first == second,
+        sections,
+        first_difference,
+        text_idempotent: Some(line.is_none()),
+        text_first_diff_line: line,
+    })
+}
+
+// ---------- helpers ----------
+
+struct RawSection<'a> {
+    key: String,
+    id: u8,
+    data: &'a [u8],
+}
+
+fn compare(original: &[u8], back: &[u8]) -> Result<(Vec<SectionDiff>, Option<String>)> {
+    if original.get(4..8) != Some(&[1, 0, 0, 0][..]) {
+        // components nest modules inside sections; compare them as a whole
+        let same = original == back;
+        let diff = SectionDiff {
+            section: "component".to_string(),
+            status: if same { "same" } else { "changed" }.to_string(),
+            original_size: Some(original.len()),
+            roundtrip_size: Some(back.len()),
+            detail: None,
+        };
+        let first = (!same).then(|| "component binary differs (compared as a whole)".to_string());
+        return Ok((vec![diff], first));
+    }
+
+    let left = sections(original)?;
+    let right = sections(back)?;
+    let right_by_key: BTreeMap<&str, &RawSection> = right.iter().map(|s| (s.key.as_str(), s)).collect();
+    let names = func_names(original);
+    let imported = imported_funcs(original);
+
+    let mut diffs = Vec::new();
+    let mut first = None;
+    for l in &left {
+        let r = right_by_key.get(l.key.as_str());
+        let (status, detail) = match r {
+            None => ("missing", Some("dropped by the round trip".to_string())),
+            Some(r) if r.data == l.data => ("same", None),
+            Some(r) if l.id == 10 => ("changed", Some(code_diff(l.data, r.data, imported, &names))),
+            Some(r) => ("changed", Some(format!("first differing byte at section offset {}", mismatch(l.data, r.data)))),
+        };
+        if first.is_none() && status != "same" {
+            first = Some(match &detail {
+                Some(d) => format!("{} section {}: {}", l.key, status, d),
+                None => format!("{} section {}", l.key, status),
+            });
+        }
+        diffs.push(SectionDiff {
+            section: l.key.clone(),
+            status: status.to_string(),
+            original_size: Some(l.data.len()),
+            roundtrip_size: r.map(|r| r.data.len()),
+            detail,
+        });
+    }
+    for r in &right {
+        if left.iter().any(|l| l.key == r.key) {
+            continue;
+        }
+        if first.is_none() {
+            first = Some(format!("{} section added by the round trip", r.key));
+        }
+        diffs.push(SectionDiff {
+            section: r.key.clone(),
+            status: "added".to_string(),
+            original_size: None,
+            roundtrip_size: Some(r.data.len()),
+            detail: None,
+        });
+    }
+    if first.is_none() && original != back {
+        first = Some("section order or framing differs".to_string());
+    }
+    Ok((diffs, first))
+}
+
+fn sections(bytes: &[u8]) -> Result<Vec<RawSection<'_>>> {
+    let mut out = Vec::new();
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    let mut pos = 8;
+    while pos < bytes.len() {
+        let id = bytes[pos];
+        pos += 1;
+        let size = read_u32(bytes, &mut pos).ok_or_else(|| anyhow!("truncated section header at {}", pos))? as usize;
+        let data = bytes
+            .get(pos..pos + size)
+            .ok_or_else(|| anyhow!("section at offset {} runs past the end of the file", pos))?;
+        pos += size;
+
+        let mut key = match id {
+            0 => {
+                let mut p = 0;
+                let len = read_u32(data, &mut p).unwrap_or(0) as usize;
+                let name = data.get(p..p + len).map(String::from_utf8_lossy).unwrap_or_default();
+                format!("custom:{}", name)
+            }
+            _ => SECTION_NAMES.get(id as usize).map_or_else(|| format!("section#{}", id), |s| s.to_string()),
+        };
+        // repeated custom sections are matched by occurrence
+        let n = seen.entry(key.clone()).or_insert(0);
+        *n += 1;
+        if *n > 1 {
+            key = format!("{}#{}", key, n);
+        }
+        out.push(RawSection { key, id, data });
+    }
+    Ok(out)
+}
+
+fn read_u32(bytes: &[u8], pos: &mut usize) -> Option<u32> {
+    let mut result = 0u32;
+    let mut shift = 0;
+    loop {
+        let b = *bytes.get(*pos)?;
+        *pos += 1;
+        result |= u32::from(b & 0x7f).checked_shl(shift)?;
+        if b & 0x80 == 0 {
+            return Some(result);
+        }
+        shift += 7;
+    }
+}
+
+fn mismatch(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).position(|(x, y)| x != y).unwrap_or(a.len().min(b.len()))
+}
+
+fn bodies(data: &[u8]) -> Vec<&[u8]> {
+    let mut pos = 0;
+    let count = read_u32(data, &mut pos).unwrap_or(0);
+    let mut out = Vec::new();
+    for _ in 0..count {
+        let Some(size) = read_u32(data, &mut pos) else { break };
+        let Some(body) = data.get(pos..pos + size as usize) else { break };
+        pos += size as usize;
+        out.push(body);
+    }
+    out
+}
+
+fn code_diff(left: &[u8], right: &[u8], imported: u32, names: &BTreeMap<u32, String>) -> String {
+    let l = bodies(left);
+    let r = bodies(right);
+    if l.len() != r.len() {
+        return format!("{} function bodies → {}", l.len(), r.len());
+    }
+    let Some(i) = l.iter().zip(&r).position(|(a, b)| a != b) else {
+        return "bodies equal; section framing differs".to_string();
+    };
+    let index = imported + i as u32;
+    let name = names.get(&index).map(|n| format!(" ${}", n)).unwrap_or_default();
+    format!(
+        "func[{}]{} body differs at byte {} ({} → {} bytes)",
+        index,
+        name,
+        mismatch(l[i], r[i]),
+        l[i].len(),
+        r[i].len()
+    )
+}
+
+fn imported_funcs(bytes: &[u8]) -> u32 {
+    let mut n = 0;
+    for payload in Parser::new(0).parse_all(bytes) {
+        if let Ok(Payload::ImportSection(reader)) = payload {
+            for im in reader.into_iter().flatten() {
+                if matches!(im.ty, TypeRef::Func(_)) {
+                    n += 1;
+                }
+            }
+        }
+    }
+    n
+}
+
+fn func_names(bytes: &[u8]) -> BTreeMap<u32, String> {
+    let mut names = BTreeMap::new();
+    for payload in Parser::new(0).parse_all(bytes) {
+        let Ok(Payload::CustomSection(c)) = payload else { continue };
+        let KnownCustom::Name(reader) = c.as_known() else { continue };
+        for sub in reader.into_iter().flatten() {
+            if let Name::Function(map) = sub {
+                for naming in map.into_iter().flatten() {
+                    names.insert(naming.index, naming.name.to_string());
+                }
+            }
+        }
+    }
+    names
+}
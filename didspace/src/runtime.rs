@@ -0,0 +1,721 @@
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use wasmtime::{
+    Config, Engine, FuncType, Instance, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder, Trap, Val,
+    ValType, WasmBacktrace, V128,
+};
+use wasmtime::component::{Component, Linker as ComponentLinker, ResourceTable};
+use wasmtime_wasi::pipe::MemoryOutputPipe;
+use wasmtime_wasi::preview1::WasiP1Ctx;
+use wasmtime_wasi::{DirPerms, FilePerms, I32Exit, IoView, WasiCtx, WasiCtxBuilder, WasiView};
+
+use crate::report::Section;
+
+/// Bytes of guest stdout/stderr kept in the report.
+const CAPTURE_LIMIT: usize = 64 * 1024;
+
+/// Changed memory ranges listed per memory after an invoke.
+const MAX_DELTA_RANGES: usize = 16;
+/// Bytes shown per changed range.
+const DELTA_BYTES: usize = 16;
+
+#[derive(Debug, Serialize)]
+pub struct SmokeReport {
+    pub outcome: String, // "success" | "exit" | "trap" | "unresolved-import" | "no-entry" | "error"
+    pub entry: Option<String>,
+    pub exit_code: Option<i32>,
+    pub message: Option<String>,
+    pub backtrace: Vec<String>,
+    pub unresolved_imports: Vec<String>,
+    pub fuel_consumed: Option<u64>,
+    pub elapsed_ms: u128,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+pub struct SmokeOptions<'a> {
+    /// Export to call instead of `_start` / `wasi:cli/run`.
+    pub invoke: Option<&'a str>,
+    pub fuel: u64,
+    pub timeout_ms: u64,
+}
+
+#[derive(Default)]
+pub struct InstanceOptions<'a> {
+    /// Fuel per call
+    pub fuel: u64,
+    /// Wall-clock limit per call, in milliseconds
+    pub timeout_ms: u64,
+    /// Cap on each linear memory, in bytes
+    pub max_memory: Option<usize>,
+    /// Preopened directories, `HOST[::GUEST]` as in `wasmtime run --dir`
+    pub dirs: &'a [String],
+    /// `NAME=VALUE`, or `NAME` to pass the host value through
+    pub env: &'a [String],
+    /// Program arguments, `argv[0]` included
+    pub args: &'a [String],
+    /// Let imports WASI does not provide trap when called instead of failing instantiation
+    pub stub_imports: bool,
+}
+
+/// A core module instantiated once and kept alive between calls, so memory
+/// and globals carry over from one invoke to the next. WASI preview1 is
+/// linked with inherited stdio.
+pub struct LiveInstance {
+    engine: Engine,
+    store: Store<CoreState>,
+    instance: Instance,
+    fuel: u64,
+    timeout_ms: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InvokeReport {
+    pub export: String,
+    /// `(i32, i32) -> i32`
+    pub signature: String,
+    pub args: Vec<String>,
+    pub outcome: String, // "ok" | "exit" | "trap" | "error"
+    pub results: Vec<String>,
+    pub exit_code: Option<i32>,
+    pub message: Option<String>,
+    pub backtrace: Vec<String>,
+    pub fuel_consumed: Option<u64>,
+    pub elapsed_ms: u128,
+    /// Exported memories the call touched
+    pub memory: Vec<MemoryDelta>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MemoryDelta {
+    pub memory: String,
+    pub pages_before: u64,
+    pub pages_after: u64,
+    pub changed_bytes: usize,
+    /// First changed ranges, as hex
+    pub changes: Vec<ByteChange>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ByteChange {
+    pub offset: usize,
+    pub len: usize,
+    pub before: String,
+    pub after: String,
+}
+
+impl LiveInstance {
+    pub fn new(bytes: &[u8], opts: InstanceOptions<'_>) -> Result<Self> {
+        let engine = make_engine()?;
+        let module = Module::new(&engine, bytes)?;
+        let mut linker: Linker<CoreState> = Linker::new(&engine);
+        wasmtime_wasi::preview1::add_to_linker_sync(&mut linker, |s| &mut s.wasi)?;
+
+        let wasi = wasi_builder(&opts)?.build_p1();
+        let mut store = Store::new(&engine, CoreState { wasi, limits: store_limits(opts.max_memory) });
+        store.limiter(|s| &mut s.limits);
+        store.set_fuel(opts.fuel)?;
+        store.set_epoch_deadline(1);
+
+        if opts.stub_imports {
+            linker.define_unknown_imports_as_traps(&module)?;
+        } else {
+            let unresolved: Vec<String> = module
+                .imports()
+                .filter(|im| linker.get_by_import(&mut store, im).is_none())
+                .map(|im| format!("{}::{}", im.module(), im.name()))
+                .collect();
+            if !unresolved.is_empty() {
+                return Err(anyhow!("imports not provided by WASI preview1: {}", unresolved.join(", ")));
+            }
+        }
+
+        let _watchdog = spawn_watchdog(&engine, opts.timeout_ms);
+        // runs the start function, if any
+        let instance = linker.instantiate(&mut store, &module)?;
+        if let Ok(init) = instance.get_typed_func::<(), ()>(&mut store, "_initialize") {
+            init.call(&mut store, ())?;
+        }
+        Ok(Self { engine, store, instance, fuel: opts.fuel, timeout_ms: opts.timeout_ms })
+    }
+
+    /// Exported functions with their signatures.
+    pub fn functions(&mut self) -> Vec<(String, String)> {
+        let funcs: Vec<_> = self
+            .instance
+            .exports(&mut self.store)
+            .filter_map(|e| {
+                let name = e.name().to_string();
+                e.into_func().map(|f| (name, f))
+            })
+            .collect();
+        funcs.into_iter().map(|(name, f)| (name, signature(&f.ty(&self.store)))).collect()
+    }
+
+    /// Call `export` with `args` parsed against its signature, reporting
+    /// results or the trap, and what changed in exported memories.
+    pub fn invoke(&mut self, export: &str, args: &[&str]) -> Result<InvokeReport> {
+        let func = self
+            .instance
+            .get_func(&mut self.store, export)
+            .ok_or_else(|| {
+                let funcs: Vec<String> = self.functions().into_iter().map(|(n, sig)| n + &sig).collect();
+                anyhow!("export '{}' not found or not a function; exports: {}", export, funcs.join(", "))
+            })?;
+        let ty = func.ty(&self.store);
+        let params = parse_args(&ty, args).map_err(|e| anyhow!("{}{}: {}", export, signature(&ty), e))?;
+        let mut results: Vec<Val> = ty.results().map(|_| Val::I32(0)).collect();
+
+        let memories = self.memories();
+        let before: Vec<Vec<u8>> = memories.iter().map(|(_, m)| m.data(&self.store).to_vec()).collect();
+
+        self.store.set_fuel(self.fuel)?;
+        self.store.set_epoch_deadline(1);
+        let started = Instant::now();
+        let watchdog = spawn_watchdog(&self.engine, self.timeout_ms);
+        let result = func.call(&mut self.store, &params, &mut results);
+        drop(watchdog);
+
+        let (outcome, exit_code, message, backtrace) = match result {
+            Ok(()) => ("ok".to_string(), None, None, Vec::new()),
+            Err(e) => {
+                let failure = classify(e, self.timeout_ms);
+                let outcome = if failure.outcome == "success" { "exit".to_string() } else { failure.outcome };
+                (outcome, failure.exit_code, failure.message, failure.backtrace)
+            }
+        };
+        let memory = memories
+            .iter()
+            .zip(&before)
+            .filter_map(|((name, mem), old)| {
+                let pages_before = (old.len() / (64 * 1024)) as u64;
+                diff_memory(name, old, mem.data(&self.store), pages_before, mem.size(&self.store))
+            })
+            .collect();
+
+        Ok(InvokeReport {
+            export: export.to_string(),
+            signature: signature(&ty),
+            args: args.iter().map(|a| a.to_string()).collect(),
+            results: if outcome == "ok" { results.iter().map(format_val).collect() } else { Vec::new() },
+            outcome,
+            exit_code,
+            message,
+            backtrace,
+            fuel_consumed: self.store.get_fuel().ok().map(|left| self.fuel - left),
+            elapsed_ms: started.elapsed().as_millis(),
+            memory,
+        })
+    }
+
+    fn memories(&mut self) -> Vec<(String, Memory)> {
+        self.instance
+            .exports(&mut self.store)
+            .filter_map(|e| {
+                let name = e.name().to_string();
+                e.into_memory().map(|m| (name, m))
+            })
+            .collect()
+    }
+}
+
+/// Parse command-line style arguments against a function's parameter types.
+pub fn parse_args(ty: &FuncType, args: &[&str]) -> Result<Vec<Val>> {
+    if ty.params().len() != args.len() {
+        return Err(anyhow!("expected {} argument(s), got {}", ty.params().len(), args.len()));
+    }
+    ty.params().zip(args).map(|(t, a)| parse_val(&t, a)).collect()
+}
+
+/// One argument: integers in decimal or `0x` hex (unsigned spellings wrap),
+/// floats including `nan`/`inf`, v128 as a 128-bit integer, refs only as `null`.
+pub fn parse_val(ty: &ValType, s: &str) -> Result<Val> {
+    let bad = || anyhow!("'{}' is not a valid {}", s, ty);
+    Ok(match ty {
+        ValType::I32 => Val::I32(parse_int(s, i32::MIN as i128, u32::MAX as i128).ok_or_else(bad)? as i32),
+        ValType::I64 => Val::I64(parse_int(s, i64::MIN as i128, u64::MAX as i128).ok_or_else(bad)? as i64),
+        ValType::F32 => Val::F32(s.parse::<f32>().map_err(|_| bad())?.to_bits()),
+        ValType::F64 => Val::F64(s.parse::<f64>().map_err(|_| bad())?.to_bits()),
+        ValType::V128 => {
+            let v = match s.strip_prefix("0x") {
+                Some(hex) => u128::from_str_radix(&hex.replace('_', ""), 16),
+                None => s.replace('_', "").parse::<u128>(),
+            };
+            Val::V128(V128::from(v.map_err(|_| bad())?))
+        }
+        ValType::Ref(r) if s == "null" && r.is_nullable() => Val::null_ref(r.heap_type()),
+        ValType::Ref(_) => return Err(anyhow!("only `null` can be passed for {}", ty)),
+    })
+}
+
+pub fn format_val(v: &Val) -> String {
+    match v {
+        Val::I32(n) => n.to_string(),
+        Val::I64(n) => n.to_string(),
+        Val::F32(bits) => f32::from_bits(*bits).to_string(),
+        Val::F64(bits) => f64::from_bits(*bits).to_string(),
+        Val::V128(v) => format!("0x{:032x}", v.as_u128()),
+        Val::FuncRef(None) | Val::ExternRef(None) | Val::AnyRef(None) => "null".to_string(),
+        Val::FuncRef(Some(_)) => "<funcref>".to_string(),
+        Val::ExternRef(Some(_)) => "<externref>".to_string(),
+        Val::AnyRef(Some(_)) => "<anyref>".to_string(),
+    }
+}
+
+pub fn signature(ty: &FuncType) -> String {
+    let params: Vec<String> = ty.params().map(|p| p.to_string()).collect();
+    let results: Vec<String> = ty.results().map(|r| r.to_string()).collect();
+    match results.len() {
+        0 => format!("({})", params.join(", ")),
+        1 => format!("({}) -> {}", params.join(", "), results[0]),
+        _ => format!("({}) -> ({})", params.join(", "), results.join(", ")),
+    }
+}
+
+pub fn invoke_section(r: &InvokeReport) -> Section {
+    let call = format!("{}({})", r.export, r.args.join(", "));
+    let out = Section::new(format!("Invoke {}", call));
+    let mut out = match r.outcome.as_str() {
+        "ok" => match r.results.len() {
+            0 => out.finding("ok", "returned"),
+            1 => out.finding("ok", format!("→ {}", r.results[0])),
+            _ => out.finding("ok", format!("→ ({})", r.results.join(", "))),
+        },
+        "exit" => out.finding("info", format!("exited with code {}", r.exit_code.unwrap_or(0))),
+        _ => out.finding_with(
+            "error",
+            format!("{}: {}", r.outcome, r.message.as_deref().unwrap_or("")),
+            r.backtrace.clone(),
+        ),
+    };
+    out = out.kv("signature", &r.signature);
+    if let Some(fuel) = r.fuel_consumed {
+        out = out.kv("fuel consumed", fuel);
+    }
+    out = out.kv("elapsed", format!("{} ms", r.elapsed_ms));
+    for m in &r.memory {
+        if m.pages_after != m.pages_before {
+            out = out.kv(format!("memory {}", m.memory), format!("grew {} → {} pages", m.pages_before, m.pages_after));
+        }
+        if m.changed_bytes > 0 {
+            out = out.kv(format!("memory {} changed", m.memory), format!("{} byte(s)", m.changed_bytes));
+            let rows = m
+                .changes
+                .iter()
+                .map(|c| vec![format!("0x{:x}", c.offset), c.len.to_string(), c.before.clone(), c.after.clone()])
+                .collect();
+            out = out.table(&["offset", "len", "before", "after"], rows);
+        }
+    }
+    out
+}
+
+/// Store state for core modules: WASI p1 context plus memory limits.
+struct CoreState {
+    wasi: WasiP1Ctx,
+    limits: StoreLimits,
+}
+
+/// Store state for components: WASI p2 context, its resource table and memory limits.
+struct ComponentState {
+    ctx: WasiCtx,
+    table: ResourceTable,
+    limits: StoreLimits,
+}
+
+impl IoView for ComponentState {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+}
+
+impl WasiView for ComponentState {
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.ctx
+    }
+}
+
+/// Instantiate and run a module (core or component) in a sandbox:
+/// WASI rooted at an empty temp dir, no network, fuel and wall-clock limits.
+pub fn smoke_run(bytes: &[u8], kind: &str, opts: SmokeOptions<'_>) -> Result<SmokeReport> {
+    let sandbox = tempfile::tempdir()?;
+    let stdout = MemoryOutputPipe::new(CAPTURE_LIMIT);
+    let stderr = MemoryOutputPipe::new(CAPTURE_LIMIT);
+
+    let mut builder = WasiCtxBuilder::new();
+    builder
+        .preopened_dir(sandbox.path(), ".", DirPerms::all(), FilePerms::all())?
+        .stdout(stdout.clone())
+        .stderr(stderr.clone())
+        .allow_tcp(false)
+        .allow_udp(false)
+        .allow_ip_name_lookup(false);
+
+    let engine = make_engine()?;
+    let started = Instant::now();
+    let _watchdog = spawn_watchdog(&engine, opts.timeout_ms);
+
+    let mut rep = if kind == "component" {
+        smoke_component(&engine, bytes, builder.build(), StoreLimits::default(), &opts)?
+    } else {
+        smoke_core(&engine, bytes, builder.build_p1(), &opts)?
+    };
+
+    rep.elapsed_ms = started.elapsed().as_millis();
+    rep.stdout = String::from_utf8_lossy(&stdout.contents()).into_owned();
+    rep.stderr = String::from_utf8_lossy(&stderr.contents()).into_owned();
+    Ok(rep)
+}
+
+/// `wasi:cli/run` (or a nullary `invoke`) of a component with the host
+/// environment from `opts`: real stdio, preopens, env and args.
+pub fn run_component(bytes: &[u8], invoke: Option<&str>, opts: InstanceOptions<'_>) -> Result<SmokeReport> {
+    let engine = make_engine()?;
+    let wasi = wasi_builder(&opts)?.build();
+    let started = Instant::now();
+    let _watchdog = spawn_watchdog(&engine, opts.timeout_ms);
+    let smoke = SmokeOptions { invoke, fuel: opts.fuel, timeout_ms: opts.timeout_ms };
+    let mut rep = smoke_component(&engine, bytes, wasi, store_limits(opts.max_memory), &smoke)?;
+    rep.elapsed_ms = started.elapsed().as_millis();
+    Ok(rep)
+}
+
+pub fn smoke_section(s: &SmokeReport) -> Section {
+    let mut out = Section::new("Smoke run")
+        .kv("entry", s.entry.as_deref().unwrap_or("<none>"))
+        .kv("outcome", &s.outcome);
+    if let Some(code) = s.exit_code {
+        out = out.kv("exit code", code);
+    }
+    if let Some(msg) = &s.message {
+        out = out.kv("message", msg);
+    }
+    if let Some(fuel) = s.fuel_consumed {
+        out = out.kv("fuel consumed", fuel);
+    }
+    out = out.kv("elapsed", format!("{} ms", s.elapsed_ms));
+    if !s.unresolved_imports.is_empty() {
+        out = out.finding_with("error", "unresolved imports", s.unresolved_imports.clone());
+    }
+    if !s.backtrace.is_empty() {
+        out = out.finding_with("info", "backtrace", s.backtrace.clone());
+    }
+    out
+}
+
+// ---------- helpers ----------
+
+fn make_engine() -> Result<Engine> {
+    let mut config = Config::new();
+    config.consume_fuel(true);
+    config.epoch_interruption(true);
+    config.wasm_backtrace(true);
+    config.wasm_component_model(true);
+    Engine::new(&config)
+}
+
+/// Inherited stdio plus the preopens, environment and arguments from `opts`.
+fn wasi_builder(opts: &InstanceOptions<'_>) -> Result<WasiCtxBuilder> {
+    let mut builder = WasiCtxBuilder::new();
+    builder.inherit_stdio().args(opts.args);
+    for dir in opts.dirs {
+        let (host, guest) = dir.split_once("::").unwrap_or((dir, dir));
+        builder
+            .preopened_dir(host, guest, DirPerms::all(), FilePerms::all())
+            .map_err(|e| anyhow!("--dir {}: {:#}", dir, e))?;
+    }
+    for var in opts.env {
+        match var.split_once('=') {
+            Some((k, v)) => builder.env(k, v),
+            None => builder.env(var, std::env::var(var).unwrap_or_default()),
+        };
+    }
+    Ok(builder)
+}
+
+fn store_limits(max_memory: Option<usize>) -> StoreLimits {
+    match max_memory {
+        Some(bytes) => StoreLimitsBuilder::new().memory_size(bytes).build(),
+        None => StoreLimits::default(),
+    }
+}
+
+/// Bump the engine epoch once the deadline passes; stores use a deadline of 1 tick.
+/// Dropping the returned sender cancels the watchdog.
+fn spawn_watchdog(engine: &Engine, timeout_ms: u64) -> mpsc::Sender<()> {
+    let (tx, rx) = mpsc::channel::<()>();
+    let engine = engine.clone();
+    std::thread::spawn(move || {
+        if let Err(mpsc::RecvTimeoutError::Timeout) = rx.recv_timeout(Duration::from_millis(timeout_ms)) {
+            engine.increment_epoch();
+        }
+    });
+    tx
+}
+
+fn empty_report(outcome: &str, entry: Option<String>) -> SmokeReport {
+    SmokeReport {
+        outcome: outcome.to_string(),
+        entry,
+        exit_code: None,
+        message: None,
+        backtrace: Vec::new(),
+        unresolved_imports: Vec::new(),
+        fuel_consumed: None,
+        elapsed_ms: 0,
+        stdout: String::new(),
+        stderr: String::new(),
+    }
+}
+
+fn smoke_core(engine: &Engine, bytes: &[u8], wasi: WasiP1Ctx, opts: &SmokeOptions<'_>) -> Result<SmokeReport> {
+    let module = match Module::new(engine, bytes) {
+        Ok(m) => m,
+        Err(e) => {
+            let mut rep = empty_report("error", None);
+            rep.message = Some(format!("compile failed: {:#}", e));
+            return Ok(rep);
+        }
+    };
+
+    let mut linker: Linker<WasiP1Ctx> = Linker::new(engine);
+    wasmtime_wasi::preview1::add_to_linker_sync(&mut linker, |t| t)?;
+
+    let mut store = Store::new(engine, wasi);
+    store.set_fuel(opts.fuel)?;
+    store.set_epoch_deadline(1);
+
+    let unresolved: Vec<String> = module
+        .imports()
+        .filter(|im| linker.get_by_import(&mut store, im).is_none())
+        .map(|im| format!("{}::{}", im.module(), im.name()))
+        .collect();
+    if !unresolved.is_empty() {
+        let mut rep = empty_report("unresolved-import", None);
+        rep.message = Some(format!("{} import(s) not provided by WASI preview1", unresolved.len()));
+        rep.unresolved_imports = unresolved;
+        return Ok(rep);
+    }
+
+    let entry = match opts.invoke {
+        Some(name) => Some(name.to_string()),
+        None if module.get_export("_start").is_some() => Some("_start".to_string()),
+        None => None,
+    };
+
+    let result = linker.instantiate(&mut store, &module).and_then(|instance| {
+        let Some(name) = entry.as_deref() else {
+            return Ok(());
+        };
+        // reactors must be initialized before any other export is called
+        if name != "_start"
+            && let Ok(init) = instance.get_typed_func::<(), ()>(&mut store, "_initialize")
+        {
+            init.call(&mut store, ())?;
+        }
+        let func = instance
+            .get_func(&mut store, name)
+            .ok_or_else(|| anyhow!("export '{}' not found or not a function", name))?;
+        let ty = func.ty(&store);
+        let params: Vec<Val> = ty
+            .params()
+            .map(|p| Val::default_for_ty(&p).ok_or_else(|| anyhow!("cannot default param of type {}", p)))
+            .collect::<Result<_>>()?;
+        let mut results: Vec<Val> = ty.results().map(|_| Val::I32(0)).collect();
+        func.call(&mut store, &params, &mut results)
+    });
+
+    let mut rep = match (result, entry) {
+        // instantiation (start function included) succeeded, but nothing was called
+        (Ok(()), None) => {
+            let mut rep = empty_report("no-entry", None);
+            rep.message = Some("no _start export; pass --invoke <export> to call one".to_string());
+            rep
+        }
+        (result, entry) => outcome_from(result, entry, opts.timeout_ms),
+    };
+    rep.fuel_consumed = store.get_fuel().ok().map(|left| opts.fuel - left);
+    Ok(rep)
+}
+
+fn smoke_component(
+    engine: &Engine,
+    bytes: &[u8],
+    wasi: WasiCtx,
+    limits: StoreLimits,
+    opts: &SmokeOptions<'_>,
+) -> Result<SmokeReport> {
+    let component = match Component::new(engine, bytes) {
+        Ok(c) => c,
+        Err(e) => {
+            let mut rep = empty_report("error", None);
+            rep.message = Some(format!("compile failed: {:#}", e));
+            return Ok(rep);
+        }
+    };
+
+    let mut linker: ComponentLinker<ComponentState> = ComponentLinker::new(engine);
+    wasmtime_wasi::add_to_linker_sync(&mut linker)?;
+
+    let mut store = Store::new(engine, ComponentState { ctx: wasi, table: ResourceTable::new(), limits });
+    store.limiter(|s| &mut s.limits);
+    store.set_fuel(opts.fuel)?;
+    store.set_epoch_deadline(1);
+
+    let pre = match linker.instantiate_pre(&component) {
+        Ok(p) => p,
+        Err(e) => {
+            let msg = format!("{:#}", e);
+            let mut rep = empty_report("unresolved-import", None);
+            rep.unresolved_imports = component
+                .component_type()
+                .imports(engine)
+                .map(|(name, _)| name.to_string())
+                .filter(|name| msg.contains(name.as_str()))
+                .collect();
+            rep.message = Some(msg);
+            return Ok(rep);
+        }
+    };
+
+    let (entry, result) = match opts.invoke {
+        Some(name) => {
+            let result = pre.instantiate(&mut store).and_then(|instance| {
+                let func = instance
+                    .get_func(&mut store, name)
+                    .ok_or_else(|| anyhow!("export '{}' not found or not a function", name))?;
+                if !func.params(&store).is_empty() {
+                    return Err(anyhow!("export '{}' takes parameters; smoke run only calls nullary exports", name));
+                }
+                let mut results = vec![wasmtime::component::Val::Bool(false); func.results(&store).len()];
+                func.call(&mut store, &[], &mut results)?;
+                func.post_return(&mut store)
+            });
+            (Some(name.to_string()), result)
+        }
+        None => {
+            let result = wasmtime_wasi::bindings::sync::Command::instantiate(&mut store, &component, &linker)
+                .and_then(|cmd| cmd.wasi_cli_run().call_run(&mut store))
+                .and_then(|r| r.map_err(|()| anyhow::Error::new(I32Exit(1))));
+            (Some("wasi:cli/run".to_string()), result)
+        }
+    };
+
+    let mut rep = outcome_from(result, entry, opts.timeout_ms);
+    rep.fuel_consumed = store.get_fuel().ok().map(|left| opts.fuel - left);
+    Ok(rep)
+}
+
+fn outcome_from(result: Result<()>, entry: Option<String>, timeout_ms: u64) -> SmokeReport {
+    match result {
+        Ok(()) => {
+            let mut rep = empty_report("success", entry);
+            rep.exit_code = Some(0);
+            rep
+        }
+        Err(e) => {
+            let mut rep = classify(e, timeout_ms);
+            rep.entry = entry;
+            rep
+        }
+    }
+}
+
+/// Outcome of a failed call: guest exit, trap (with backtrace) or host error.
+fn classify(err: anyhow::Error, timeout_ms: u64) -> SmokeReport {
+    if let Some(exit) = err.downcast_ref::<I32Exit>() {
+        let mut rep = empty_report(if exit.0 == 0 { "success" } else { "exit" }, None);
+        rep.exit_code = Some(exit.0);
+        return rep;
+    }
+
+    let backtrace = err
+        .downcast_ref::<WasmBacktrace>()
+        .map(|bt| {
+            bt.frames()
+                .iter()
+                .enumerate()
+                .map(|(i, f)| {
+                    let name = f.func_name().unwrap_or("<unnamed>");
+                    match f.module_offset() {
+                        Some(off) => format!("#{} func[{}] {} @ 0x{:x}", i, f.func_index(), name, off),
+                        None => format!("#{} func[{}] {}", i, f.func_index(), name),
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut rep = if let Some(trap) = err.downcast_ref::<Trap>() {
+        let mut rep = empty_report("trap", None);
+        rep.message = Some(if *trap == Trap::Interrupt {
+            format!("timeout: exceeded {} ms", timeout_ms)
+        } else {
+            trap.to_string()
+        });
+        rep
+    } else {
+        let mut rep = empty_report("error", None);
+        rep.message = Some(format!("{:#}", err));
+        rep
+    };
+    rep.backtrace = backtrace;
+    rep
+}
+
+/// `i128` in range, from decimal or `0x` hex, `_` separators allowed.
+fn parse_int(s: &str, min: i128, max: i128) -> Option<i128> {
+    let s = s.replace('_', "");
+    let (neg, digits) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(&s)),
+    };
+    let n = match digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        Some(hex) => i128::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<i128>().ok()?,
+    };
+    let n = if neg { -n } else { n };
+    (min..=max).contains(&n).then_some(n)
+}
+
+/// Changed ranges between two snapshots of one memory; `None` if nothing changed.
+fn diff_memory(name: &str, before: &[u8], after: &[u8], pages_before: u64, pages_after: u64) -> Option<MemoryDelta> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    let mut changed_bytes = 0;
+    for i in 0..before.len().max(after.len()) {
+        if before.get(i).copied().unwrap_or(0) == after.get(i).copied().unwrap_or(0) {
+            continue;
+        }
+        changed_bytes += 1;
+        match ranges.last_mut() {
+            // merge changes a few bytes apart into one range
+            Some((_, end)) if i <= *end + 4 => *end = i + 1,
+            _ => ranges.push((i, i + 1)),
+        }
+    }
+    if changed_bytes == 0 && pages_before == pages_after {
+        return None;
+    }
+    let hex = |mem: &[u8], start: usize, end: usize| {
+        let shown = end.min(start + DELTA_BYTES);
+        let mut out: Vec<String> = (start..shown).map(|i| format!("{:02x}", mem.get(i).copied().unwrap_or(0))).collect();
+        if end > shown {
+            out.push("…".to_string());
+        }
+        out.join(" ")
+    };
+    let changes = ranges
+        .iter()
+        .take(MAX_DELTA_RANGES)
+        .map(|&(start, end)| ByteChange {
+            offset: start,
+            len: end - start,
+            before: hex(before, start, end),
+            after: hex(after, start, end),
+        })
+        .collect();
+    Some(MemoryDelta { memory: name.to_string(), pages_before, pages_after, changed_bytes, changes })
+}
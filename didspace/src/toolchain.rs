@@ -1,6 +1,11 @@
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::time::{Duration, Instant};
 use serde::Serialize;
 
+use crate::report::Section;
+
 #[derive(Debug, Clone, Serialize)]
 pub enum CheckStatus {
     Ok,
@@ -17,6 +22,24 @@ pub struct Toolcheck {
     pub fix: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolVersion {
+    pub tool: &'static str,
+    pub path: Option<String>,
+    pub version: Option<String>,
+    /// First line of `--version` output
+    pub raw: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SdkInstall {
+    pub path: String,
+    pub version: Option<String>,
+    pub sysroot: Option<String>,
+    /// clang, clang++ and a sysroot with stdio.h are all present
+    pub complete: bool,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct ToolchainReport {
     pub ok: usize,
@@ -24,6 +47,10 @@ pub struct ToolchainReport {
     pub err: usize,
     pub skip: usize,
     pub checks: Vec<Toolcheck>,
+    pub tools: Vec<ToolVersion>,
+    pub rust_targets: Vec<String>,
+    pub sdks: Vec<SdkInstall>,
+    pub recommended_sdk: Option<String>,
 }
 
 impl ToolchainReport {
@@ -43,25 +70,52 @@ impl ToolchainReport {
         (ok, warn, err, skip)
     }
 
-    pub fn to_text(&self) -> String {
-        let mut out = String::new();
+    pub fn to_sections(&self) -> Vec<Section> {
         let (ok, warn, err, skip) = self.summary_counts();
-        out.push_str(&format!(
-            "Toolchain checks: ✅ {}  ⚠️ {}  ❌ {}  ⏭ {} \n",
-            ok, warn, err, skip
-        ));
-
+        let mut checks = Section::new("Toolchain checks")
+            .kv("summary", format!("✅ {}  ⚠️ {}  ❌ {}  ⏭ {}", ok, warn, err, skip));
         for c in &self.checks {
-            let icon = match c.status {
-                CheckStatus::Ok => "✅",
-                CheckStatus::Warn => "⚠️",
-                CheckStatus::Err => "❌",
-                CheckStatus::Skip => "⏭",
+            let level = match c.status {
+                CheckStatus::Ok => "ok",
+                CheckStatus::Warn => "warn",
+                CheckStatus::Err => "error",
+                CheckStatus::Skip => "info",
             };
-            out.push_str(&format!("{} {}: {}\n", icon, c.name, c.details));
-            if let Some(fix) = &c.fix {
-                out.push_str(&format!("   fix: {}\n", fix));
-            }
+            let fix = c.fix.iter().map(|f| format!("fix: {}", f)).collect();
+            checks = checks.finding_with(level, format!("{}: {}", c.name, c.details), fix);
+        }
+        let mut out = vec![checks];
+
+        if !self.tools.is_empty() {
+            let rows = self
+                .tools
+                .iter()
+                .map(|t| {
+                    vec![
+                        t.tool.to_string(),
+                        t.version.clone().unwrap_or_else(|| "-".to_string()),
+                        t.path.clone().unwrap_or_else(|| "(not found)".to_string()),
+                    ]
+                })
+                .collect();
+            out.push(Section::new("Tool versions").table(&["tool", "version", "path"], rows));
+        }
+
+        if !self.sdks.is_empty() {
+            let rows = self
+                .sdks
+                .iter()
+                .map(|sdk| {
+                    let recommended = self.recommended_sdk.as_deref() == Some(sdk.path.as_str());
+                    vec![
+                        sdk.path.clone(),
+                        sdk.version.clone().unwrap_or_else(|| "unknown".to_string()),
+                        if sdk.complete { "yes" } else { "no" }.to_string(),
+                        if recommended { "*" } else { "" }.to_string(),
+                    ]
+                })
+                .collect();
+            out.push(Section::new("wasi-sdk installs").table(&["path", "version", "complete", "recommended"], rows));
         }
         out
     }
@@ -72,8 +126,29 @@ pub struct ToolchainOptions<'a> {
     pub wasi_sysroot: Option<&'a str>,
     pub check_cpp: bool,
     pub check_wasmtime: bool,
+    /// Run `--version` on the tools and check installed rustup targets
+    pub detect_versions: bool,
+    /// Look for wasi-sdk installs under common prefixes
+    pub discover_sdks: bool,
+    /// Native C compiler and wasm2c runtime sources (for `wasm-c --build`)
+    pub native_cc: Option<&'a str>,
 }
 
+/// Tools whose version we report, with the executables tried in order.
+const VERSIONED_TOOLS: &[(&str, &[&str])] = &[
+    ("clang", &["wasm32-wasi-clang", "clang"]),
+    ("wasmtime", &["wasmtime"]),
+    ("wasm2c", &["wasm2c"]),
+    ("wasm-opt", &["wasm-opt"]),
+    ("wasm-tools", &["wasm-tools"]),
+    ("rustc", &["rustc"]),
+];
+
+const RUST_WASM_TARGETS: &[&str] = &["wasm32-wasip1", "wasm32-wasip2", "wasm32-unknown-unknown"];
+
+/// How long a `--version` / `rustup target list` probe may take before it is killed.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// Entry point
 pub fn toolchain_check(opts: ToolchainOptions<'_>) -> ToolchainReport {
     let mut checks: Vec<Toolcheck> = Vec::new();
@@ -224,7 +299,95 @@ pub fn toolchain_check(opts: ToolchainOptions<'_>) -> ToolchainReport {
         }
     }
 
-    let mut rep = ToolchainReport { ok:0, warn:0, err:0, skip:0, checks };
+    // 7) tool versions + rustup targets
+    let mut tools = Vec::new();
+    let mut rust_targets = Vec::new();
+    if opts.detect_versions {
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        tools = VERSIONED_TOOLS
+            .iter()
+            .map(|(tool, exes)| detect_tool_version(tool, exes, &path))
+            .collect();
+
+        match installed_rust_targets() {
+            Some(installed) => {
+                for target in RUST_WASM_TARGETS {
+                    if installed.iter().any(|t| t == target) {
+                        checks.push(Toolcheck {
+                            name: "rustup:target",
+                            status: CheckStatus::Ok,
+                            details: format!("{} installed", target),
+                            fix: None,
+                        });
+                    } else {
+                        checks.push(Toolcheck {
+                            name: "rustup:target",
+                            status: CheckStatus::Warn,
+                            details: format!("{} not installed", target),
+                            fix: Some(format!("rustup target add {}", target)),
+                        });
+                    }
+                }
+                rust_targets = installed
+                    .into_iter()
+                    .filter(|t| t.starts_with("wasm32"))
+                    .collect();
+            }
+            None => checks.push(Toolcheck {
+                name: "rustup:target",
+                status: CheckStatus::Skip,
+                details: "rustup not found in PATH".into(),
+                fix: None,
+            }),
+        }
+    }
+
+    // 8) wasi-sdk installs
+    let mut sdks = Vec::new();
+    let mut recommended_sdk = None;
+    if opts.discover_sdks {
+        sdks = discover_wasi_sdks();
+        recommended_sdk = recommend_sdk(&sdks).map(|s| s.path.clone());
+        match &recommended_sdk {
+            Some(p) => checks.push(Toolcheck {
+                name: "wasi-sdk",
+                status: CheckStatus::Ok,
+                details: format!("{} install(s) found, recommended: {}", sdks.len(), p),
+                fix: None,
+            }),
+            None if sdks.is_empty() => checks.push(Toolcheck {
+                name: "wasi-sdk",
+                status: CheckStatus::Warn,
+                details: "no wasi-sdk install found under common prefixes".into(),
+                fix: Some("install a wasi-sdk release into /opt/wasi-sdk or set WASI_SDK_PATH".into()),
+            }),
+            None => checks.push(Toolcheck {
+                name: "wasi-sdk",
+                status: CheckStatus::Warn,
+                details: format!("{} install(s) found, none complete", sdks.len()),
+                fix: Some("reinstall wasi-sdk (missing clang, clang++ or sysroot)".into()),
+            }),
+        }
+    }
+
+    // 9) native side of the wasm2c pipeline
+    if let Some(cc) = opts.native_cc {
+        checks.extend(native_checks(cc, find_wasm2c_runtime(None).as_deref()));
+    }
+
+    checks.extend(known_bad_combinations(&tools, &sdks, recommended_sdk.as_deref()));
+
+    let mut rep = ToolchainReport {
+        ok: 0,
+        warn: 0,
+        err: 0,
+        skip: 0,
+        checks,
+        tools,
+        rust_targets,
+        sdks,
+        recommended_sdk,
+    };
     //let mut ok = 0usize;
     //let mut warn = 0usize;
     //let mut err = 0usize;
@@ -262,9 +425,73 @@ fn detect_sysroot(cli: Option<&str>) -> Option<PathBuf> {
     None
 }
 
-fn which(cmd: &str) -> Option<PathBuf> {
-    let path = std::env::var_os("PATH")?;
-    for dir in std::env::split_paths(&path) {
+/// Native compiler + wasm2c runtime checks, shared with `wasm-c --build`.
+pub fn native_checks(cc: &str, runtime: Option<&Path>) -> Vec<Toolcheck> {
+    let mut checks = Vec::new();
+    let found = if cc.contains('/') { Some(PathBuf::from(cc)).filter(|p| is_executable(p)) } else { which(cc) };
+    match found {
+        Some(p) => checks.push(Toolcheck {
+            name: "native:cc",
+            status: CheckStatus::Ok,
+            details: format!("{} found at {}", cc, p.display()),
+            fix: None,
+        }),
+        None => checks.push(Toolcheck {
+            name: "native:cc",
+            status: CheckStatus::Err,
+            details: format!("native C compiler '{}' not found (needed to build wasm2c output)", cc),
+            fix: Some("install gcc or clang, or point CC / DIDSPACE_NATIVE_CC at one".into()),
+        }),
+    }
+    match runtime {
+        Some(dir) => checks.push(Toolcheck {
+            name: "wasm2c:runtime",
+            status: CheckStatus::Ok,
+            details: format!("wasm-rt-impl.c in {}", dir.display()),
+            fix: None,
+        }),
+        None => checks.push(Toolcheck {
+            name: "wasm2c:runtime",
+            status: CheckStatus::Warn,
+            details: "wasm2c runtime sources (wasm-rt-impl.c) not found".into(),
+            fix: Some("pass --wasm2c-runtime or set WASM2C_RUNTIME to wabt's wasm2c/ directory".into()),
+        }),
+    }
+    checks
+}
+
+/// Directory holding wasm-rt-impl.c: explicit, `$WASM2C_RUNTIME`, `$WABT_ROOT/wasm2c`,
+/// or next to the `wasm2c` binary (install prefix or wabt build tree).
+pub fn find_wasm2c_runtime(explicit: Option<&str>) -> Option<PathBuf> {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    if let Some(p) = explicit {
+        candidates.push(PathBuf::from(p));
+    }
+    if let Ok(p) = std::env::var("WASM2C_RUNTIME") {
+        candidates.push(PathBuf::from(p));
+    }
+    if let Ok(p) = std::env::var("WABT_ROOT") {
+        candidates.push(PathBuf::from(p).join("wasm2c"));
+    }
+    if let Some(bin) = which("wasm2c").and_then(|p| p.canonicalize().ok()) {
+        for up in bin.ancestors().skip(1).take(3) {
+            candidates.push(up.join("share").join("wabt").join("wasm2c"));
+            candidates.push(up.join("include"));
+            candidates.push(up.join("wasm2c"));
+        }
+    }
+    candidates.push(PathBuf::from("/usr/share/wabt/wasm2c"));
+    candidates.push(PathBuf::from("/usr/local/share/wabt/wasm2c"));
+    candidates.into_iter().find(|d| d.join("wasm-rt-impl.c").is_file())
+}
+
+pub fn which(cmd: &str) -> Option<PathBuf> {
+    which_in(cmd, &std::env::var_os("PATH")?)
+}
+
+/// `which` over an explicit search path (same syntax as `PATH`).
+fn which_in(cmd: &str, path: &OsStr) -> Option<PathBuf> {
+    for dir in std::env::split_paths(path) {
         let full = dir.join(cmd);
         if is_executable(&full) {
             return Some(full);
@@ -285,8 +512,287 @@ fn is_executable(p: &Path) -> bool {
     if !p.is_file() {
         return false;
     }
-    // su linux basta che esista; se vuoi puoi controllare permessi +x
-    true
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        p.metadata()
+            .map(|m| m.permissions().mode() & 0o111 != 0)
+            .unwrap_or(false)
+    }
+    #[cfg(not(unix))]
+    {
+        true
+    }
+}
+
+fn detect_tool_version(tool: &'static str, exes: &[&str], search_path: &OsStr) -> ToolVersion {
+    let Some(path) = exes.iter().find_map(|e| which_in(e, search_path)) else {
+        return ToolVersion { tool, path: None, version: None, raw: None };
+    };
+    let raw = version_line(&path);
+    ToolVersion {
+        tool,
+        path: Some(path.display().to_string()),
+        version: raw.as_deref().and_then(parse_version),
+        raw,
+    }
+}
+
+/// First non-empty line of `<exe> --version`.
+fn version_line(exe: &Path) -> Option<String> {
+    let mut cmd = Command::new(exe);
+    cmd.arg("--version");
+    let out = probe(cmd)?;
+    let text = if out.stdout.is_empty() { out.stderr } else { out.stdout };
+    String::from_utf8_lossy(&text)
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .map(str::to_string)
+}
+
+/// Run a probe with no stdin and kill it after `PROBE_TIMEOUT`, so a hanging
+/// wrapper cannot stall `doctor --check-toolchain`. `None` on spawn failure or timeout.
+fn probe(mut cmd: Command) -> Option<Output> {
+    let mut child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+    let deadline = Instant::now() + PROBE_TIMEOUT;
+    while child.try_wait().ok()?.is_none() {
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    child.wait_with_output().ok()
+}
+
+/// First token that starts with a digit, cut at the first char that isn't a digit or '.'.
+/// "clang version 18.1.2-wasi-sdk" → "18.1.2", "wasm-opt version 116 (version_116)" → "116".
+fn parse_version(line: &str) -> Option<String> {
+    line.split_whitespace()
+        .map(|tok| tok.trim_start_matches('v'))
+        .filter(|tok| tok.starts_with(|c: char| c.is_ascii_digit()))
+        .map(|tok| {
+            tok.chars()
+                .take_while(|c| c.is_ascii_digit() || *c == '.')
+                .collect::<String>()
+                .trim_end_matches('.')
+                .to_string()
+        })
+        .next()
+}
+
+/// "18.1.2" → [18, 1, 2]; missing components compare as 0.
+fn version_key(v: &str) -> Vec<u64> {
+    v.split('.').map(|p| p.parse().unwrap_or(0)).collect()
+}
+
+fn version_lt(v: &str, than: &str) -> bool {
+    let (mut a, mut b) = (version_key(v), version_key(than));
+    let n = a.len().max(b.len());
+    a.resize(n, 0);
+    b.resize(n, 0);
+    a < b
+}
+
+fn installed_rust_targets() -> Option<Vec<String>> {
+    let rustup = which("rustup")?;
+    let mut cmd = Command::new(rustup);
+    cmd.args(["target", "list", "--installed"]);
+    let out = probe(cmd)?;
+    if !out.status.success() {
+        return None;
+    }
+    Some(
+        String::from_utf8_lossy(&out.stdout)
+            .lines()
+            .map(|l| l.trim().to_string())
+            .filter(|l| !l.is_empty())
+            .collect(),
+    )
+}
+
+/// Candidate roots: $WASI_SDK_PATH, then `wasi-sdk*` dirs under the usual prefixes.
+fn sdk_candidates() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    if let Ok(sdk) = std::env::var("WASI_SDK_PATH")
+        && !sdk.trim().is_empty()
+    {
+        roots.push(PathBuf::from(sdk));
+    }
+
+    let mut prefixes = vec![PathBuf::from("/opt"), PathBuf::from("/usr/local"), PathBuf::from("/usr/lib")];
+    if let Some(home) = std::env::var_os("HOME") {
+        let home = PathBuf::from(home);
+        prefixes.push(home.join(".local"));
+        prefixes.push(home.join("opt"));
+        prefixes.push(home);
+    }
+
+    for prefix in prefixes {
+        let Ok(entries) = std::fs::read_dir(&prefix) else { continue };
+        let mut found: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| {
+                p.is_dir()
+                    && p.file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| n.starts_with("wasi-sdk"))
+            })
+            .collect();
+        found.sort();
+        roots.extend(found);
+    }
+
+    // the same install is often reachable through a symlink (/opt/wasi-sdk → wasi-sdk-24.0)
+    let mut seen = Vec::new();
+    roots.retain(|r| {
+        let canon = std::fs::canonicalize(r).unwrap_or_else(|_| r.clone());
+        if seen.contains(&canon) {
+            false
+        } else {
+            seen.push(canon);
+            true
+        }
+    });
+    roots
+}
+
+fn discover_wasi_sdks() -> Vec<SdkInstall> {
+    sdk_candidates()
+        .into_iter()
+        .filter(|root| root.join("bin").is_dir())
+        .map(|root| {
+            let sysroot = root.join("share").join("wasi-sysroot");
+            let has_sysroot = sysroot.is_dir();
+            let has_stdio = [
+                sysroot.join("include").join("stdio.h"),
+                sysroot.join("include").join("wasm32-wasi").join("stdio.h"),
+                sysroot.join("include").join("wasm32-wasip1").join("stdio.h"),
+            ]
+            .iter()
+            .any(|p| p.exists());
+            let clang = root.join("bin").join("clang");
+            let complete = is_executable(&clang) && is_executable(&root.join("bin").join("clang++")) && has_stdio;
+
+            SdkInstall {
+                version: sdk_version(&root),
+                path: root.display().to_string(),
+                sysroot: has_sysroot.then(|| sysroot.display().to_string()),
+                complete,
+            }
+        })
+        .collect()
+}
+
+/// wasi-sdk ≥ 22 ships a VERSION file ("24.0" on its first line); older
+/// installs only carry the version in the directory name (wasi-sdk-20.0).
+fn sdk_version(root: &Path) -> Option<String> {
+    if let Ok(text) = std::fs::read_to_string(root.join("VERSION"))
+        && let Some(v) = text.lines().next().and_then(parse_version)
+    {
+        return Some(v);
+    }
+    let name = root.file_name()?.to_str()?;
+    name.strip_prefix("wasi-sdk-")
+        .and_then(|rest| parse_version(&rest.replace('-', " ")))
+}
+
+/// Newest complete install wins; installs without a known version rank last.
+fn recommend_sdk(sdks: &[SdkInstall]) -> Option<&SdkInstall> {
+    sdks.iter()
+        .filter(|s| s.complete)
+        .max_by(|a, b| match (&a.version, &b.version) {
+            (Some(x), Some(y)) => version_key(x).cmp(&version_key(y)),
+            (Some(_), None) => std::cmp::Ordering::Greater,
+            (None, Some(_)) => std::cmp::Ordering::Less,
+            (None, None) => std::cmp::Ordering::Equal,
+        })
+}
+
+fn known_bad_combinations(tools: &[ToolVersion], sdks: &[SdkInstall], recommended: Option<&str>) -> Vec<Toolcheck> {
+    let version = |tool: &str| {
+        tools
+            .iter()
+            .find(|t| t.tool == tool)
+            .and_then(|t| t.version.clone())
+    };
+    let mut checks = Vec::new();
+
+    if let Some(rustc) = version("rustc") {
+        if version_lt(&rustc, "1.78") {
+            checks.push(Toolcheck {
+                name: "combo:rustc",
+                status: CheckStatus::Warn,
+                details: format!("rustc {} predates the wasm32-wasip1 target (added in 1.78)", rustc),
+                fix: Some("rustup update stable".into()),
+            });
+        }
+        if let Some(opt) = version("wasm-opt") {
+            // rustc 1.82 (LLVM 19) turns on reference-types and multivalue by default
+            if !version_lt(&rustc, "1.82") && version_lt(&opt, "117") {
+                checks.push(Toolcheck {
+                    name: "combo:rustc+wasm-opt",
+                    status: CheckStatus::Warn,
+                    details: format!(
+                        "wasm-opt {} cannot read reference-types/multivalue emitted by rustc {}",
+                        opt, rustc
+                    ),
+                    fix: Some("upgrade binaryen to ≥ 117 or pass --enable-reference-types --enable-multivalue".into()),
+                });
+            }
+        }
+    }
+
+    if let Some(wasmtime) = version("wasmtime")
+        && version_lt(&wasmtime, "17")
+    {
+        checks.push(Toolcheck {
+            name: "combo:wasmtime",
+            status: CheckStatus::Warn,
+            details: format!("wasmtime {} predates WASI 0.2.0; components built today will not run", wasmtime),
+            fix: Some("install wasmtime ≥ 17".into()),
+        });
+    }
+
+    if let Some(rec) = recommended.and_then(|p| sdks.iter().find(|s| s.path == p)) {
+        if let Some(sdk_ver) = &rec.version
+            && version_lt(sdk_ver, "22")
+        {
+            checks.push(Toolcheck {
+                name: "combo:wasi-sdk",
+                status: CheckStatus::Warn,
+                details: format!(
+                    "wasi-sdk {} only knows --target=wasm32-wasi (no wasm32-wasip1/wasip2 sysroot dirs)",
+                    sdk_ver
+                ),
+                fix: Some("install wasi-sdk ≥ 22".into()),
+            });
+        }
+        if let Some(clang) = version("clang")
+            && let Some(clang_path) = tools.iter().find(|t| t.tool == "clang").and_then(|t| t.path.clone())
+            && !Path::new(&clang_path).starts_with(&rec.path)
+        {
+            checks.push(Toolcheck {
+                name: "combo:clang+wasi-sdk",
+                status: CheckStatus::Warn,
+                details: format!(
+                    "clang {} in PATH ({}) is not from the recommended wasi-sdk; its builtins may not match the sysroot",
+                    clang, clang_path
+                ),
+                fix: Some(format!("export PATH={}/bin:$PATH", rec.path)),
+            });
+        }
+    }
+
+    checks
 }
 
 fn find_emulated_libs(sysroot: &Path) -> Vec<String> {
@@ -318,3 +824,117 @@ fn find_emulated_libs(sysroot: &Path) -> Vec<String> {
     found
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(tool: &'static str, version: &str, path: &str) -> ToolVersion {
+        ToolVersion { tool, path: Some(path.to_string()), version: Some(version.to_string()), raw: None }
+    }
+
+    fn sdk(path: &str, version: Option<&str>, complete: bool) -> SdkInstall {
+        SdkInstall { path: path.to_string(), version: version.map(str::to_string), sysroot: None, complete }
+    }
+
+    fn names(checks: &[Toolcheck]) -> Vec<&'static str> {
+        checks.iter().map(|c| c.name).collect()
+    }
+
+    #[test]
+    fn parse_version_takes_first_numeric_token() {
+        assert_eq!(parse_version("clang version 18.1.2-wasi-sdk").as_deref(), Some("18.1.2"));
+        assert_eq!(parse_version("wasm-opt version 116 (version_116)").as_deref(), Some("116"));
+        assert_eq!(parse_version("wasmtime 30.0.2 (398694a59 2025-02-20)").as_deref(), Some("30.0.2"));
+        assert_eq!(parse_version("rustc v1.84.0.").as_deref(), Some("1.84.0"));
+        assert_eq!(parse_version("no version here"), None);
+    }
+
+    #[test]
+    fn recommend_sdk_prefers_newest_complete() {
+        let sdks = [
+            sdk("/opt/wasi-sdk-20.0", Some("20.0"), true),
+            sdk("/opt/wasi-sdk-25.0", Some("25.0"), false),
+            sdk("/opt/wasi-sdk-24.0", Some("24.0"), true),
+            sdk("/opt/wasi-sdk", None, true),
+        ];
+        assert_eq!(recommend_sdk(&sdks).map(|s| s.path.as_str()), Some("/opt/wasi-sdk-24.0"));
+        assert_eq!(recommend_sdk(&sdks[3..]).map(|s| s.path.as_str()), Some("/opt/wasi-sdk"));
+        assert!(recommend_sdk(&sdks[1..2]).is_none());
+        // 10.0 > 9.0 numerically, not lexically
+        let sdks = [sdk("/a", Some("9.0"), true), sdk("/b", Some("10.0"), true)];
+        assert_eq!(recommend_sdk(&sdks).map(|s| s.path.as_str()), Some("/b"));
+    }
+
+    #[test]
+    fn known_bad_combinations_flags_old_tools() {
+        let tools = [
+            tool("rustc", "1.70.0", "/usr/bin/rustc"),
+            tool("wasmtime", "16.0.0", "/usr/bin/wasmtime"),
+            tool("clang", "18.1.2", "/usr/bin/clang"),
+        ];
+        let sdks = [sdk("/opt/wasi-sdk-20.0", Some("20.0"), true)];
+        let checks = known_bad_combinations(&tools, &sdks, Some("/opt/wasi-sdk-20.0"));
+        assert_eq!(names(&checks), ["combo:rustc", "combo:wasmtime", "combo:wasi-sdk", "combo:clang+wasi-sdk"]);
+
+        let tools = [tool("rustc", "1.84.0", "/usr/bin/rustc"), tool("wasm-opt", "116", "/usr/bin/wasm-opt")];
+        assert_eq!(names(&known_bad_combinations(&tools, &[], None)), ["combo:rustc+wasm-opt"]);
+    }
+
+    #[test]
+    fn known_bad_combinations_accepts_current_setup() {
+        let tools = [
+            tool("rustc", "1.84.0", "/usr/bin/rustc"),
+            tool("wasm-opt", "121", "/usr/bin/wasm-opt"),
+            tool("wasmtime", "30.0.2", "/usr/bin/wasmtime"),
+            tool("clang", "19.1.5", "/opt/wasi-sdk-25.0/bin/clang"),
+        ];
+        let sdks = [sdk("/opt/wasi-sdk-25.0", Some("25.0"), true)];
+        assert!(known_bad_combinations(&tools, &sdks, Some("/opt/wasi-sdk-25.0")).is_empty());
+    }
+
+    #[test]
+    fn known_bad_combinations_compares_whole_path_components() {
+        let tools = [tool("clang", "19.1.5", "/opt/wasi-sdk-20/bin/clang")];
+        let sdks = [sdk("/opt/wasi-sdk-2", Some("25.0"), true)];
+        let checks = known_bad_combinations(&tools, &sdks, Some("/opt/wasi-sdk-2"));
+        assert!(checks.iter().any(|c| c.name == "combo:clang+wasi-sdk"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn detect_tool_version_runs_fake_executables() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let script = |name: &str, body: &str| {
+            let p = dir.path().join(name);
+            std::fs::write(&p, format!("#!/bin/sh\n{}\n", body)).unwrap();
+            std::fs::set_permissions(&p, std::fs::Permissions::from_mode(0o755)).unwrap();
+        };
+        script("clang", "echo 'clang version 18.1.2-wasi-sdk'");
+        script("wasm2c", "echo '1.0.36' >&2");
+        // reads stdin: must see EOF instead of inheriting the test's stdin
+        script("wasm-tools", "cat >/dev/null; echo 'wasm-tools 1.243.0'");
+        script("wasm-opt", "exec sleep 60");
+
+        // only the fake tools are visible; the real PATH stays untouched
+        let path = dir.path().as_os_str();
+
+        let clang = detect_tool_version("clang", &["didspace-missing-wrapper", "clang"], path);
+        assert_eq!(clang.path, Some(dir.path().join("clang").display().to_string()));
+        assert_eq!(clang.version.as_deref(), Some("18.1.2"));
+        assert_eq!(clang.raw.as_deref(), Some("clang version 18.1.2-wasi-sdk"));
+
+        assert_eq!(detect_tool_version("wasm2c", &["wasm2c"], path).version.as_deref(), Some("1.0.36"));
+        assert_eq!(detect_tool_version("wasm-tools", &["wasm-tools"], path).version.as_deref(), Some("1.243.0"));
+
+        let started = Instant::now();
+        let hung = detect_tool_version("wasm-opt", &["wasm-opt"], path);
+        assert!(hung.path.is_some() && hung.version.is_none());
+        assert!(started.elapsed() < PROBE_TIMEOUT + Duration::from_secs(5));
+
+        let missing = detect_tool_version("wasmtime", &["didspace-missing-tool"], path);
+        assert!(missing.path.is_none() && missing.version.is_none());
+    }
+}
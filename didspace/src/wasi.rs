@@ -1,72 +1,75 @@
 use wasmparser::{Parser, Payload, ComponentType, ComponentValType, ComponentExternalKind};
 use anyhow::Result;
+use serde::Serialize;
 
-pub fn detect_wasi_imports(wasm_bytes: &[u8]) -> Result<(bool, String)> {
-    let mut parser = Parser::new(0);
-    let mut wasi_found = false;
-    let mut out = String::new();
-    out.push_str("WASI Imports\n");
-    out.push_str("===========\n");
+use crate::report::{Report, Section};
+
+/// Imports from `wasi*` modules, as `module::name`.
+pub fn detect_wasi_imports(wasm_bytes: &[u8]) -> Result<Vec<String>> {
+    let parser = Parser::new(0);
+    let mut found = Vec::new();
 
     for payload in parser.parse_all(wasm_bytes) {
-        match payload? {
-            Payload::ImportSection(imports) => {
-                for import in imports {
-                    let import = import?;
-                    if import.module.starts_with("wasi") {
-                        out.push_str(&format!(
-                            "Found WASI import: module='{}', name='{}'\n",
-                            import.module,
-                            import.name
-                        ));
-                        println!(
-                            "Found WASI import: module='{}', name='{}'",
-                            import.module, import.name
-                        );
-                        wasi_found = true;
-                    }
+        if let Payload::ImportSection(imports) = payload? {
+            for import in imports {
+                let import = import?;
+                if import.module.starts_with("wasi") {
+                    found.push(format!("{}::{}", import.module, import.name));
                 }
             }
-            _ => {}
         }
     }
-    
-    if !wasi_found {
-        out.push_str("No WASI imports found.\n");
-    }
-
-    Ok((wasi_found, out))
+    Ok(found)
 }
 
-pub fn detect_component_model(wasm_bytes: &[u8]) -> Result<bool, Box<dyn std::error::Error>> {
-    let mut parser = Parser::new(0);
-    let mut component_found = false;
+pub fn wasi_imports_section(imports: &[String]) -> Section {
+    Section::new(format!("WASI imports ({})", imports.len())).list(imports.to_vec())
+}
 
+/// True when the binary contains component-model sections.
+pub fn detect_component_model(wasm_bytes: &[u8]) -> Result<bool> {
+    let parser = Parser::new(0);
     for payload in parser.parse_all(wasm_bytes) {
-        match payload? {
-            Payload::ComponentSection { .. } => {
-                println!("Found Component Model section!");
-                component_found = true;
-            }
-            Payload::ComponentTypeSection(_) => println!("Found Component Type section"),
-            Payload::ComponentImportSection(_) => println!("Found Component Import section"),
-            Payload::ComponentExportSection(_) => println!("Found Component Export section"),
-            _ => {}
+        if matches!(
+            payload?,
+            Payload::ComponentSection { .. }
+                | Payload::ComponentTypeSection(_)
+                | Payload::ComponentImportSection(_)
+                | Payload::ComponentExportSection(_)
+        ) {
+            return Ok(true);
         }
     }
+    Ok(false)
+}
 
-    Ok(component_found)
+#[derive(Debug, Serialize)]
+pub struct ComponentAnalysis {
+    /// Function types as `func(a: U32) -> String`, other types as their debug form
+    pub types: Vec<String>,
+    pub imports: Vec<String>,
+    pub exports: Vec<String>,
+    pub func_count: usize,
+}
+
+impl ComponentAnalysis {
+    pub fn to_report(&self) -> Report {
+        Report::new("Component Analysis")
+            .section(Section::untitled().finding("ok", "Component Model detected").kv("functions", self.func_count))
+            .section(Section::new("Types").list(self.types.clone()))
+            .section(Section::new("Imports").list(self.imports.clone()))
+            .section(Section::new("Exports").list(self.exports.clone()))
+    }
 }
 
-pub fn analyze_component(wasm_bytes: &[u8]) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+pub fn analyze_component(wasm_bytes: &[u8]) -> Result<ComponentAnalysis> {
 
-    let mut parser = Parser::new(0);
+    let parser = Parser::new(0);
     let mut comp_types = Vec::new();
     let mut imports = Vec::new();
     let mut exports = Vec::new();
     let mut func_count = 0;
 
-    println!("Component Analysis:");
     for payload in parser.parse_all(wasm_bytes) {
         match payload? {
             // Component Type Section
@@ -104,12 +107,11 @@ pub fn analyze_component(wasm_bytes: &[u8]) -> Result<String, Box<dyn std::error
                                 }
                                 None => String::new(),
                             };
-                            comp_types.push(format!("  - func({}){}", params.join(", "), result));
+                            comp_types.push(format!("func({}){}", params.join(", "), result));
                             func_count += 1;
                         },
                         _ => {
-                            println!("  - other type: {:?}", ty);
-                            comp_types.push(format!("  - other type: {:?}", ty));
+                            comp_types.push(format!("other type: {:?}", ty));
                         }
                     }
                 }
@@ -139,34 +141,5 @@ pub fn analyze_component(wasm_bytes: &[u8]) -> Result<String, Box<dyn std::error
         }
     }
 
-    let mut out = String::new();
-    out.push_str("Component Analysis:\n");
-    out.push_str("✅ Component Model detected\n\n");
-    out.push_str("Types:\n");
-    println!("Component Analysis:");
-    println!("✅ Component Model detected");
-    println!("Types:");
-    for ctp in comp_types {
-        println!("  {}", ctp);
-        out.push_str(&format!("{}\n", ctp));
-    }
-    println!("Imports:");
-    out.push_str("\nImports:\n");
-    for imp in imports {
-        println!("  {}\n", imp);
-        out.push_str(&format!("  {}\n", imp));
-    }
-    println!("Exports:");
-    out.push_str("\nExports:\n");
-    for exp in exports {
-        println!("  {}", exp);
-        out.push_str(&format!("  {}\n", exp));
-    }
-    out.push_str("\nTypes Summary:\n");
-    out.push_str(&format!("  • Functions: {}\n", func_count));
-    println!("Types Summary:");
-    println!("  • Functions: {}", func_count);
-
-    Ok(out)
+    Ok(ComponentAnalysis { types: comp_types, imports, exports, func_count })
 }
-
@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::Result;
+
+use didspace::doctor::{doctor_report, DoctorOptions};
+use didspace::lint::lint_module;
+use didspace::report::Report;
+
+const POLL: Duration = Duration::from_millis(300);
+
+/// What one run produced: the report to show on the first run, the module to compare,
+/// and any extra files discovered while building (headers from depfiles).
+pub struct WatchStep {
+    pub report: Report,
+    pub wasm: String,
+    pub deps: Vec<PathBuf>,
+}
+
+/// The parts of a module the delta reports on.
+#[derive(Debug, Default)]
+pub struct Snapshot {
+    pub size: usize,
+    pub imports: Vec<String>,
+    pub exports: Vec<String>,
+    /// `[rule] message` for every lint finding
+    pub lint: Vec<String>,
+}
+
+pub fn snapshot(bytes: &[u8], lint_levels: &BTreeMap<String, String>) -> Result<Snapshot> {
+    let rep = doctor_report(
+        bytes,
+        DoctorOptions {
+            wasi_sysroot: None,
+            max_list: usize::MAX,
+            compat_table: None,
+            expect_eh: None,
+            module_path: None,
+        },
+    )?;
+    let (imports, exports) = match (rep.core, rep.component) {
+        (Some(c), _) => (c.imports, c.exports),
+        (None, Some(c)) => (c.imports, c.exports),
+        _ => (Vec::new(), Vec::new()),
+    };
+    let lint = lint_module(bytes, &rep.kind, lint_levels)?
+        .findings
+        .into_iter()
+        .map(|f| format!("[{}] {}", f.rule, f.message))
+        .collect();
+    Ok(Snapshot { size: bytes.len(), imports, exports, lint })
+}
+
+pub fn delta_to_text(prev: &Snapshot, cur: &Snapshot) -> String {
+    let mut out = String::new();
+    let diff = cur.size as i64 - prev.size as i64;
+    let pct = if prev.size > 0 { diff as f64 * 100.0 / prev.size as f64 } else { 0.0 };
+    out.push_str(&format!(
+        "  size:    {} → {} bytes ({:+} B, {:+.1}%)\n",
+        prev.size, cur.size, diff, pct
+    ));
+    for (label, before, after) in [
+        ("imports", &prev.imports, &cur.imports),
+        ("exports", &prev.exports, &cur.exports),
+        ("lint", &prev.lint, &cur.lint),
+    ] {
+        let added: Vec<&String> = after.iter().filter(|x| !before.contains(x)).collect();
+        let removed: Vec<&String> = before.iter().filter(|x| !after.contains(x)).collect();
+        for a in &added {
+            out.push_str(&format!("  {:<8} + {}\n", format!("{}:", label), a));
+        }
+        for r in &removed {
+            out.push_str(&format!("  {:<8} - {}\n", format!("{}:", label), r));
+        }
+    }
+    if prev.imports == cur.imports && prev.exports == cur.exports && prev.lint == cur.lint {
+        out.push_str("  imports, exports and lint findings unchanged\n");
+    }
+    out
+}
+
+/// Run `step` now and again whenever one of `inputs` (or a dependency it reports) changes.
+/// The first run prints the full report; later runs print the delta against the last
+/// successful one. Failures are printed and the loop keeps going until the process is
+/// interrupted.
+pub fn watch(
+    inputs: Vec<PathBuf>,
+    lint_levels: &BTreeMap<String, String>,
+    mut step: impl FnMut() -> Result<WatchStep>,
+) -> ! {
+    let mut watched = inputs.clone();
+    let mut last: Option<Snapshot> = None;
+    let mut run = 0;
+    loop {
+        run += 1;
+        let started = Instant::now();
+        let outcome = step().and_then(|s| {
+            let bytes = std::fs::read(&s.wasm)?;
+            Ok((snapshot(&bytes, lint_levels)?, s))
+        });
+        let took = started.elapsed().as_millis();
+        match outcome {
+            Ok((snap, s)) => {
+                match &last {
+                    None => {
+                        print!("{}", s.report.to_text());
+                        println!("👀 run {} ok in {} ms; {} bytes", run, took, snap.size);
+                    }
+                    Some(prev) => {
+                        println!("👀 run {} ok in {} ms ({})", run, took, s.wasm);
+                        print!("{}", delta_to_text(prev, &snap));
+                    }
+                }
+                watched = inputs.clone();
+                for d in s.deps {
+                    if !watched.contains(&d) {
+                        watched.push(d);
+                    }
+                }
+                last = Some(snap);
+            }
+            Err(e) => println!("👀 run {} failed in {} ms:\n{}", run, took, e),
+        }
+        println!("👀 watching {} file(s); Ctrl-C to stop", watched.len());
+        wait_for_change(&watched);
+    }
+}
+
+// ---------- helpers ----------
+
+fn mtimes(paths: &[PathBuf]) -> Vec<Option<SystemTime>> {
+    paths
+        .iter()
+        .map(|p| std::fs::metadata(p).and_then(|m| m.modified()).ok())
+        .collect()
+}
+
+/// Poll modification times; return once they changed and then held still for one interval
+/// (editors often write a file in several steps).
+fn wait_for_change(paths: &[PathBuf]) {
+    let before = mtimes(paths);
+    loop {
+        thread::sleep(POLL);
+        let now = mtimes(paths);
+        if now != before {
+            thread::sleep(POLL);
+            if mtimes(paths) == now {
+                return;
+            }
+        }
+    }
+}
@@ -5,11 +5,13 @@ use crate::wasmrt::WasmRuntime;
 use crate::memlayout::MemoryLayout;
 use crate::dwarfparser::DwarfParser;
 use crate::disasm::ModuleDisasm;
+use crate::differential::run_differential;
 
 pub struct WasmDebugger<'a> {
     runtime: WasmRuntime<'a>,
     dwarf: Rc<DwarfParser<'a>>,
     pub layout: Option<MemoryLayout>,
+    pub wasm_bytes: Option<Vec<u8>>, // für diff: frische Instanzen je Backend
     disasm: ModuleDisasm<'a>,
     breakpoints: HashSet<String>,
     current_pc: u64, // Aktuelle Instruktionsadresse
@@ -24,6 +26,7 @@ impl<'a> WasmDebugger<'a> {
             runtime,
             dwarf,
             layout: None,
+            wasm_bytes: None,
             disasm,
             breakpoints: HashSet::new(),
             current_pc: 0,
@@ -37,7 +40,7 @@ impl<'a> WasmDebugger<'a> {
         let mut rl: Editor::<(), DefaultHistory> = Editor::new()?;
         // Optional: rl.load_history("debuggerhistory.txt").ok();
         println!("/ Welcome to wasmdbg - your interactive WebAssembly Debugger!");
-        println!("Commands: break <func>, run, step, continue, print <var>, memdump, quit\n!");
+        println!("Commands: break <func>, run, step, continue, print <var>, memdump, diff <export> [args], quit\n!");
 
         use rustyline::{DefaultEditor, Cmd, Event, EventHandler};
         let mut rl = DefaultEditor::new()?;
@@ -138,6 +141,20 @@ impl<'a> WasmDebugger<'a> {
                     self.runtime.populate_symbols();
                     self.runtime.dump_symbols();
                 }
+                "diff" => {
+                    let Some(export) = args.get(1) else {
+                        println!("Syntax: diff <export> [args...]");
+                        continue;
+                    };
+                    let Some(bytes) = self.wasm_bytes.as_deref() else {
+                        println!("diff: keine Modul-Bytes geladen");
+                        continue;
+                    };
+                    match run_differential(bytes, export, &args[2..]) {
+                        Ok(report) => print!("{}", report),
+                        Err(e) => println!("diff Fehler: {}", e),
+                    }
+                }
                 "quit" | "exit" => {
                     println!("Exiting wasmdbg.");
                     break;
@@ -163,7 +180,7 @@ impl<'a> WasmDebugger<'a> {
                     }
                 }
                 _ => {
-                    println!("Unknown command. Try: break, run, step, continue, print, memdump, quit");
+                    println!("Unknown command. Try: break, run, step, continue, print, memdump, diff, quit");
                 }
             }
         }
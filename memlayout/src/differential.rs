@@ -0,0 +1,399 @@
+use std::fmt;
+
+use wasmer::{imports, Instance, Module, Store, Type, Value};
+
+use crate::errors::RuntimeError;
+use crate::wasmrt::{Backend, WasmRuntime};
+
+/// What one backend made of the call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Outcome {
+    Returned(Vec<String>),
+    /// Normalized trap kind, so backends that word the same trap differently still agree
+    Trap(String),
+    /// Compile, instantiation or argument error before the call ran
+    Failed(String),
+}
+
+#[derive(Debug)]
+pub struct BackendRun {
+    pub backend: String,
+    pub outcome: Outcome,
+    /// Exported globals after the call, in export order
+    pub globals: Vec<(String, String)>,
+    /// FNV-1a over every exported memory after the call
+    pub memory_hash: Option<u64>,
+    pub memory_bytes: usize,
+}
+
+#[derive(Debug)]
+pub struct DiffReport {
+    pub export: String,
+    pub args: Vec<String>,
+    pub runs: Vec<BackendRun>,
+    /// Differences against the first backend that executed; empty when all agree.
+    /// Backends that never got to run the call are not compared, only listed.
+    pub divergences: Vec<String>,
+}
+
+/// Run `export(args)` on a fresh instance under every compiled-in backend
+/// (and the wasmi interpreter with `--features wasmi`), then compare results,
+/// traps, exported globals and a hash of final linear memory.
+pub fn run_differential(wasm_bytes: &[u8], export: &str, args: &[&str]) -> Result<DiffReport, RuntimeError> {
+    let backends = Backend::available();
+    if backends.is_empty() && !cfg!(feature = "wasmi") {
+        return Err(RuntimeError::Unknown(
+            "no backend compiled in; build with --features cranelift,singlepass,llvm[,wasmi]".into(),
+        ));
+    }
+
+    let mut runs: Vec<BackendRun> = backends
+        .into_iter()
+        .map(|b| run_wasmer(b, wasm_bytes, export, args))
+        .collect();
+    #[cfg(feature = "wasmi")]
+    runs.push(run_wasmi(wasm_bytes, export, args));
+
+    let divergences = compare(&runs);
+    Ok(DiffReport {
+        export: export.to_string(),
+        args: args.iter().map(|a| a.to_string()).collect(),
+        runs,
+        divergences,
+    })
+}
+
+impl DiffReport {
+    /// Backends that got to run the call (returned or trapped)
+    pub fn executed(&self) -> impl Iterator<Item = &BackendRun> {
+        self.runs.iter().filter(|r| r.executed())
+    }
+
+    /// At least two backends executed, so there was something to compare
+    pub fn compared(&self) -> bool {
+        self.executed().count() >= 2
+    }
+
+    /// Backends were compared and none diverged; a single backend never agrees
+    pub fn agrees(&self) -> bool {
+        self.compared() && self.divergences.is_empty()
+    }
+}
+
+impl fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "🔀 {}({}) on {} backend(s)", self.export, self.args.join(", "), self.runs.len())?;
+        for run in &self.runs {
+            let outcome = match &run.outcome {
+                Outcome::Returned(vals) => format!("→ [{}]", vals.join(", ")),
+                Outcome::Trap(t) => format!("trap: {}", t),
+                Outcome::Failed(e) => format!("failed: {}", e),
+            };
+            let memory = match run.memory_hash {
+                Some(h) => format!("mem {} B #{:016x}", run.memory_bytes, h),
+                None => "no memory".to_string(),
+            };
+            writeln!(f, "   {:<22} {:<40} {}", run.backend, outcome, memory)?;
+        }
+        for run in self.runs.iter().filter(|r| !r.executed()) {
+            writeln!(f, "⚠️  could not run on {}", run.backend)?;
+        }
+        if !self.compared() {
+            writeln!(
+                f,
+                "⚠️  only {} of {} backend(s) executed; nothing was compared (build with more of --features cranelift,singlepass,llvm,wasmi)",
+                self.executed().count(),
+                self.runs.len()
+            )?;
+        } else if self.divergences.is_empty() {
+            writeln!(f, "✅ all backends agree")?;
+        } else {
+            writeln!(f, "❌ {} divergence(s):", self.divergences.len())?;
+            for d in &self.divergences {
+                writeln!(f, "   - {}", d)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn run_wasmer(backend: Backend, wasm_bytes: &[u8], export: &str, args: &[&str]) -> BackendRun {
+    let mut run = BackendRun::new(backend.name());
+    let mut store = Store::new(WasmRuntime::make_engine_for(backend));
+
+    let instance = match Module::new(&store, wasm_bytes)
+        .map_err(|e| e.to_string())
+        .and_then(|m| Instance::new(&mut store, &m, &imports! {}).map_err(|e| e.to_string()))
+    {
+        Ok(i) => i,
+        Err(e) => {
+            run.outcome = Outcome::Failed(e);
+            return run;
+        }
+    };
+    let func = match instance.exports.get_function(export) {
+        Ok(f) => f.clone(),
+        Err(e) => {
+            run.outcome = Outcome::Failed(e.to_string());
+            return run;
+        }
+    };
+    let params = match parse_params(func.ty(&store).params(), args) {
+        Ok(p) => p,
+        Err(e) => {
+            run.outcome = Outcome::Failed(e);
+            return run;
+        }
+    };
+
+    run.outcome = match func.call(&mut store, &params) {
+        Ok(vals) => Outcome::Returned(vals.iter().map(format_value).collect()),
+        Err(e) => Outcome::Trap(match e.clone().to_trap() {
+            Some(code) => normalize_trap(&format!("{:?}", code)),
+            None => e.message(),
+        }),
+    };
+
+    let mut memories = Vec::new();
+    for (name, ext) in instance.exports.iter() {
+        match ext {
+            wasmer::Extern::Global(g) => run.globals.push((name.to_string(), format_value(&g.get(&mut store)))),
+            wasmer::Extern::Memory(m) => memories.push(m.view(&store).copy_to_vec().unwrap_or_default()),
+            _ => {}
+        }
+    }
+    run.set_memory(&memories);
+    run
+}
+
+#[cfg(feature = "wasmi")]
+fn run_wasmi(wasm_bytes: &[u8], export: &str, args: &[&str]) -> BackendRun {
+    use wasmi::{Engine, Linker, Module, Store, Val};
+
+    let mut run = BackendRun::new("wasmi (interpreter)");
+    let engine = Engine::default();
+    let mut store = Store::new(&engine, ());
+    let linker = Linker::<()>::new(&engine);
+
+    let instance = match Module::new(&engine, wasm_bytes)
+        .and_then(|m| linker.instantiate_and_start(&mut store, &m))
+    {
+        Ok(i) => i,
+        Err(e) => {
+            run.outcome = Outcome::Failed(e.to_string());
+            return run;
+        }
+    };
+    let Some(func) = instance.get_func(&store, export) else {
+        run.outcome = Outcome::Failed(format!("Missing export {}", export));
+        return run;
+    };
+
+    let ty = func.ty(&store);
+    let params: Result<Vec<Val>, String> = if ty.params().len() != args.len() {
+        Err(format!("expected {} argument(s), got {}", ty.params().len(), args.len()))
+    } else {
+        ty.params()
+            .iter()
+            .zip(args)
+            .map(|(t, a)| {
+                let t = format!("{:?}", t).to_lowercase();
+                parse_value(&t, a).and_then(|v| match v {
+                    Value::I32(n) => Ok(Val::I32(n)),
+                    Value::I64(n) => Ok(Val::I64(n)),
+                    Value::F32(x) => Ok(Val::F32(x.into())),
+                    Value::F64(x) => Ok(Val::F64(x.into())),
+                    _ => Err(format!("arguments of type {} are not supported by wasmi", t)),
+                })
+            })
+            .collect()
+    };
+    let params = match params {
+        Ok(p) => p,
+        Err(e) => {
+            run.outcome = Outcome::Failed(e);
+            return run;
+        }
+    };
+
+    let mut results = vec![Val::I32(0); ty.results().len()];
+    run.outcome = match func.call(&mut store, &params, &mut results) {
+        Ok(()) => Outcome::Returned(results.iter().map(format_wasmi).collect()),
+        Err(e) => Outcome::Trap(match e.as_trap_code() {
+            Some(code) => normalize_trap(&format!("{:?}", code)),
+            None => e.to_string(),
+        }),
+    };
+
+    let mut memories = Vec::new();
+    for ex in instance.exports(&store) {
+        let name = ex.name().to_string();
+        if let Some(g) = ex.clone().into_global() {
+            run.globals.push((name, format_wasmi(&g.get(&store))));
+        } else if let Some(m) = ex.into_memory() {
+            memories.push(m.data(&store).to_vec());
+        }
+    }
+    run.set_memory(&memories);
+    run
+}
+
+impl BackendRun {
+    fn new(backend: &str) -> Self {
+        Self {
+            backend: backend.to_string(),
+            outcome: Outcome::Failed("not run".into()),
+            globals: Vec::new(),
+            memory_hash: None,
+            memory_bytes: 0,
+        }
+    }
+
+    /// The call itself ran, whatever it returned; `Failed` runs never got that far
+    pub fn executed(&self) -> bool {
+        !matches!(self.outcome, Outcome::Failed(_))
+    }
+
+    fn set_memory(&mut self, memories: &[Vec<u8>]) {
+        if memories.is_empty() {
+            return;
+        }
+        let mut hash = FNV_OFFSET;
+        for mem in memories {
+            hash = fnv1a(hash, mem);
+        }
+        self.memory_hash = Some(hash);
+        self.memory_bytes = memories.iter().map(Vec::len).sum();
+    }
+}
+
+/// Compare every executed run against the first executed one. A `Failed` run
+/// says nothing about the module's behaviour (singlepass lacking a proposal,
+/// say), so it is left out rather than counted as a divergence.
+fn compare(runs: &[BackendRun]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut executed = runs.iter().filter(|r| r.executed());
+    let Some(reference) = executed.next() else {
+        return out;
+    };
+    for run in executed {
+        let vs = format!("{} vs {}", run.backend, reference.backend);
+        if run.outcome != reference.outcome {
+            out.push(format!("{}: outcome {:?} ≠ {:?}", vs, run.outcome, reference.outcome));
+        }
+        for ((name, value), (_, ref_value)) in run.globals.iter().zip(&reference.globals) {
+            if value != ref_value {
+                out.push(format!("{}: global {} = {} ≠ {}", vs, name, value, ref_value));
+            }
+        }
+        if run.globals.len() != reference.globals.len() {
+            out.push(format!("{}: {} exported globals ≠ {}", vs, run.globals.len(), reference.globals.len()));
+        }
+        if run.memory_bytes != reference.memory_bytes {
+            out.push(format!("{}: final memory size {} B ≠ {} B", vs, run.memory_bytes, reference.memory_bytes));
+        } else if run.memory_hash != reference.memory_hash {
+            out.push(format!(
+                "{}: final memory contents differ (#{:016x} ≠ #{:016x})",
+                vs,
+                run.memory_hash.unwrap_or(0),
+                reference.memory_hash.unwrap_or(0)
+            ));
+        }
+    }
+    out
+}
+
+fn parse_params(types: &[Type], args: &[&str]) -> Result<Vec<Value>, String> {
+    if types.len() != args.len() {
+        return Err(format!("expected {} argument(s), got {}", types.len(), args.len()));
+    }
+    types
+        .iter()
+        .zip(args)
+        .map(|(t, a)| parse_value(&format!("{:?}", t).to_lowercase(), a))
+        .collect()
+}
+
+/// `ty` is the lowercase type name (`i32`, `f64`, ...). Integers take decimal
+/// or `0x` hex, and unsigned spellings wrap (`0xffffffff` is -1).
+fn parse_value(ty: &str, s: &str) -> Result<Value, String> {
+    let bad = || format!("'{}' is not a valid {}", s, ty);
+    let int = |s: &str| -> Option<i128> {
+        let (neg, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let n = match digits.strip_prefix("0x") {
+            Some(hex) => i128::from_str_radix(hex, 16).ok()?,
+            None => digits.parse::<i128>().ok()?,
+        };
+        Some(if neg { -n } else { n })
+    };
+    match ty {
+        "i32" => int(s)
+            .filter(|n| (i32::MIN as i128..=u32::MAX as i128).contains(n))
+            .map(|n| Value::I32(n as i32))
+            .ok_or_else(bad),
+        "i64" => int(s)
+            .filter(|n| (i64::MIN as i128..=u64::MAX as i128).contains(n))
+            .map(|n| Value::I64(n as i64))
+            .ok_or_else(bad),
+        "f32" => s.parse::<f32>().map(Value::F32).map_err(|_| bad()),
+        "f64" => s.parse::<f64>().map(Value::F64).map_err(|_| bad()),
+        "v128" => int(s).map(|n| Value::V128(n as u128)).ok_or_else(bad),
+        _ => Err(format!("arguments of type {} are not supported", ty)),
+    }
+}
+
+/// Floats keep their bits when NaN: NaN payloads are a classic backend difference.
+fn format_value(v: &Value) -> String {
+    match v {
+        Value::I32(n) => n.to_string(),
+        Value::I64(n) => n.to_string(),
+        Value::F32(x) if x.is_nan() => format!("nan:0x{:08x}", x.to_bits()),
+        Value::F32(x) => x.to_string(),
+        Value::F64(x) if x.is_nan() => format!("nan:0x{:016x}", x.to_bits()),
+        Value::F64(x) => x.to_string(),
+        Value::V128(n) => format!("0x{:032x}", n),
+        other => format!("{:?}", other),
+    }
+}
+
+#[cfg(feature = "wasmi")]
+fn format_wasmi(v: &wasmi::Val) -> String {
+    match v {
+        wasmi::Val::I32(n) => format_value(&Value::I32(*n)),
+        wasmi::Val::I64(n) => format_value(&Value::I64(*n)),
+        wasmi::Val::F32(x) => format_value(&Value::F32(f32::from_bits(x.to_bits()))),
+        wasmi::Val::F64(x) => format_value(&Value::F64(f64::from_bits(x.to_bits()))),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Map wasmer and wasmi trap codes onto the spec wording.
+fn normalize_trap(code: &str) -> String {
+    match code {
+        "HeapAccessOutOfBounds" | "MemoryOutOfBounds" => "out of bounds memory access",
+        "TableAccessOutOfBounds" | "TableOutOfBounds" => "undefined element",
+        "IndirectCallToNull" => "uninitialized element",
+        "BadSignature" => "indirect call type mismatch",
+        "IntegerOverflow" => "integer overflow",
+        "IntegerDivisionByZero" => "integer divide by zero",
+        "BadConversionToInteger" => "invalid conversion to integer",
+        "UnreachableCodeReached" => "unreachable",
+        "StackOverflow" => "call stack exhausted",
+        "HeapMisaligned" | "UnalignedAtomic" => "unaligned atomic",
+        other => other,
+    }
+    .to_string()
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
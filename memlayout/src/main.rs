@@ -14,8 +14,31 @@ mod debugger;
 use crate::debugger::WasmDebugger;
 mod disasm;
 use disasm::ModuleDisasm;
+mod differential;
+use differential::run_differential;
 
 fn main() -> Result<(), anyhow::Error> {
+    // Nicht-interaktiv: memlayout diff <file.wasm> <export> [args...]
+    let cli: Vec<String> = std::env::args().skip(1).collect();
+    if cli.first().map(String::as_str) == Some("diff") {
+        let (Some(file), Some(export)) = (cli.get(1), cli.get(2)) else {
+            anyhow::bail!("usage: memlayout diff <file.wasm> <export> [args...]");
+        };
+        let args: Vec<&str> = cli[3..].iter().map(String::as_str).collect();
+        let report = run_differential(&std::fs::read(file)?, export, &args)?;
+        print!("{}", report);
+        // 0 = alle ausgeführten Backends stimmen überein, 1 = Abweichung,
+        // 2 = weniger als zwei Backends ausgeführt, nichts verglichen
+        let code = if !report.compared() {
+            2
+        } else if report.agrees() {
+            0
+        } else {
+            1
+        };
+        std::process::exit(code);
+    }
+
     // 1. WASM-Datei laden
     let wasm_bytes = std::fs::read("app.wasm")?;
 
@@ -38,6 +61,7 @@ fn main() -> Result<(), anyhow::Error> {
 
     let mut dbg = WasmDebugger::new(runtime, dwarf_parser.clone(), disasm);
     dbg.layout = Some(layout); // V damit print <var> funktioniert
+    dbg.wasm_bytes = Some(wasm_bytes.clone());
     dbg.repl()?;
 
 /*
@@ -38,6 +38,37 @@ pub struct SymbolInfo {
     pub address: Option<u64>,
 }
 
+/// Compiler-Backends von wasmer; welche verfügbar sind, entscheiden die Cargo-Features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Cranelift,
+    Singlepass,
+    Llvm,
+}
+
+impl Backend {
+    /// Alle einkompilierten Backends, in der Reihenfolge, in der `make_engine` sie bevorzugt.
+    pub fn available() -> Vec<Backend> {
+        #[allow(unused_mut)]
+        let mut out = Vec::new();
+        #[cfg(feature = "cranelift")]
+        out.push(Backend::Cranelift);
+        #[cfg(feature = "singlepass")]
+        out.push(Backend::Singlepass);
+        #[cfg(feature = "llvm")]
+        out.push(Backend::Llvm);
+        out
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Cranelift => "cranelift",
+            Backend::Singlepass => "singlepass",
+            Backend::Llvm => "llvm",
+        }
+    }
+}
+
 pub struct WasmRuntime<'a> {
     store: RefCell<Store>,
     instance: Instance,
@@ -55,28 +86,24 @@ pub struct WasmRuntime<'a> {
 impl<'a> WasmRuntime<'a> {
 
     fn make_engine() -> Engine {
-        #[cfg(feature = "cranelift")]
-        {
-            let compiler = Cranelift::default();
-            let raw_engine = EngineBuilder::new(compiler).engine();
-            return Engine::from(raw_engine);
-        }
-
-        #[cfg(feature = "singlepass")]
-        {
-            let compiler = Singlepass::default();
-            let raw_engine = EngineBuilder::new(compiler).engine();
-            return Engine::from(raw_engine);
+        match Backend::available().first() {
+            Some(backend) => Self::make_engine_for(*backend),
+            None => panic!("Kein Backend aktiviert — benutze --features cranelift | singlepass | llvm"),
         }
+    }
 
-        // Fallback: wenn kein Feature gesetzt → Cranelift
-        #[cfg(feature = "llvm")]
-        {
-            let compiler = LLVM::default();
-            let raw_engine = EngineBuilder::new(compiler).engine();
-            return Engine::from(raw_engine);
+    /// Engine für ein bestimmtes Backend; nur mit den in `Backend::available()` gelisteten aufrufen.
+    pub fn make_engine_for(backend: Backend) -> Engine {
+        match backend {
+            #[cfg(feature = "cranelift")]
+            Backend::Cranelift => Engine::from(EngineBuilder::new(Cranelift::default()).engine()),
+            #[cfg(feature = "singlepass")]
+            Backend::Singlepass => Engine::from(EngineBuilder::new(Singlepass::default()).engine()),
+            #[cfg(feature = "llvm")]
+            Backend::Llvm => Engine::from(EngineBuilder::new(LLVM::default()).engine()),
+            #[allow(unreachable_patterns)]
+            other => panic!("Backend {} nicht aktiviert — benutze --features {}", other.name(), other.name()),
         }
-        panic!("Kein Backend aktiviert — benutze --features cranelift | singlepass | llvm");
     }
 
     pub fn init_store() -> Store {